@@ -0,0 +1,57 @@
+//! How long a job printing as fast as it can takes with and without a pane
+//! being drawn at the same time, and how long those frames take. Run with
+//! `cargo bench --bench render`.
+
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use itask::{App, JobConfig};
+use ratatui::{backend::TestBackend, Terminal};
+
+const LINES: &str = "500000";
+
+fn producer() -> JobConfig {
+    JobConfig::new("seq", &["1", LINES]).log_cap(100_000)
+}
+
+fn app() -> App<TestBackend> {
+    App::with_terminal(Terminal::new(TestBackend::new(160, 48)).unwrap())
+}
+
+/// Until every job has finished, drawing in between when `draw` is set.
+/// Gives the time taken and how long each frame took.
+fn run(app: &mut App<TestBackend>, draw: bool) -> (Duration, Vec<Duration>) {
+    let started = Instant::now();
+    let mut frames = vec![];
+    while app.model().running() > 0 {
+        match draw {
+            true => {
+                let frame = Instant::now();
+                app.draw().unwrap();
+                frames.push(frame.elapsed());
+            }
+            false => sleep(Duration::from_millis(1)),
+        }
+    }
+    (started.elapsed(), frames)
+}
+
+fn main() {
+    let mut quiet = app();
+    quiet.model().start_job(producer()).unwrap();
+    let (alone, _) = run(&mut quiet, false);
+    println!("{LINES} lines, not drawn: {alone:.2?}");
+
+    let mut drawn = app();
+    drawn.model().start_job(producer()).unwrap();
+    let (with_frames, mut frames) = run(&mut drawn, true);
+    frames.sort();
+    let mean = frames.iter().sum::<Duration>() / frames.len().max(1) as u32;
+    let worst = frames.last().copied().unwrap_or_default();
+    println!(
+        "{LINES} lines, drawn: {with_frames:.2?} over {} frames, {mean:.2?} mean, {worst:.2?} worst",
+        frames.len(),
+    );
+}
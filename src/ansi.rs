@@ -0,0 +1,301 @@
+use std::ops::Range;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+
+/// An OSC 8 hyperlink over `range` (bytes) of the parsed text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    pub range: Range<usize>,
+    pub url: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Parsed {
+    pub text: String,
+    pub links: Vec<Link>,
+    /// SGR colors and attributes over byte ranges of the text, for
+    /// [`styled`].
+    pub styles: Vec<(Range<usize>, Style)>,
+}
+
+/// Strips escape sequences out of `raw`, keeping the text OSC 8 hyperlinks
+/// wrap and where they point, and the colors SGR sequences set. Anything
+/// else (window titles, cursor movement and the like) means nothing inside
+/// a pane and is dropped. Colors don't carry over from one line to the next.
+pub fn parse(raw: &str) -> Parsed {
+    let mut parsed = Parsed::default();
+    let mut open: Option<(usize, String)> = None;
+    let mut style = (0, Style::new());
+    let mut rest = raw;
+
+    while let Some(start) = rest.find('\x1b') {
+        parsed.text.push_str(&rest[..start]);
+        let body = &rest[start + 1..];
+        let Some(body) = body.strip_prefix(']') else {
+            rest = match body.strip_prefix('[') {
+                Some(csi) => {
+                    let (params, end, next) = csi_parts(csi);
+                    if end == Some('m') {
+                        let at = parsed.text.len();
+                        let (from, current) = style;
+                        if from < at && current != Style::new() {
+                            parsed.styles.push((from..at, current));
+                        }
+                        style = (at, sgr(current, params));
+                    }
+                    next
+                }
+                // Some other escape: intermediates, then a final byte
+                None => {
+                    let mut chars = body
+                        .trim_start_matches(|c| ('\x20'..='\x2f').contains(&c))
+                        .chars();
+                    chars.next();
+                    chars.as_str()
+                }
+            };
+            continue;
+        };
+
+        // Terminated by BEL or ST (ESC \), or runs off the end of the line
+        let (end, skip) = match (body.find('\x07'), body.find("\x1b\\")) {
+            (Some(b), Some(s)) if s < b => (s, 2),
+            (Some(b), _) => (b, 1),
+            (None, Some(s)) => (s, 2),
+            (None, None) => (body.len(), 0),
+        };
+
+        if let Some(link) = body[..end].strip_prefix("8;") {
+            let url = link.split_once(';').map_or("", |(_, url)| url);
+            let at = parsed.text.len();
+            if let Some((from, url)) = open.take() {
+                parsed.links.push(Link {
+                    range: from..at,
+                    url,
+                });
+            }
+            if !url.is_empty() {
+                open = Some((at, url.to_string()));
+            }
+        }
+
+        rest = &body[end + skip..];
+    }
+    parsed.text.push_str(rest);
+
+    let (from, current) = style;
+    if from < parsed.text.len() && current != Style::new() {
+        parsed.styles.push((from..parsed.text.len(), current));
+    }
+
+    // A link left open runs to the end of the line
+    if let Some((from, url)) = open {
+        parsed.links.push(Link {
+            range: from..parsed.text.len(),
+            url,
+        });
+    }
+
+    parsed
+}
+
+/// Splits a CSI sequence (after `ESC [`) into its parameters, its final
+/// byte and what follows, or runs it off the end of the line if unfinished.
+fn csi_parts(csi: &str) -> (&str, Option<char>, &str) {
+    let params = csi
+        .find(|c: char| !('\x30'..='\x3f').contains(&c))
+        .unwrap_or(csi.len());
+    let end = csi[params..]
+        .find(|c: char| !('\x20'..='\x2f').contains(&c))
+        .map(|i| params + i);
+    match end.and_then(|i| Some((i, csi[i..].chars().next()?))) {
+        Some((i, c)) if ('\x40'..='\x7e').contains(&c) => (&csi[..params], Some(c), &csi[i + 1..]),
+        _ => (&csi[..params], None, ""),
+    }
+}
+
+/// Applies SGR parameters like `1;31` to `style`. Resets only take back what
+/// earlier codes set, so the pane's own styling shows through.
+fn sgr(mut style: Style, params: &str) -> Style {
+    let mut codes = params
+        .split([';', ':'])
+        .map(|p| p.parse::<u8>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        let mut modifier = |add: bool, modifier: Modifier| match add {
+            true => style.add_modifier.insert(modifier),
+            false => style.add_modifier.remove(modifier),
+        };
+        match code {
+            0 => style = Style::new(),
+            1 => modifier(true, Modifier::BOLD),
+            2 => modifier(true, Modifier::DIM),
+            3 => modifier(true, Modifier::ITALIC),
+            4 => modifier(true, Modifier::UNDERLINED),
+            5 | 6 => modifier(true, Modifier::SLOW_BLINK),
+            7 => modifier(true, Modifier::REVERSED),
+            8 => modifier(true, Modifier::HIDDEN),
+            9 => modifier(true, Modifier::CROSSED_OUT),
+            22 => modifier(false, Modifier::BOLD | Modifier::DIM),
+            23 => modifier(false, Modifier::ITALIC),
+            24 => modifier(false, Modifier::UNDERLINED),
+            25 => modifier(false, Modifier::SLOW_BLINK),
+            27 => modifier(false, Modifier::REVERSED),
+            28 => modifier(false, Modifier::HIDDEN),
+            29 => modifier(false, Modifier::CROSSED_OUT),
+            30..=37 => style.fg = Some(color(code - 30)),
+            38 => style.fg = extended(&mut codes).or(style.fg),
+            39 => style.fg = None,
+            40..=47 => style.bg = Some(color(code - 40)),
+            48 => style.bg = extended(&mut codes).or(style.bg),
+            49 => style.bg = None,
+            90..=97 => style.fg = Some(color(code - 90 + 8)),
+            100..=107 => style.bg = Some(color(code - 100 + 8)),
+            _ => {}
+        }
+    }
+    style
+}
+
+/// One of the 16 standard colors, bright ones from 8.
+fn color(n: u8) -> Color {
+    const COLORS: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ];
+    COLORS[n as usize % 16]
+}
+
+/// The rest of a `38` or `48`: `5;n` for the 256-color palette or `2;r;g;b`.
+fn extended(codes: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()?)),
+        2 => Some(Color::Rgb(codes.next()?, codes.next()?, codes.next()?)),
+        _ => None,
+    }
+}
+
+/// Splits `text` into spans, patching in each style over its byte range in
+/// order so later ones win where they overlap.
+pub fn styled<'a>(text: &str, styles: &[(Range<usize>, Style)]) -> Vec<Span<'a>> {
+    let mut cuts = vec![0, text.len()];
+    for (range, _) in styles {
+        cuts.extend([range.start.min(text.len()), range.end.min(text.len())]);
+    }
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let spans = cuts
+        .windows(2)
+        .filter(|w| text.is_char_boundary(w[0]) && text.is_char_boundary(w[1]))
+        .map(|w| {
+            let style = styles
+                .iter()
+                .filter(|(r, _)| r.start <= w[0] && w[1] <= r.end)
+                .fold(Style::new(), |style, (_, s)| style.patch(*s));
+            Span::styled(text[w[0]..w[1]].to_string(), style)
+        })
+        .collect::<Vec<_>>();
+
+    match spans.is_empty() {
+        true => vec![Span::raw(text.to_string())],
+        false => spans,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Stylize;
+
+    use super::*;
+
+    #[test]
+    fn strips_osc8_keeping_link_text() {
+        let raw = "see \x1b]8;;file:///src/main.rs\x1b\\src/main.rs\x1b]8;;\x1b\\ for details";
+        let parsed = parse(raw);
+        assert_eq!(parsed.text, "see src/main.rs for details");
+        assert_eq!(
+            parsed.links,
+            [Link {
+                range: 4..15,
+                url: "file:///src/main.rs".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn handles_bel_params_and_unclosed_links() {
+        let raw = "\x1b]8;id=1;https://a.example\x07a\x1b]8;;\x07 \x1b]8;;https://b.example\x07b";
+        let parsed = parse(raw);
+        assert_eq!(parsed.text, "a b");
+        assert_eq!(parsed.links[0].range, 0..1);
+        assert_eq!(parsed.links[1].range, 2..3);
+        assert_eq!(parsed.links[1].url, "https://b.example");
+    }
+
+    #[test]
+    fn drops_other_osc_sequences() {
+        assert_eq!(parse("\x1b]0;window title\x07done").text, "done");
+        assert_eq!(parse("plain").text, "plain");
+        assert_eq!(parse("cut \x1b]8;;http").text, "cut ");
+    }
+
+    #[test]
+    fn keeps_sgr_colors() {
+        let parsed = parse("\x1b[1m\x1b[32mCompiling\x1b[0m itask \x1b[38;5;208mv0.1\x1b[39m.");
+        assert_eq!(parsed.text, "Compiling itask v0.1.");
+        assert_eq!(
+            parsed.styles,
+            [
+                (0..9, Style::new().bold().green()),
+                (16..20, Style::new().fg(Color::Indexed(208))),
+            ]
+        );
+
+        let parsed = parse("\x1b[48;2;1;2;3;4mlast\x1b[24m");
+        assert_eq!(
+            parsed.styles,
+            [(0..4, Style::new().bg(Color::Rgb(1, 2, 3)).underlined())]
+        );
+    }
+
+    #[test]
+    fn strips_other_escapes() {
+        assert_eq!(parse("\x1b[2K\x1b[1Gdone").text, "done");
+        assert_eq!(parse("\x1b(Bplain\x1b=").text, "plain");
+        assert_eq!(parse("cut \x1b[38;5").text, "cut ");
+        assert_eq!(parse("end\x1b").text, "end");
+        assert!(parse("\x1b[31m\x1b[0m").styles.is_empty());
+    }
+
+    #[test]
+    fn later_styles_win() {
+        let spans = styled(
+            "hello world",
+            &[
+                (0..11, Style::new().underlined()),
+                (6..11, Style::new().red()),
+            ],
+        );
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1].content, "world");
+        assert_eq!(spans[1].style, Style::new().underlined().red());
+        assert_eq!(styled("", &[]).len(), 1);
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Stdout, Write},
+    path::Path,
+    process::Command,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use ratatui::{
+    backend::Backend,
+    crossterm::{
+        event::{
+            DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+            KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        },
+        execute,
+        terminal::{
+            disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+            LeaveAlternateScreen,
+        },
+    },
+    prelude::CrosstermBackend,
+    Terminal,
+};
+
+use crate::{clipboard::Clipboard, toml::Table, Model};
+
+/// Shortest gap between frames by default, about 30 a second.
+pub const FRAME: Duration = Duration::from_millis(33);
+/// How long to wait for input by default before checking on everything else.
+pub const POLL: Duration = Duration::from_millis(50);
+/// Longest gap between frames, even with nothing new to show.
+pub const TICK: Duration = Duration::from_secs(1);
+
+/// How the event loop is paced, from the config's `[pacing]` table:
+///
+/// ```toml
+/// [pacing]
+/// poll_ms = 100  # input latency, and how often the loop wakes when idle
+/// frame_ms = 66  # shortest gap between frames while output streams in
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pacing {
+    pub poll: Duration,
+    pub frame: Duration,
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Self {
+            poll: POLL,
+            frame: FRAME,
+        }
+    }
+}
+
+impl Pacing {
+    pub fn from_table(t: &Table) -> anyhow::Result<Self> {
+        let mut pacing = Self::default();
+        for (key, value) in t {
+            let duration = match key.as_str() {
+                "poll_ms" => &mut pacing.poll,
+                "frame_ms" => &mut pacing.frame,
+                _ => bail!("unknown pacing entry `{key}`"),
+            };
+            *duration = value
+                .as_integer()
+                .and_then(|ms| ms.try_into().ok())
+                .filter(|&ms| ms > 0)
+                .map(Duration::from_millis)
+                .with_context(|| format!("`{key}` must be a positive integer"))?;
+        }
+        Ok(pacing)
+    }
+}
+
+/// Owns the terminal and the model, and drives the event loop between them.
+pub struct App<B: Backend> {
+    terminal: Terminal<B>,
+    model: Arc<Model>,
+}
+
+/// Fails unless both stdin and stdout are terminals, the TUI needing both.
+fn interactive(stdin: bool, stdout: bool) -> anyhow::Result<()> {
+    if !stdin || !stdout {
+        bail!(
+            "no interactive terminal, stdin or stdout is piped or redirected \
+             (e.g. when running under CI), use `itask run <job>` to run a job \
+             without the TUI"
+        );
+    }
+    Ok(())
+}
+
+impl App<CrosstermBackend<Stdout>> {
+    /// Takes over the real terminal. Call [`App::restore`] once done with it.
+    pub fn new() -> anyhow::Result<Self> {
+        let mut stdout = io::stdout();
+        interactive(io::stdin().is_terminal(), stdout.is_terminal())?;
+
+        take_over(&mut stdout)?;
+        let terminal =
+            Terminal::new(CrosstermBackend::new(stdout)).context("creating terminal failed")?;
+
+        let model = Model {
+            clipboard: Clipboard::terminal(),
+            ..Default::default()
+        };
+        Ok(Self {
+            terminal,
+            model: Arc::new(model),
+        })
+    }
+
+    pub fn restore(&mut self) -> anyhow::Result<()> {
+        give_back(self.terminal.backend_mut())?;
+        self.terminal.show_cursor().context("unable to show cursor")
+    }
+}
+
+/// Raw mode, the alternate screen, the mouse and pastes, as the TUI wants
+/// the terminal.
+fn take_over(out: &mut impl Write) -> anyhow::Result<()> {
+    enable_raw_mode().context("failed to enable raw mode")?;
+    execute!(out, EnterAlternateScreen).context("unable to enter alternate screen")?;
+    execute!(out, EnableMouseCapture).context("unable to capture the mouse")?;
+    execute!(out, EnableBracketedPaste).context("unable to enable bracketed paste")?;
+    // Lets Ctrl+Enter be told apart from Enter where the terminal allows
+    if supports_keyboard_enhancement().unwrap_or(false) {
+        execute!(
+            out,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )
+        .context("unable to enable keyboard enhancements")?;
+    }
+    Ok(())
+}
+
+/// Undoes [`take_over`], leaving the terminal as the shell had it.
+fn give_back(out: &mut impl Write) -> anyhow::Result<()> {
+    if supports_keyboard_enhancement().unwrap_or(false) {
+        execute!(out, PopKeyboardEnhancementFlags)
+            .context("unable to disable keyboard enhancements")?;
+    }
+    execute!(out, DisableMouseCapture).context("unable to release the mouse")?;
+    execute!(out, DisableBracketedPaste).context("unable to disable bracketed paste")?;
+    disable_raw_mode().context("failed to disable raw mode")?;
+    execute!(out, LeaveAlternateScreen).context("unable to switch to main screen")
+}
+
+impl<B: Backend> App<B> {
+    pub fn with_terminal(terminal: Terminal<B>) -> Self {
+        Self {
+            terminal,
+            model: Arc::new(Model::default()),
+        }
+    }
+
+    pub fn model(&self) -> &Arc<Model> {
+        &self.model
+    }
+
+    pub fn terminal(&self) -> &Terminal<B> {
+        &self.terminal
+    }
+
+    /// Renders a single frame without touching input.
+    pub fn draw(&mut self) -> anyhow::Result<()> {
+        let model = self.model.clone();
+        self.terminal
+            .draw(|f| model.render(f))
+            .context("failed to draw frame")?;
+        Ok(())
+    }
+
+    /// Hands the terminal to `$PAGER`, `less` by default, on `path` until it
+    /// exits, then takes it back and redraws everything. Jobs keep running
+    /// meanwhile.
+    fn page(&mut self, path: &Path) -> anyhow::Result<()> {
+        let pager = env::var("PAGER")
+            .ok()
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| "less".to_string());
+        let mut words = pager.split_whitespace();
+        let program = words.next().unwrap_or("less");
+
+        give_back(&mut io::stdout())?;
+        let status = Command::new(program).args(words).arg(path).status();
+        take_over(&mut io::stdout())?;
+        self.terminal.clear().context("failed to clear terminal")?;
+
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => self.model.flash(format!("{program} exited with {status}")),
+            Err(e) => self.model.flash(format!("Couldn't run {program}: {e}")),
+        }
+        self.draw()
+    }
+
+    /// Polls input and redraws until the model asks to quit. Frames are only
+    /// drawn when something changed, at most [`Pacing::frame`] apart, plus
+    /// one every [`TICK`] to keep countdowns moving.
+    pub fn run(&mut self) -> anyhow::Result<()> {
+        let pacing = self.model.pacing();
+        self.model.restore_session()?;
+        self.draw()?;
+        let mut drawn = Instant::now();
+        loop {
+            self.model.keys(pacing.poll)?;
+            if self.model.quit() {
+                return self.model.save_session();
+            }
+            if let Some(path) = self.model.take_page() {
+                self.page(&path)?;
+                drawn = Instant::now();
+            }
+
+            let since = drawn.elapsed();
+            if since >= pacing.frame && (self.model.take_dirty() || since >= TICK) {
+                self.draw()?;
+                drawn = Instant::now();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn without_a_terminal_points_at_headless_mode() {
+        assert!(interactive(true, true).is_ok());
+        let err = interactive(false, true).unwrap_err().to_string();
+        assert!(err.starts_with("no interactive terminal"), "{err}");
+        assert!(err.ends_with("use `itask run <job>` to run a job without the TUI"));
+        assert!(interactive(true, false).is_err());
+    }
+}
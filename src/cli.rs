@@ -0,0 +1,186 @@
+use std::{path::PathBuf, process::ExitStatus};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    ui::{config_menu, main_menu},
+    Config,
+};
+
+pub const USAGE: &str = "\
+Usage: itask [options]
+       itask [--config <path>] run <job>
+
+Commands:
+  run <job>        Run the job named <job> without the TUI, its output on
+                   stdout, and exit with its exit code
+
+Options:
+  --config <path>  Read jobs from <path> instead of itask.toml, also given
+                   as --config=<path>
+  --run <job>      Start the job named <job> on launch, can be repeated
+  --list           Print the jobs and exit
+  --help           Print this and exit
+  --version        Print the version and exit
+
+Environment:
+  ITASK_SESSION    prompt or autostart, to bring back the jobs left running
+                   last time, asking first or not; overrides the config's
+                   `session`";
+
+/// What `itask` was asked to do on the command line.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Args {
+    pub config: PathBuf,
+    /// Whether `--config` was given, so the file has to be there. The
+    /// default `itask.toml` may be missing.
+    pub config_given: bool,
+    /// Names of jobs to start as the TUI opens, see [`Config::find`].
+    pub run: Vec<String>,
+    pub list: bool,
+    pub help: bool,
+    pub version: bool,
+    /// `itask run <job>`: the job to run in the foreground instead.
+    pub headless: Option<String>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            config: PathBuf::from("itask.toml"),
+            config_given: false,
+            run: vec![],
+            list: false,
+            help: false,
+            version: false,
+            headless: None,
+        }
+    }
+}
+
+impl Args {
+    /// Parses `args`, the program name already skipped. Values go after a
+    /// space or an `=`, as in `--config=ci.toml`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = |what: &str| {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .with_context(|| format!("`{flag}` needs {what}"))
+            };
+
+            match flag {
+                "--config" => {
+                    parsed.config = value("a path")?.into();
+                    parsed.config_given = true;
+                }
+                "--run" => parsed.run.push(value("a job name")?),
+                "--list" => parsed.list = true,
+                "--help" | "-h" => parsed.help = true,
+                "--version" | "-V" => parsed.version = true,
+                "run" if parsed.headless.is_none() => {
+                    parsed.headless = Some(args.next().context("`run` needs a job name")?)
+                }
+                _ => bail!("unknown argument `{arg}`, see --help"),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// What `itask run` exits with: the job's own code, or 128 plus the signal
+/// that killed it, as shells report it.
+pub fn exit_code(status: ExitStatus) -> u8 {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128u8.saturating_add(signal as u8);
+    }
+    status.code().map_or(1, |code| code as u8)
+}
+
+/// The jobs the menu would show, one per line and indented by section.
+pub fn job_list(config: Option<&Config>) -> String {
+    match config {
+        Some(config) => config_menu(config).tree(),
+        None => main_menu().tree(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Args> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_flags_and_values() {
+        assert_eq!(parse(&[]).unwrap(), Args::default());
+
+        let args = parse(&["--config", "ci.toml", "--run", "api", "--run=web", "--list"]).unwrap();
+        assert_eq!(args.config, PathBuf::from("ci.toml"));
+        assert!(args.config_given);
+        assert_eq!(args.run, ["api", "web"]);
+        assert!(args.list && !args.help);
+        assert_eq!(args.headless, None);
+
+        let args = parse(&["--config", "ci.toml", "run", "build"]).unwrap();
+        assert_eq!(args.headless.as_deref(), Some("build"));
+        assert_eq!(
+            parse(&["--config=a=b.toml"]).unwrap().config,
+            PathBuf::from("a=b.toml")
+        );
+        let args = parse(&["--config=ci.toml", "run", "build"]).unwrap();
+        assert_eq!(args.config, PathBuf::from("ci.toml"));
+        assert!(args.config_given);
+        assert_eq!(args.headless.as_deref(), Some("build"));
+        assert!(parse(&["--version"]).unwrap().version);
+    }
+
+    #[test]
+    fn reports_bad_arguments() {
+        let err = |args: &[&str]| parse(args).unwrap_err().to_string();
+        assert_eq!(err(&["--config"]), "`--config` needs a path");
+        assert_eq!(err(&["--run"]), "`--run` needs a job name");
+        assert_eq!(
+            err(&["--verbose"]),
+            "unknown argument `--verbose`, see --help"
+        );
+        assert_eq!(err(&["run"]), "`run` needs a job name");
+        assert_eq!(
+            err(&["run", "a", "run", "b"]),
+            "unknown argument `run`, see --help"
+        );
+    }
+
+    #[test]
+    fn passes_on_exit_codes() {
+        let status = |script| {
+            crate::JobConfig::new("sh", &["-c", script])
+                .run_attached()
+                .unwrap()
+        };
+        assert_eq!(exit_code(status("exit 0")), 0);
+        assert_eq!(exit_code(status("exit 3")), 3);
+        #[cfg(unix)]
+        assert_eq!(exit_code(status("kill -9 $$")), 137);
+    }
+
+    #[test]
+    fn lists_jobs_by_section() {
+        let config = Config::parse(
+            "[[job]]\nname = \"api\"\nsection = \"Run\"\ncommand = \"a\"\n\n\
+             [[job]]\ncommand = \"make\"\nargs = [\"all\"]\n",
+        )
+        .unwrap();
+        assert_eq!(job_list(Some(&config)), "Run\n  api\nmake all\n");
+    }
+}
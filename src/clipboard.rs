@@ -0,0 +1,108 @@
+use std::{
+    io::{self, Write},
+    process::{Command, ExitStatus, Stdio},
+    sync::RwLock,
+};
+
+use crate::lock::Recover;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+/// Programs that put what's piped into them on the system clipboard, tried
+/// in turn. Any missing or failing (`xclip` without `$DISPLAY`, say) is
+/// passed over.
+const PROGRAMS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+fn pipe_to(program: &str, args: &[&str], text: &str) -> io::Result<ExitStatus> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Closed once written, for the program to see the end of it
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    child.wait()
+}
+
+/// Where the model puts copied text. Only [`Clipboard::terminal`] sends it
+/// anywhere, the default just keeps it for [`Clipboard::last`], as tests and
+/// other backends want.
+#[derive(Default)]
+pub struct Clipboard {
+    terminal: bool,
+    last: RwLock<Option<String>>,
+}
+
+impl Clipboard {
+    /// Sends copies to the system clipboard through the first of
+    /// [`PROGRAMS`] that takes them. Failing that, they go to the terminal
+    /// itask's drawing to as the OSC 52 escape sequence, which the terminal
+    /// (or ssh/tmux in front of it) handles for us if it can.
+    pub fn terminal() -> Self {
+        Self {
+            terminal: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn copy(&self, text: &str) -> io::Result<()> {
+        *self.last.write_or_recover() = Some(text.to_string());
+        if self.terminal && !self.copy_with_program(text) {
+            let mut stdout = io::stdout();
+            write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+            stdout.flush()?;
+        }
+        Ok(())
+    }
+
+    fn copy_with_program(&self, text: &str) -> bool {
+        PROGRAMS.iter().any(|(program, args)| {
+            pipe_to(program, args, text).is_ok_and(|status| status.success())
+        })
+    }
+
+    /// What was copied most recently.
+    pub fn last(&self) -> Option<String> {
+        self.last.read_or_recover().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::base64;
+
+    #[test]
+    fn encodes_with_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64("café".as_bytes()), "Y2Fmw6k=");
+    }
+}
@@ -0,0 +1,49 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `at` as `HH:MM:SS.mmm` in local time, or UTC where the offset can't be
+/// looked up.
+pub fn time_of_day(at: SystemTime) -> String {
+    let since = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs() as i64;
+    hms(secs + utc_offset(secs), since.subsec_millis())
+}
+
+fn hms(secs: i64, millis: u32) -> String {
+    let secs = secs.rem_euclid(24 * 60 * 60);
+    format!(
+        "{:02}:{:02}:{:02}.{millis:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Seconds local time is ahead of UTC at `secs` past the epoch.
+#[cfg(unix)]
+fn utc_offset(secs: i64) -> i64 {
+    let t = secs as libc::time_t;
+    // Plain old data, filled in by `localtime_r`
+    let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+    match unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+        true => 0,
+        false => tm.tm_gmtoff as i64,
+    }
+}
+
+#[cfg(not(unix))]
+fn utc_offset(_: i64) -> i64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_the_time_of_day() {
+        assert_eq!(hms(3723, 4), "01:02:03.004");
+        assert_eq!(hms(86400 + 59, 999), "00:00:59.999");
+        assert_eq!(hms(-1, 0), "23:59:59.000");
+        assert_eq!(time_of_day(SystemTime::now()).len(), 12);
+    }
+}
@@ -0,0 +1,478 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+
+use crate::{job::JobConfig, toml, Hook, Keys, Pacing, PaneLayout, SessionRestore, Theme};
+
+/// Jobs defined in `itask.toml`, one `[[job]]` table each:
+///
+/// ```toml
+/// [[job]]
+/// name = "Sites (bin)"
+/// section = "Run (Server)"
+/// command = "cargo"
+/// args = ["run", "--bin", "sites"]
+/// cwd = "sites"
+/// env = { RUST_LOG = "debug" }
+/// log_dir = ".itask/logs"
+/// colors = [{ prefix = "WARN", color = "yellow" }, { prefix = "ERROR", color = "red" }]
+///
+/// [[job]]
+/// name = "Build (logged)"
+/// command = "cargo build 2>&1 | tee build.log"
+/// shell = true
+///
+/// [[group]]
+/// name = "Everything"
+/// jobs = ["Sites (bin)", "Build (logged)"]
+/// ```
+///
+/// Beyond what [`JobConfig::from_table`] reads, `section` groups jobs under
+/// a submenu, `icon` replaces the default menu icon and `autostart = true`
+/// runs the job on launch. Each `[[group]]` lists jobs by title to start
+/// together from the menu's Groups section, and each `[[hook]]` runs a
+/// script on job events, see [`Hook::from_table`]. Colours come from an
+/// optional `[theme]` table, see [`Theme`], and the event loop's timing from
+/// `[pacing]`, see [`Pacing`]. `[keys]` remaps key bindings, see [`Keys`],
+/// and `layout = "tabs"` starts in the tabbed [`PaneLayout`]. With
+/// `min_pane_width = 60`, split panes that would be narrower than that many
+/// columns give way to tabs until there's room again. `session = "prompt"`
+/// remembers which jobs were running when itask quit and offers to start
+/// them again next time, `"autostart"` starts them without asking, see
+/// [`SessionRestore`]. `ITASK_SESSION` set to either overrides it.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub jobs: Vec<MenuJob>,
+    pub groups: Vec<Group>,
+    pub hooks: Vec<Hook>,
+    pub theme: Theme,
+    pub pacing: Pacing,
+    pub keys: Keys,
+    pub layout: PaneLayout,
+    pub min_pane_width: Option<u16>,
+    pub session: Option<SessionRestore>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MenuJob {
+    pub section: Option<String>,
+    pub icon: Option<String>,
+    /// Started as soon as itask launches, see [`Model::autostart`](crate::Model::autostart).
+    pub autostart: bool,
+    pub job: JobConfig,
+}
+
+/// Jobs started together, see [`Model::start_group`](crate::Model::start_group).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    pub name: String,
+    /// Titles of jobs in the same config, see [`Config::find`].
+    pub jobs: Vec<String>,
+}
+
+impl MenuJob {
+    /// The menu entry's title: the job's name, or its command without one.
+    pub fn title(&self) -> String {
+        self.job
+            .name
+            .clone()
+            .unwrap_or_else(|| self.job.command_line())
+    }
+}
+
+impl Config {
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    /// The job whose menu entry is titled `name`.
+    pub fn find(&self, name: &str) -> Option<&MenuJob> {
+        self.jobs.iter().find(|entry| entry.title() == name)
+    }
+
+    /// `None` if there's no file at `path`, unless it's `required`.
+    pub fn load(path: impl AsRef<Path>, required: bool) -> anyhow::Result<Option<Self>> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(src) => {
+                let mut config = Self::parse(&src)
+                    .with_context(|| format!("failed to load {}", path.display()))?;
+                // `./` for a bare path, or it'd still be looked up on `PATH`
+                let dir = path
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."));
+                config.hooks = config
+                    .hooks
+                    .into_iter()
+                    .map(|hook| hook.relative_to(dir))
+                    .collect();
+                Ok(Some(config))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    pub fn parse(src: &str) -> anyhow::Result<Self> {
+        let table = toml::parse(src)?;
+        let jobs = table
+            .get("job")
+            .map(|jobs| jobs.as_array().context("`job` must be an array of tables"))
+            .transpose()?
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, job)| {
+                let parse = || -> anyhow::Result<MenuJob> {
+                    let t = job.as_table().context("`job` must be an array of tables")?;
+                    let text = |key: &str| {
+                        t.get(key)
+                            .map(|v| v.as_str().map(str::to_string))
+                            .map(|v| v.with_context(|| format!("`{key}` must be a string")))
+                            .transpose()
+                    };
+
+                    let autostart = t
+                        .get("autostart")
+                        .map(|v| v.as_bool().context("`autostart` must be a boolean"))
+                        .transpose()?;
+
+                    Ok(MenuJob {
+                        section: text("section")?,
+                        icon: text("icon")?,
+                        autostart: autostart.unwrap_or(false),
+                        job: JobConfig::from_table(t)?,
+                    })
+                };
+                parse().with_context(|| format!("in job {}", i + 1))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let groups = table
+            .get("group")
+            .map(|groups| {
+                groups
+                    .as_array()
+                    .context("`group` must be an array of tables")
+            })
+            .transpose()?
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, group)| {
+                let parse = || -> anyhow::Result<Group> {
+                    let t = group
+                        .as_table()
+                        .context("`group` must be an array of tables")?;
+                    let name = t
+                        .get("name")
+                        .context("group is missing `name`")?
+                        .as_str()
+                        .context("`name` must be a string")?;
+                    let names = t
+                        .get("jobs")
+                        .and_then(|v| v.as_array())
+                        .and_then(|jobs| {
+                            jobs.iter().map(|j| j.as_str()).collect::<Option<Vec<_>>>()
+                        })
+                        .context("`jobs` must be an array of job titles")?;
+                    if let Some(missing) = names
+                        .iter()
+                        .find(|name| !jobs.iter().any(|j: &MenuJob| j.title() == **name))
+                    {
+                        anyhow::bail!("no job named `{missing}`");
+                    }
+                    Ok(Group {
+                        name: name.to_string(),
+                        jobs: names.into_iter().map(str::to_string).collect(),
+                    })
+                };
+                parse().with_context(|| format!("in group {}", i + 1))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let hooks = table
+            .get("hook")
+            .map(|hooks| {
+                hooks
+                    .as_array()
+                    .context("`hook` must be an array of tables")
+            })
+            .transpose()?
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, hook)| {
+                hook.as_table()
+                    .context("`hook` must be an array of tables")
+                    .and_then(Hook::from_table)
+                    .with_context(|| format!("in hook {}", i + 1))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let theme = match table.get("theme") {
+            Some(theme) => Theme::from_table(theme.as_table().context("`theme` must be a table")?)?,
+            None => Theme::default(),
+        };
+        let pacing = match table.get("pacing") {
+            Some(pacing) => {
+                Pacing::from_table(pacing.as_table().context("`pacing` must be a table")?)?
+            }
+            None => Pacing::default(),
+        };
+        let keys = match table.get("keys") {
+            Some(keys) => Keys::from_table(keys.as_table().context("`keys` must be a table")?)?,
+            None => Keys::default(),
+        };
+        let layout = table
+            .get("layout")
+            .map(|v| {
+                v.as_str()
+                    .and_then(PaneLayout::from_name)
+                    .context("`layout` must be \"split\" or \"tabs\"")
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let min_pane_width = table
+            .get("min_pane_width")
+            .map(|v| {
+                v.as_integer()
+                    .and_then(|w| w.try_into().ok())
+                    .filter(|&w| w > 0)
+                    .context("`min_pane_width` must be a positive integer")
+            })
+            .transpose()?;
+        let session = table
+            .get("session")
+            .map(|v| {
+                v.as_str()
+                    .and_then(SessionRestore::from_name)
+                    .context("`session` must be \"prompt\" or \"autostart\"")
+            })
+            .transpose()?;
+
+        Ok(Self {
+            jobs,
+            groups,
+            hooks,
+            theme,
+            pacing,
+            keys,
+            layout,
+            min_pane_width,
+            session,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ratatui::style::{Style, Stylize};
+
+    use super::*;
+    use crate::HookTrigger;
+
+    #[test]
+    fn parses_jobs() {
+        let config = Config::parse(
+            r#"
+            [[job]]
+            name = "Sites (bin)"
+            section = "Run (Server)"
+            icon = "🦀"
+            command = "cargo"
+            args = ["run", "--bin", "sites"]
+            cwd = "sites"
+            env = { RUST_LOG = "debug" }
+
+            [[job]]
+            command = "npm"
+            args = ["run", "dev"]
+            autostart = true
+            "#,
+        )
+        .unwrap();
+
+        let [sites, npm] = &config.jobs[..] else {
+            panic!("expected two jobs, got {:?}", config.jobs);
+        };
+        assert_eq!(sites.title(), "Sites (bin)");
+        assert_eq!(sites.section.as_deref(), Some("Run (Server)"));
+        assert_eq!(sites.job.cwd.as_deref(), Some(Path::new("sites")));
+        assert_eq!(sites.job.env["RUST_LOG"], "debug");
+        assert_eq!(npm.title(), "npm run dev");
+        assert_eq!(npm.section, None);
+        assert!(npm.autostart && !sites.autostart);
+    }
+
+    #[test]
+    fn reports_which_job_is_wrong() {
+        let err = Config::parse("[[job]]\ncommand = \"a\"\n\n[[job]]\nargs = []\n").unwrap_err();
+        assert_eq!(format!("{err:#}"), "in job 2: job is missing `command`");
+
+        assert!(Config::parse("job = 1").is_err());
+        assert!(Config::parse("").unwrap().jobs.is_empty());
+    }
+
+    #[test]
+    fn groups_name_jobs_by_title() {
+        let jobs = "[[job]]\nname = \"wasm\"\ncommand = \"a\"\n\n[[job]]\ncommand = \"npm\"\nargs = [\"run\", \"dev\"]\n\n";
+        let config = Config::parse(&format!(
+            "{jobs}[[group]]\nname = \"frontend\"\njobs = [\"wasm\", \"npm run dev\"]\n"
+        ))
+        .unwrap();
+        assert_eq!(
+            config.group("frontend"),
+            Some(&Group {
+                name: "frontend".into(),
+                jobs: vec!["wasm".into(), "npm run dev".into()],
+            })
+        );
+
+        let err = |group| {
+            format!(
+                "{:#}",
+                Config::parse(&format!("{jobs}{group}")).unwrap_err()
+            )
+        };
+        assert_eq!(
+            err("[[group]]\nname = \"x\"\njobs = [\"web\"]\n"),
+            "in group 1: no job named `web`"
+        );
+        assert_eq!(
+            err("[[group]]\njobs = []\n"),
+            "in group 1: group is missing `name`"
+        );
+    }
+
+    #[test]
+    fn reads_the_theme() {
+        let config = Config::parse("[theme]\nerror = \"magenta\"\n").unwrap();
+        assert_eq!(config.theme.error, Style::new().magenta());
+        assert_eq!(config.theme.selected, Theme::default().selected);
+        assert!(Config::parse("theme = 1").is_err());
+    }
+
+    #[test]
+    fn reads_the_layout() {
+        assert_eq!(Config::parse("").unwrap().layout, PaneLayout::Split);
+        assert_eq!(
+            Config::parse("layout = \"tabs\"").unwrap().layout,
+            PaneLayout::Tabs
+        );
+        let err = Config::parse("layout = \"grid\"").unwrap_err();
+        assert_eq!(err.to_string(), "`layout` must be \"split\" or \"tabs\"");
+    }
+
+    #[test]
+    fn reads_hooks() {
+        let config = Config::parse(
+            r#"
+            [[hook]]
+            script = "notify.sh"
+            on = "failure"
+
+            [[hook]]
+            script = "page.sh"
+            on = "match"
+            pattern = "panicked"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.hooks.len(), 2);
+        assert_eq!(config.hooks[0].script, Path::new("notify.sh"));
+        assert!(matches!(config.hooks[0].on, HookTrigger::Failure));
+        assert!(matches!(&config.hooks[1].on, HookTrigger::Match(p) if p == "panicked"));
+
+        let err = |src| format!("{:#}", Config::parse(src).unwrap_err());
+        assert_eq!(
+            err("[[hook]]\nscript = \"a.sh\"\non = \"match\"\n"),
+            "in hook 1: `match` hooks need a `pattern` string"
+        );
+        assert_eq!(
+            err("[[hook]]\nscript = \"a.sh\"\non = \"line\"\n"),
+            "in hook 1: `on` must be start, exit, failure or match"
+        );
+        assert_eq!(
+            err("[[hook]]\non = \"exit\"\n"),
+            "in hook 1: `script` must be a string"
+        );
+    }
+
+    #[test]
+    fn hook_scripts_are_found_next_to_the_config() {
+        let dir = std::env::temp_dir().join(format!("itask-hook-dir-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("itask.toml");
+        fs::write(
+            &path,
+            "[[hook]]\nscript = \"notify.sh\"\non = \"exit\"\n\n\
+             [[hook]]\nscript = \"/bin/true\"\non = \"exit\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&path, true).unwrap().unwrap();
+        assert_eq!(config.hooks[0].script, dir.join("notify.sh"));
+        assert_eq!(config.hooks[1].script, Path::new("/bin/true"));
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn only_a_required_config_has_to_exist() {
+        let path = std::env::temp_dir().join(format!("itask-missing-{}.toml", std::process::id()));
+        assert!(Config::load(&path, false).unwrap().is_none());
+        let err = Config::load(&path, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("failed to read {}", path.display())
+        );
+    }
+
+    #[test]
+    fn reads_the_min_pane_width() {
+        assert_eq!(Config::parse("").unwrap().min_pane_width, None);
+        assert_eq!(
+            Config::parse("min_pane_width = 60").unwrap().min_pane_width,
+            Some(60)
+        );
+        for bad in ["0", "-1", "\"wide\"", "70000"] {
+            let err = Config::parse(&format!("min_pane_width = {bad}")).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "`min_pane_width` must be a positive integer"
+            );
+        }
+    }
+
+    #[test]
+    fn reads_the_session() {
+        assert_eq!(Config::parse("").unwrap().session, None);
+        assert_eq!(
+            Config::parse("session = \"prompt\"").unwrap().session,
+            Some(SessionRestore::Prompt)
+        );
+        assert_eq!(
+            Config::parse("session = \"autostart\"").unwrap().session,
+            Some(SessionRestore::Autostart)
+        );
+        let err = Config::parse("session = true").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`session` must be \"prompt\" or \"autostart\""
+        );
+    }
+
+    #[test]
+    fn reads_the_pacing() {
+        let config = Config::parse("[pacing]\npoll_ms = 200\n").unwrap();
+        assert_eq!(config.pacing.poll, Duration::from_millis(200));
+        assert_eq!(config.pacing.frame, Pacing::default().frame);
+
+        let err = |src| format!("{:#}", Config::parse(src).unwrap_err());
+        assert_eq!(
+            err("[pacing]\nframe_ms = 0\n"),
+            "`frame_ms` must be a positive integer"
+        );
+        assert_eq!(err("[pacing]\ntick = 1\n"), "unknown pacing entry `tick`");
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::BTreeMap,
+    process::{Command, Stdio},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::bail;
+
+use crate::{hooks::JobEvent, lock::Recover};
+
+/// How a named job tells the jobs waiting on it that they can start.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Readiness {
+    /// Exited successfully, the way a build step finishes.
+    #[default]
+    Exit,
+    /// Printed a line containing the given text, e.g. `Listening on`.
+    Line(String),
+    /// The given command exits zero. Polled while the job runs.
+    Command { program: String, args: Vec<String> },
+}
+
+impl Readiness {
+    pub fn command(program: &str, args: &[&str]) -> Self {
+        Self::Command {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    /// Whether `event` shows the job is ready. Command checks never come
+    /// through events, see [`Readiness::check`].
+    pub fn satisfied_by(&self, event: &JobEvent) -> bool {
+        match (self, event) {
+            (Readiness::Exit, JobEvent::Exit(status)) => status.success(),
+            (Readiness::Line(pattern), JobEvent::Line(line)) => line.contains(pattern),
+            _ => false,
+        }
+    }
+
+    /// Runs the check command once, quietly.
+    pub fn check(&self) -> bool {
+        let Readiness::Command { program, args } = self else {
+            return false;
+        };
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    after: Vec<String>,
+    ready: bool,
+}
+
+/// Named jobs, what each waits on, and which are ready.
+#[derive(Clone, Default)]
+pub struct Graph(Arc<RwLock<BTreeMap<String, Node>>>);
+
+impl Graph {
+    /// Records a job starting, refusing any that would close a cycle and so
+    /// wait forever.
+    pub fn add(&self, name: &str, after: &[String]) -> anyhow::Result<()> {
+        let mut nodes = self.0.write_or_recover();
+
+        let mut stack = after.to_vec();
+        let mut seen = vec![];
+        while let Some(dep) = stack.pop() {
+            if dep == name {
+                bail!("`{name}` would end up waiting on itself");
+            }
+            if let Some(node) = nodes.get(&dep).filter(|_| !seen.contains(&dep)) {
+                stack.extend(node.after.iter().cloned());
+            }
+            seen.push(dep);
+        }
+
+        nodes.insert(
+            name.to_string(),
+            Node {
+                after: after.to_vec(),
+                ready: false,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn set_ready(&self, name: &str) {
+        if let Some(node) = self.0.write_or_recover().get_mut(name) {
+            node.ready = true;
+        }
+    }
+
+    /// The jobs in `after` that aren't ready yet, including any that haven't
+    /// been started at all.
+    pub fn waiting_on(&self, after: &[String]) -> Vec<String> {
+        let nodes = self.0.read_or_recover();
+        after
+            .iter()
+            .filter(|dep| !nodes.get(*dep).is_some_and(|n| n.ready))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn waits_until_every_dependency_is_ready() {
+        let graph = Graph::default();
+        let after = names(&["backend", "db"]);
+        assert_eq!(graph.waiting_on(&after), after);
+
+        graph.add("backend", &[]).unwrap();
+        graph.add("db", &[]).unwrap();
+        graph.set_ready("db");
+        assert_eq!(graph.waiting_on(&after), ["backend"]);
+
+        graph.set_ready("backend");
+        assert!(graph.waiting_on(&after).is_empty());
+    }
+
+    #[test]
+    fn refuses_cycles() {
+        let graph = Graph::default();
+        assert!(graph.add("self", &names(&["self"])).is_err());
+
+        graph.add("a", &names(&["b"])).unwrap();
+        graph.add("b", &names(&["c"])).unwrap();
+        assert!(graph.add("c", &names(&["a"])).is_err());
+        assert!(graph.add("c", &names(&["d"])).is_ok());
+    }
+
+    #[test]
+    fn readiness_from_events() {
+        let ok = Command::new("true").status().unwrap();
+        let failed = Command::new("false").status().unwrap();
+        assert!(Readiness::Exit.satisfied_by(&JobEvent::Exit(ok)));
+        assert!(!Readiness::Exit.satisfied_by(&JobEvent::Exit(failed)));
+
+        let line = Readiness::Line("Listening on".into());
+        assert!(line.satisfied_by(&JobEvent::Line("Listening on :8080".into())));
+        assert!(!line.satisfied_by(&JobEvent::Exit(ok)));
+
+        assert!(Readiness::command("true", &[]).check());
+        assert!(!Readiness::command("false", &[]).check());
+    }
+}
@@ -0,0 +1,141 @@
+/// A successful fuzzy match: how good it is, and which chars it used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub score: i32,
+    /// Byte offsets into the text of each matched char.
+    pub indices: Vec<usize>,
+}
+
+const MATCH: i32 = 16;
+const WORD_START: i32 = 10;
+const CONSECUTIVE: i32 = 8;
+const FIRST_CHAR: i32 = 4;
+const GAP: i32 = 1;
+
+/// Scores `text` against `pattern` as a case-insensitive subsequence,
+/// favouring matches at word starts and runs of consecutive chars.
+/// `None` if some char of the pattern can't be found in order.
+pub fn score(pattern: &str, text: &str) -> Option<Match> {
+    let pattern = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    let text = text.char_indices().collect::<Vec<_>>();
+    if pattern.is_empty() {
+        return Some(Match {
+            score: 0,
+            indices: vec![],
+        });
+    }
+
+    let eq = |p: char, t: char| p.to_lowercase().eq(t.to_lowercase());
+    let bonus = |j: usize| {
+        let c = text[j].1;
+        let start = match j.checked_sub(1).map(|k| text[k].1) {
+            None => WORD_START + FIRST_CHAR,
+            Some(prev) if !prev.is_alphanumeric() => WORD_START,
+            Some(prev) if prev.is_lowercase() && c.is_uppercase() => WORD_START,
+            Some(_) => 0,
+        };
+        MATCH + start
+    };
+
+    // best[i][j]: top score with pattern[i] matched at text[j], plus where
+    // pattern[i - 1] went to get it
+    let mut best = vec![vec![None::<(i32, usize)>; text.len()]; pattern.len()];
+    for (i, &p) in pattern.iter().enumerate() {
+        for j in 0..text.len() {
+            if !eq(p, text[j].1) {
+                continue;
+            }
+
+            best[i][j] = match i {
+                0 => Some((bonus(j) - GAP * j as i32, 0)),
+                _ => (0..j)
+                    .filter_map(|k| {
+                        let (prev, _) = best[i - 1][k]?;
+                        let link = match k + 1 == j {
+                            true => CONSECUTIVE,
+                            false => -GAP * (j - k - 1) as i32,
+                        };
+                        Some((prev + link + bonus(j), k))
+                    })
+                    .max_by_key(|(s, k)| (*s, std::cmp::Reverse(*k))),
+            };
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut j, (score, _)) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, b)| Some((j, (*b)?)))
+        .max_by_key(|(j, (s, _))| (*s, std::cmp::Reverse(*j)))?;
+
+    let mut indices = vec![0; pattern.len()];
+    for i in (0..=last).rev() {
+        indices[i] = text[j].0;
+        j = best[i][j].unwrap().1;
+    }
+
+    Some(Match { score, indices })
+}
+
+/// Every item matching `pattern`, best first. Ties go to the shorter text,
+/// then to the original order.
+pub fn rank<'a, T>(
+    pattern: &str,
+    items: &'a [T],
+    text: impl Fn(&T) -> &str,
+) -> Vec<(Match, &'a T)> {
+    let mut ranked = items
+        .iter()
+        .filter_map(|item| Some((score(pattern, text(item))?, item)))
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|(m, item)| (std::cmp::Reverse(m.score), text(item).len()));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_only() {
+        assert!(score("bfe", "Build Frontend").is_some());
+        assert!(score("BFE", "build frontend").is_some());
+        assert!(score("efb", "Build Frontend").is_none());
+        assert_eq!(score("", "anything").unwrap().score, 0);
+    }
+
+    #[test]
+    fn prefers_word_starts() {
+        let m = score("bf", "Build Frontend").unwrap();
+        assert_eq!(m.indices, [0, 6]);
+        let m = score("sw", "Sites (wasm)").unwrap();
+        assert_eq!(m.indices, [0, 7]);
+    }
+
+    #[test]
+    fn ranks_build_frontend_first_for_bfe() {
+        let items = [
+            "Sites (bin)",
+            "Build Frontend (release)",
+            "Rebuild frontend",
+            "Sub-frame editor",
+            "Build Frontend",
+            "Configure iTask",
+        ];
+        let ranked = rank("bfe", &items, |s| s);
+        let titles = ranked.iter().map(|(_, s)| **s).collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            [
+                "Build Frontend",
+                "Build Frontend (release)",
+                "Sub-frame editor",
+                "Rebuild frontend"
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::spawn,
+};
+
+use anyhow::{bail, Context};
+
+use crate::{lock::Recover, toml::Table};
+
+/// Something that happened to a running job.
+#[derive(Clone, Debug)]
+pub enum JobEvent {
+    Start,
+    Exit(ExitStatus),
+    Line(String),
+}
+
+impl JobEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobEvent::Start => "start",
+            JobEvent::Exit(_) => "exit",
+            JobEvent::Line(_) => "line",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum HookTrigger {
+    Start,
+    Exit,
+    /// Only exits with a non-zero (or signal) status.
+    Failure,
+    /// An output line containing the given text. Lines matching while the
+    /// script from an earlier one is still going don't run it again.
+    Match(String),
+}
+
+impl HookTrigger {
+    fn matches(&self, event: &JobEvent) -> bool {
+        match (self, event) {
+            (HookTrigger::Start, JobEvent::Start) => true,
+            (HookTrigger::Exit, JobEvent::Exit(_)) => true,
+            (HookTrigger::Failure, JobEvent::Exit(status)) => !status.success(),
+            (HookTrigger::Match(pattern), JobEvent::Line(line)) => line.contains(pattern),
+            _ => false,
+        }
+    }
+}
+
+/// An external script run whenever its trigger fires.
+///
+/// The script gets the details through the environment: `ITASK_EVENT`,
+/// `ITASK_JOB` and `ITASK_COMMAND` always, `ITASK_EXIT_CODE` on exits and
+/// `ITASK_LINE` on matches.
+#[derive(Clone, Debug)]
+pub struct Hook {
+    pub script: PathBuf,
+    pub on: HookTrigger,
+}
+
+impl Hook {
+    /// Reads a `[[hook]]` table: a `script`, what it runs `on` (`start`,
+    /// `exit`, `failure` or `match`) and, for `match`, the `pattern`. Loaded
+    /// from a file, a relative `script` is found next to it, see
+    /// [`Hook::relative_to`].
+    pub fn from_table(t: &Table) -> anyhow::Result<Self> {
+        let script = t
+            .get("script")
+            .and_then(|v| v.as_str())
+            .context("`script` must be a string")?;
+        let on = match t.get("on").and_then(|v| v.as_str()) {
+            Some("start") => HookTrigger::Start,
+            Some("exit") => HookTrigger::Exit,
+            Some("failure") => HookTrigger::Failure,
+            Some("match") => HookTrigger::Match(
+                t.get("pattern")
+                    .and_then(|v| v.as_str())
+                    .context("`match` hooks need a `pattern` string")?
+                    .into(),
+            ),
+            _ => bail!("`on` must be start, exit, failure or match"),
+        };
+        Ok(Self {
+            script: script.into(),
+            on,
+        })
+    }
+
+    /// Takes a relative `script` as relative to `dir`, where the config it
+    /// came from lives, rather than leaving it to be looked up on `PATH`.
+    pub fn relative_to(mut self, dir: &Path) -> Self {
+        if self.script.is_relative() {
+            self.script = dir.join(&self.script);
+        }
+        self
+    }
+}
+
+/// Whether a hook's script is still running, see [`HookTrigger::Match`].
+type Busy = Arc<AtomicBool>;
+
+#[derive(Clone, Default)]
+pub struct Hooks(Arc<RwLock<Vec<(Hook, Busy)>>>);
+
+impl Hooks {
+    pub fn add(&self, hook: Hook) {
+        self.0.write_or_recover().push((hook, Busy::default()));
+    }
+
+    /// Runs every hook the event triggers, each waited on by a thread of
+    /// its own. Gives back why any couldn't be started.
+    pub fn dispatch(&self, job: &str, command: &str, event: &JobEvent) -> Vec<anyhow::Error> {
+        let mut failed = vec![];
+        for (hook, busy) in self.0.read_or_recover().iter() {
+            if !hook.on.matches(event) {
+                continue;
+            }
+            let coalesce = matches!(hook.on, HookTrigger::Match(_));
+            if coalesce && busy.swap(true, Ordering::Relaxed) {
+                continue;
+            }
+
+            let mut c = Command::new(&hook.script);
+            c.env("ITASK_EVENT", event.name())
+                .env("ITASK_JOB", job)
+                .env("ITASK_COMMAND", command)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+
+            match event {
+                JobEvent::Exit(status) => {
+                    c.env(
+                        "ITASK_EXIT_CODE",
+                        status.code().map(|c| c.to_string()).unwrap_or_default(),
+                    );
+                }
+                JobEvent::Line(line) => {
+                    c.env("ITASK_LINE", line);
+                }
+                JobEvent::Start => {}
+            }
+
+            match c.spawn() {
+                Ok(mut child) => {
+                    let busy = busy.clone();
+                    spawn(move || {
+                        child.wait().ok();
+                        busy.store(false, Ordering::Relaxed);
+                    });
+                }
+                Err(e) => {
+                    busy.store(false, Ordering::Relaxed);
+                    let script = hook.script.display();
+                    failed.push(anyhow::Error::new(e).context(format!("hook `{script}` failed")));
+                }
+            }
+        }
+        failed
+    }
+}
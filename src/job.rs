@@ -0,0 +1,2306 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc::{self, Sender},
+        Arc, RwLock,
+    },
+    thread::{self, sleep, spawn},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Context;
+use itertools::Itertools;
+use ratatui::style::{Color, Style};
+
+use crate::{
+    ansi::{self, Link},
+    clock,
+    deps::{Graph, Readiness},
+    hooks::{Hooks, JobEvent},
+    lock::Recover,
+    logfile::LogFile,
+    search::{self, Filter},
+    theme::parse_color,
+    toml::{Table, Value},
+    Dirty,
+};
+
+/// Everything needed to (re)launch a job.
+#[derive(Clone, Debug)]
+pub struct JobConfig {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Run the command line through `sh -c` (`cmd /C` on Windows), for
+    /// pipes, redirects and builtins. Off by default, so arguments are never
+    /// reinterpreted by a shell unless asked for.
+    pub shell: bool,
+    /// Where the command runs, itask's own directory if unset.
+    pub cwd: Option<PathBuf>,
+    /// Also write output to a file in here, named as [`LogFile::path`] says.
+    pub log_dir: Option<PathBuf>,
+    /// Set on top of the environment itask was started with.
+    pub env: BTreeMap<String, String>,
+    /// How many times a failing command is re-run before giving up.
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every retry after it.
+    pub backoff: Duration,
+    pub retention: Retention,
+    /// How many lines of output are kept before `retention` kicks in.
+    pub log_cap: usize,
+    /// Whether stderr shares the pane with stdout or gets its own below it.
+    pub output: OutputLayout,
+    /// Keep only what follows the last `\r` in a line, the way a terminal
+    /// would show a progress bar that redraws itself.
+    pub collapse_cr: bool,
+    /// Start the command again whenever it exits, for dev servers and the
+    /// like. Can be switched off while the job runs.
+    pub restart: bool,
+    /// Leave the process running when itask quits, rather than sending it
+    /// SIGTERM. Its output pipes still close with itask, so it should cope
+    /// with that, e.g. by writing to a file instead.
+    pub detach: bool,
+    /// Note the local time each line was read at, shown before it.
+    pub timestamps: bool,
+    /// Keep stdin open for lines typed into the pane, see
+    /// [`Job::send_input`], for programs that take commands there. Otherwise
+    /// the process reads nothing.
+    pub stdin: bool,
+    /// What marks a line as an error for [`Job::last_error`], in any case.
+    pub error_pattern: String,
+    /// Colours for lines by how they start, see [`JobConfig::line_color`].
+    pub colors: Vec<(String, Color)>,
+    /// What other jobs call this one in their `after`. Also its title.
+    pub name: Option<String>,
+    /// Named jobs that must be ready before this one starts.
+    pub after: Vec<String>,
+    /// When jobs waiting on this one may go ahead. Only used if named.
+    pub ready: Readiness,
+}
+
+impl JobConfig {
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            shell: false,
+            cwd: None,
+            log_dir: None,
+            env: BTreeMap::new(),
+            retries: 0,
+            backoff: Duration::from_secs(1),
+            retention: Retention::default(),
+            log_cap: LOG_CAP,
+            output: OutputLayout::default(),
+            collapse_cr: true,
+            restart: false,
+            detach: false,
+            timestamps: false,
+            stdin: false,
+            error_pattern: "error".into(),
+            colors: vec![],
+            name: None,
+            after: vec![],
+            ready: Readiness::default(),
+        }
+    }
+
+    pub fn shell(mut self, shell: bool) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    pub fn log_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.log_dir = Some(dir.into());
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn after(mut self, jobs: &[&str]) -> Self {
+        self.after = jobs.iter().map(|j| j.to_string()).collect();
+        self
+    }
+
+    pub fn ready_when(mut self, ready: Readiness) -> Self {
+        self.ready = ready;
+        self
+    }
+
+    pub fn restart(mut self, restart: bool) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    pub fn detach(mut self, detach: bool) -> Self {
+        self.detach = detach;
+        self
+    }
+
+    pub fn timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    pub fn stdin(mut self, stdin: bool) -> Self {
+        self.stdin = stdin;
+        self
+    }
+
+    pub fn error_pattern(mut self, pattern: &str) -> Self {
+        self.error_pattern = pattern.to_string();
+        self
+    }
+
+    /// Colours lines starting with `prefix`, after any rules added before.
+    pub fn color(mut self, prefix: &str, color: Color) -> Self {
+        self.colors.push((prefix.to_string(), color));
+        self
+    }
+
+    pub fn collapse_cr(mut self, collapse_cr: bool) -> Self {
+        self.collapse_cr = collapse_cr;
+        self
+    }
+
+    pub fn retention(mut self, retention: Retention) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn output(mut self, output: OutputLayout) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn log_cap(mut self, log_cap: usize) -> Self {
+        self.log_cap = log_cap;
+        self
+    }
+
+    pub fn retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// The program and its arguments as typed on a shell, roughly.
+    pub fn command_line(&self) -> String {
+        [self.program.as_str()]
+            .into_iter()
+            .chain(self.args.iter().map(String::as_str))
+            .join(" ")
+    }
+
+    /// What to paste into a shell to run the job by hand: its `cwd` and
+    /// `env` first, then the command, with anything a shell would split or
+    /// expand quoted. Shell jobs' command lines are left as written.
+    pub fn shell_line(&self) -> String {
+        let cd = self
+            .cwd
+            .iter()
+            .map(|cwd| format!("cd {} &&", quote(&cwd.display().to_string())));
+        let env = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{key}={}", quote(value)));
+        let command = match self.shell {
+            true => vec![self.command_line()],
+            false => [&self.program]
+                .into_iter()
+                .chain(&self.args)
+                .map(|word| quote(word))
+                .collect(),
+        };
+        cd.chain(env).chain(command).join(" ")
+    }
+
+    pub fn command(&self) -> Command {
+        let mut c = match self.shell {
+            true => {
+                let (shell, flag) = match cfg!(windows) {
+                    true => ("cmd", "/C"),
+                    false => ("sh", "-c"),
+                };
+                let mut c = Command::new(shell);
+                c.arg(flag).arg(self.command_line());
+                c
+            }
+            false => {
+                let mut c = Command::new(&self.program);
+                c.args(&self.args);
+                c
+            }
+        };
+        c.envs(&self.env);
+        if let Some(cwd) = &self.cwd {
+            c.current_dir(cwd);
+        }
+        c
+    }
+
+    /// Runs the job once in the foreground, as `itask run` does, both its
+    /// streams copied line by line to this process's stdout.
+    pub fn run_attached(&self) -> anyhow::Result<ExitStatus> {
+        if let Some(cwd) = self.cwd.as_ref().filter(|cwd| !cwd.is_dir()) {
+            anyhow::bail!("failed to start: `{}` is not a directory", cwd.display());
+        }
+        let mut child = self
+            .command()
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to start `{}`", self.program))?;
+
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            anyhow::bail!("failed to capture the output of `{}`", self.program);
+        };
+        // Whole lines at a time, so the two streams never split one another
+        fn copy(from: impl Read) {
+            let mut from = BufReader::new(from);
+            let mut line = vec![];
+            while from.read_until(b'\n', &mut line).is_ok_and(|n| n > 0) {
+                if io::stdout().lock().write_all(&line).is_err() {
+                    return;
+                }
+                line.clear();
+            }
+        }
+        thread::scope(|s| {
+            s.spawn(|| copy(stdout));
+            s.spawn(|| copy(stderr));
+        });
+
+        child
+            .wait()
+            .with_context(|| format!("failed to wait for `{}`", self.program))
+    }
+
+    pub fn to_table(&self) -> Table {
+        let mut t = Table::new();
+        t.insert("command".into(), Value::String(self.program.clone()));
+        t.insert("args".into(), strings(&self.args));
+        t.insert("shell".into(), Value::Boolean(self.shell));
+        if let Some(cwd) = &self.cwd {
+            t.insert("cwd".into(), Value::String(cwd.display().to_string()));
+        }
+        if let Some(dir) = &self.log_dir {
+            t.insert("log_dir".into(), Value::String(dir.display().to_string()));
+        }
+        if !self.env.is_empty() {
+            t.insert(
+                "env".into(),
+                Value::Table(
+                    self.env
+                        .iter()
+                        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                        .collect(),
+                ),
+            );
+        }
+        t.insert("retries".into(), Value::Integer(self.retries as i64));
+        t.insert(
+            "backoff_ms".into(),
+            Value::Integer(self.backoff.as_millis() as i64),
+        );
+        t.insert(
+            "retention".into(),
+            Value::String(self.retention.name().into()),
+        );
+        t.insert(
+            "log_cap".into(),
+            Value::Integer(self.log_cap.try_into().unwrap_or(i64::MAX)),
+        );
+        t.insert("output".into(), Value::String(self.output.name().into()));
+        t.insert("collapse_cr".into(), Value::Boolean(self.collapse_cr));
+        t.insert("restart".into(), Value::Boolean(self.restart));
+        t.insert("detach".into(), Value::Boolean(self.detach));
+        t.insert("timestamps".into(), Value::Boolean(self.timestamps));
+        t.insert("stdin".into(), Value::Boolean(self.stdin));
+        t.insert(
+            "error_pattern".into(),
+            Value::String(self.error_pattern.clone()),
+        );
+        if !self.colors.is_empty() {
+            let rules = self.colors.iter().map(|(prefix, color)| {
+                let mut rule = Table::new();
+                rule.insert("prefix".into(), Value::String(prefix.clone()));
+                rule.insert("color".into(), Value::String(color.to_string()));
+                Value::Table(rule)
+            });
+            t.insert("colors".into(), Value::Array(rules.collect()));
+        }
+        if let Some(name) = &self.name {
+            t.insert("name".into(), Value::String(name.clone()));
+        }
+        if !self.after.is_empty() {
+            t.insert("after".into(), strings(&self.after));
+        }
+        match &self.ready {
+            Readiness::Exit => {}
+            Readiness::Line(pattern) => {
+                t.insert("ready_line".into(), Value::String(pattern.clone()));
+            }
+            Readiness::Command { program, args } => {
+                let mut command = vec![program.clone()];
+                command.extend(args.iter().cloned());
+                t.insert("ready_command".into(), strings(&command));
+            }
+        }
+        t
+    }
+
+    /// Reads a job back from [`JobConfig::to_table`]'s format, the same one
+    /// `itask.toml` uses. Only `command` is required.
+    pub fn from_table(t: &Table) -> anyhow::Result<Self> {
+        let program = t
+            .get("command")
+            .and_then(Value::as_str)
+            .context("job is missing `command`")?;
+        let mut config = Self::new(program, &[]);
+
+        if let Some(args) = t.get("args") {
+            config.args = from_strings(args).context("`args` must be an array of strings")?;
+        }
+        if let Some(shell) = t.get("shell") {
+            config.shell = shell.as_bool().context("`shell` must be a boolean")?;
+        }
+        if let Some(cwd) = t.get("cwd") {
+            config.cwd = Some(cwd.as_str().context("`cwd` must be a string")?.into());
+        }
+        if let Some(dir) = t.get("log_dir") {
+            config.log_dir = Some(dir.as_str().context("`log_dir` must be a string")?.into());
+        }
+        if let Some(env) = t.get("env") {
+            config.env = env
+                .as_table()
+                .context("`env` must be a table")?
+                .iter()
+                .map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect::<Option<_>>()
+                .context("`env` values must be strings")?;
+        }
+        if let Some(retries) = t.get("retries") {
+            config.retries = retries
+                .as_integer()
+                .and_then(|r| r.try_into().ok())
+                .context("`retries` must be a positive integer")?;
+        }
+        if let Some(backoff) = t.get("backoff_ms") {
+            config.backoff = backoff
+                .as_integer()
+                .and_then(|b| b.try_into().ok())
+                .map(Duration::from_millis)
+                .context("`backoff_ms` must be a positive integer")?;
+        }
+        if let Some(retention) = t.get("retention") {
+            config.retention = retention
+                .as_str()
+                .and_then(Retention::from_name)
+                .context("`retention` must be keep-tail, keep-head or both-ends")?;
+        }
+        if let Some(cap) = t.get("log_cap") {
+            config.log_cap = cap
+                .as_integer()
+                .and_then(|c| c.try_into().ok())
+                .filter(|&c| c > 0)
+                .context("`log_cap` must be a positive integer")?;
+        }
+        if let Some(output) = t.get("output") {
+            config.output = output
+                .as_str()
+                .and_then(OutputLayout::from_name)
+                .context("`output` must be merged or split")?;
+        }
+        if let Some(restart) = t.get("restart") {
+            config.restart = restart.as_bool().context("`restart` must be a boolean")?;
+        }
+        if let Some(detach) = t.get("detach") {
+            config.detach = detach.as_bool().context("`detach` must be a boolean")?;
+        }
+        if let Some(timestamps) = t.get("timestamps") {
+            config.timestamps = timestamps
+                .as_bool()
+                .context("`timestamps` must be a boolean")?;
+        }
+        if let Some(stdin) = t.get("stdin") {
+            config.stdin = stdin.as_bool().context("`stdin` must be a boolean")?;
+        }
+        if let Some(pattern) = t.get("error_pattern") {
+            config.error_pattern = pattern
+                .as_str()
+                .context("`error_pattern` must be a string")?
+                .into();
+        }
+        if let Some(collapse) = t.get("collapse_cr") {
+            config.collapse_cr = collapse
+                .as_bool()
+                .context("`collapse_cr` must be a boolean")?;
+        }
+        if let Some(colors) = t.get("colors") {
+            config.colors = colors
+                .as_array()
+                .context("`colors` must be an array of { prefix, color } tables")?
+                .iter()
+                .map(|rule| {
+                    let rule = rule.as_table();
+                    let prefix = rule
+                        .and_then(|r| r.get("prefix")?.as_str())
+                        .context("`colors` entries need a `prefix` string")?;
+                    let color = rule
+                        .and_then(|r| r.get("color"))
+                        .context("`colors` entries need a `color`")?;
+                    Ok((prefix.to_string(), parse_color(color)?))
+                })
+                .collect::<anyhow::Result<_>>()?;
+        }
+        if let Some(name) = t.get("name") {
+            config.name = Some(name.as_str().context("`name` must be a string")?.into());
+        }
+        if let Some(after) = t.get("after") {
+            config.after = from_strings(after).context("`after` must be an array of strings")?;
+        }
+        if let Some(pattern) = t.get("ready_line") {
+            config.ready = Readiness::Line(
+                pattern
+                    .as_str()
+                    .context("`ready_line` must be a string")?
+                    .into(),
+            );
+        }
+        if let Some(command) = t.get("ready_command") {
+            let mut command = from_strings(command)
+                .filter(|c| !c.is_empty())
+                .context("`ready_command` must be a non-empty array of strings")?;
+            config.ready = Readiness::Command {
+                program: command.remove(0),
+                args: command,
+            };
+        }
+
+        Ok(config)
+    }
+
+    /// The colour of the first of [`JobConfig::colors`] whose prefix `text`
+    /// starts with, leading whitespace aside.
+    pub fn line_color(&self, text: &str) -> Option<Color> {
+        let text = text.trim_start();
+        self.colors
+            .iter()
+            .find(|(prefix, _)| text.starts_with(prefix.as_str()))
+            .map(|(_, color)| *color)
+    }
+
+    /// Delay before the given (1-based) retry.
+    pub fn backoff_for(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+    }
+}
+
+fn strings(strings: &[String]) -> Value {
+    Value::Array(strings.iter().cloned().map(Value::String).collect())
+}
+
+fn from_strings(value: &Value) -> Option<Vec<String>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|a| a.as_str().map(str::to_string))
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub enum JobStatus {
+    /// Held back until the named jobs are ready.
+    Waiting {
+        on: Vec<String>,
+    },
+    /// `attempt` is 0 for the first run and counts retries after that.
+    Running {
+        attempt: u32,
+    },
+    Retrying {
+        attempt: u32,
+        at: Instant,
+    },
+    /// Exited with restart on, starting again at `at`.
+    Restarting {
+        at: Instant,
+    },
+    /// `took` is how long the last run lasted.
+    Exited {
+        status: ExitStatus,
+        took: Duration,
+    },
+    /// The command couldn't be spawned at all, e.g. a missing `cwd`.
+    Unstartable,
+    /// Ended from itask with [`Job::stop`].
+    Stopped,
+}
+
+impl JobStatus {
+    /// Whether the job is done for good, one way or another.
+    pub fn finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Exited { .. } | JobStatus::Unstartable | JobStatus::Stopped
+        )
+    }
+}
+
+/// The running child of a job, for stopping it from outside the thread that
+/// waits on it.
+#[derive(Default)]
+pub struct Process {
+    stopped: AtomicBool,
+    child: RwLock<Option<Child>>,
+    /// Of the latest child, 0 before the first one started.
+    pid: AtomicU32,
+    /// The latest child's, with [`JobConfig::stdin`], until its output ends.
+    stdin: RwLock<Option<Sender<String>>>,
+    /// When the latest child started and, once waited on, when it ended.
+    ran: RwLock<Option<(Instant, Option<Instant>)>>,
+}
+
+impl Process {
+    /// The OS process id of the latest child, kept after it exits.
+    pub fn pid(&self) -> Option<u32> {
+        Some(self.pid.load(Ordering::Relaxed)).filter(|&pid| pid != 0)
+    }
+
+    /// How long the latest child ran, or has been running for.
+    pub fn ran_for(&self) -> Option<Duration> {
+        let (started, ended) = (*self.ran.read_or_recover())?;
+        Some(ended.unwrap_or_else(Instant::now) - started)
+    }
+
+    /// Whether the child [`Process::pid`] names is still there to signal.
+    pub fn alive(&self) -> bool {
+        self.child.read_or_recover().is_some()
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// Queues `line` and a newline for the child's stdin. A thread of the
+    /// job's does the writing, so a child that stops reading can't block
+    /// the caller.
+    pub fn write_line(&self, line: &str) -> anyhow::Result<()> {
+        self.stdin
+            .read_or_recover()
+            .as_ref()
+            .and_then(|stdin| stdin.send(line.to_string()).ok())
+            .context("the job isn't reading input")
+    }
+
+    /// Kills the child if there is one, and keeps the job from running it
+    /// again.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(child) = self.child.write_or_recover().as_mut() {
+            child.kill().ok();
+        }
+    }
+
+    /// Asks the child to exit, with SIGTERM where there's such a thing, and
+    /// keeps the job from running it again.
+    pub fn terminate(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(child) = self.child.write_or_recover().as_mut() {
+            // Not yet waited on, so the pid can't have been reused
+            #[cfg(unix)]
+            unsafe {
+                libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+            }
+            #[cfg(not(unix))]
+            child.kill().ok();
+        }
+    }
+
+    /// Sleeps for `duration`, or until stopped.
+    fn sleep(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        while !self.stopped() {
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return;
+            }
+            sleep(left.min(Duration::from_millis(100)));
+        }
+    }
+}
+
+/// `word` in single quotes unless it's made of nothing a POSIX shell would
+/// treat specially.
+fn quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:@,+%".contains(c);
+    match !word.is_empty() && word.chars().all(plain) {
+        true => word.to_string(),
+        false => format!("'{}'", word.replace('\'', r"'\''")),
+    }
+}
+
+/// `delay` in whole seconds, rounded up so it only says 0s once it's over.
+fn countdown(delay: Duration) -> String {
+    format!("{}s", delay.as_secs_f32().ceil())
+}
+
+/// The part of `line` a terminal would leave visible after each `\r` moved
+/// the cursor back to the start. A trailing `\r` (as in CRLF) is ignored.
+pub fn collapse_cr(line: &str) -> &str {
+    let line = line.trim_end_matches('\r');
+    line.rsplit('\r').next().unwrap_or(line)
+}
+
+/// Output lines kept per job unless its config says otherwise.
+pub const LOG_CAP: usize = 1000;
+
+/// How many matching lines [`Job::pinned`] keeps in view.
+pub const PINNED: usize = 3;
+
+/// Which lines survive once a job's output passes the cap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Retention {
+    /// Drop the oldest lines, keeping the latest output.
+    #[default]
+    KeepTail,
+    /// Stop recording once full, keeping the first lines.
+    KeepHead,
+    /// Keep the first and latest halves, with a marker where lines were cut.
+    BothEnds,
+}
+
+impl Retention {
+    pub fn name(self) -> &'static str {
+        match self {
+            Retention::KeepTail => "keep-tail",
+            Retention::KeepHead => "keep-head",
+            Retention::BothEnds => "both-ends",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Retention::KeepTail,
+            Retention::KeepHead,
+            Retention::BothEnds,
+        ]
+        .into_iter()
+        .find(|r| r.name() == name)
+    }
+}
+
+/// How a pane shows the two streams.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputLayout {
+    /// Interleaved as they arrive, stderr in the error colour.
+    #[default]
+    Merged,
+    /// Stdout on top, stderr in a smaller area below with its own buffer,
+    /// so a chatty stdout can't push errors out.
+    Split,
+}
+
+impl OutputLayout {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputLayout::Merged => "merged",
+            OutputLayout::Split => "split",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [OutputLayout::Merged, OutputLayout::Split]
+            .into_iter()
+            .find(|o| o.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogLine {
+    pub text: String,
+    pub stream: Stream,
+    pub links: Vec<Link>,
+    /// Colors the program asked for, see [`ansi::parse`].
+    pub styles: Vec<(Range<usize>, Style)>,
+    /// When it was read, if the job has [`JobConfig::timestamps`] on.
+    pub time: Option<String>,
+}
+
+impl LogLine {
+    pub fn new(text: impl Into<String>, stream: Stream) -> Self {
+        Self {
+            text: text.into(),
+            stream,
+            links: vec![],
+            styles: vec![],
+            time: None,
+        }
+    }
+
+    /// A line as the program wrote it, escape sequences and all.
+    pub fn parse(raw: &str, stream: Stream) -> Self {
+        let parsed = ansi::parse(raw);
+        Self {
+            text: parsed.text,
+            stream,
+            links: parsed.links,
+            styles: parsed.styles,
+            time: None,
+        }
+    }
+
+    /// A line itask writes itself, such as a retry notice.
+    pub fn note(text: impl Into<String>) -> Self {
+        Self::new(text, Stream::Stdout)
+    }
+}
+
+/// Which streams a pane shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StreamFilter {
+    #[default]
+    Both,
+    Stdout,
+    Stderr,
+}
+
+impl StreamFilter {
+    pub fn next(self) -> Self {
+        match self {
+            StreamFilter::Both => StreamFilter::Stdout,
+            StreamFilter::Stdout => StreamFilter::Stderr,
+            StreamFilter::Stderr => StreamFilter::Both,
+        }
+    }
+
+    pub fn shows(self, stream: Stream) -> bool {
+        match self {
+            StreamFilter::Both => true,
+            StreamFilter::Stdout => stream == Stream::Stdout,
+            StreamFilter::Stderr => stream == Stream::Stderr,
+        }
+    }
+}
+
+/// Captured output, capped at `cap` lines plus an elision marker.
+pub struct Logs {
+    /// Everything held, or with [`Retention::BothEnds`] once lines are
+    /// elided, the head and then the marker.
+    pub lines: VecDeque<LogLine>,
+    /// Lines trimmed off the front so far, making `dropped + i` the absolute
+    /// number of `lines[i]`. Only [`Retention::KeepTail`] trims the front.
+    pub dropped: usize,
+    /// The newest lines kept past the marker by [`Retention::BothEnds`].
+    pub tail: VecDeque<LogLine>,
+    /// The absolute number of `tail[0]`, moving on with each line elided so
+    /// the lines behind the marker keep theirs.
+    pub tail_from: usize,
+    /// Lines discarded behind the elision marker.
+    pub elided: usize,
+    pub retention: Retention,
+    pub cap: usize,
+}
+
+impl Default for Logs {
+    fn default() -> Self {
+        Self::new(Retention::default(), LOG_CAP)
+    }
+}
+
+impl Logs {
+    pub fn new(retention: Retention, cap: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            dropped: 0,
+            tail: VecDeque::new(),
+            tail_from: 0,
+            elided: 0,
+            retention,
+            cap,
+        }
+    }
+
+    pub fn push(&mut self, line: LogLine) {
+        match self.retention {
+            Retention::KeepTail => {
+                self.lines.push_back(line);
+                while self.lines.len() > self.cap {
+                    self.lines.pop_front();
+                    self.dropped += 1;
+                }
+            }
+
+            _ if self.elided == 0 && self.lines.len() < self.cap => self.lines.push_back(line),
+
+            Retention::KeepHead => {
+                if self.elided == 0 {
+                    self.lines.push_back(LogLine::note(""));
+                }
+                self.elided += 1;
+                *self.lines.back_mut().unwrap() = self.marker();
+            }
+
+            Retention::BothEnds => {
+                if self.elided == 0 {
+                    let head = self.cap / 2;
+                    self.tail = self.lines.split_off(head);
+                    self.tail_from = self.dropped + head;
+                    // Numbered as the first line it stands in for
+                    self.lines.push_back(LogLine::note(""));
+                }
+                self.tail.push_back(line);
+                self.tail.pop_front();
+                self.tail_from += 1;
+                self.elided += 1;
+                *self.lines.back_mut().unwrap() = self.marker();
+            }
+        }
+    }
+
+    fn marker(&self) -> LogLine {
+        LogLine::note(format!("[... {} lines elided ...]", self.elided))
+    }
+
+    /// How many lines of output are held, leaving out the elision marker.
+    pub fn held(&self) -> usize {
+        self.lines.len() + self.tail.len() - usize::from(self.elided > 0)
+    }
+
+    /// Whether the cap's been reached, so each new line costs one, the
+    /// oldest or the newest depending on `retention`.
+    pub fn full(&self) -> bool {
+        self.elided > 0 || self.held() >= self.cap
+    }
+
+    /// "120/1000 lines", with "full" after it once [`Logs::full`].
+    pub fn usage(&self) -> String {
+        match self.full() {
+            true => format!("{}/{} lines, full", self.held(), self.cap),
+            false => format!("{}/{} lines", self.held(), self.cap),
+        }
+    }
+
+    /// Absolute number one past the newest line.
+    pub fn end(&self) -> usize {
+        match self.tail.is_empty() {
+            true => self.dropped + self.lines.len(),
+            false => self.tail_from + self.tail.len(),
+        }
+    }
+
+    /// Every line held, marker included, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LogLine> {
+        self.lines.iter().chain(&self.tail)
+    }
+
+    /// Absolute numbers of the lines held within `range`, oldest first,
+    /// skipping over the ones elided.
+    pub fn numbers(&self, range: Range<usize>) -> impl DoubleEndedIterator<Item = usize> {
+        let [head, tail] = self
+            .spans()
+            .map(|s| s.start.max(range.start)..s.end.min(range.end));
+        head.chain(tail)
+    }
+
+    /// How many of the lines held come before line `n`.
+    pub fn held_before(&self, n: usize) -> usize {
+        self.spans()
+            .into_iter()
+            .map(|s| n.clamp(s.start, s.end) - s.start)
+            .sum()
+    }
+
+    /// The numbers of `lines` and of `tail`.
+    fn spans(&self) -> [Range<usize>; 2] {
+        [
+            self.dropped..self.dropped + self.lines.len(),
+            self.tail_from..self.tail_from + self.tail.len(),
+        ]
+    }
+
+    /// Empties the buffer. Line numbers carry on from where they were, so
+    /// nothing pointing at old lines finds a new one in their place.
+    pub fn clear(&mut self) {
+        self.dropped = self.end();
+        self.lines.clear();
+        self.tail.clear();
+        self.elided = 0;
+    }
+
+    pub fn get(&self, line: usize) -> Option<&LogLine> {
+        match line.checked_sub(self.tail_from) {
+            Some(i) if !self.tail.is_empty() => self.tail.get(i),
+            _ => self.lines.get(line.checked_sub(self.dropped)?),
+        }
+    }
+}
+
+/// A pane's worth of output, see [`Job::snapshot`].
+pub struct Snapshot {
+    /// The lines shown, each with its absolute number.
+    pub lines: Vec<(usize, LogLine)>,
+    /// As [`Job::position`] gives it.
+    pub position: Option<String>,
+    /// As [`Job::extent`] gives it.
+    pub extent: Option<(usize, usize)>,
+    /// As [`Logs::usage`] gives it, and whether [`Logs::full`].
+    pub usage: (String, bool),
+}
+
+/// Whole lines picked out with `v`, from `anchor` to wherever `cursor` has
+/// been moved since. Both are absolute line numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub anchor: usize,
+    pub cursor: usize,
+}
+
+impl Selection {
+    pub fn contains(&self, line: usize) -> bool {
+        self.anchor.min(self.cursor) <= line && line <= self.anchor.max(self.cursor)
+    }
+}
+
+/// What every job shares with the model that started it.
+#[derive(Clone, Default)]
+pub struct Shared {
+    pub hooks: Hooks,
+    pub deps: Graph,
+    pub dirty: Dirty,
+}
+
+pub struct Job {
+    /// The config's name, else the command cut down to fit a pane title.
+    pub title: String,
+    /// The whole command line, shown under the focused pane.
+    pub command: String,
+    pub config: JobConfig,
+    pub logs: Arc<RwLock<Logs>>,
+    /// Stderr, for [`OutputLayout::Split`]. Empty otherwise, stderr going
+    /// into `logs` with the rest.
+    pub stderr: Arc<RwLock<Logs>>,
+    pub status: Arc<RwLock<JobStatus>>,
+    /// Absolute line shown at the top of the pane, `None` to follow the tail.
+    pub scroll: Option<usize>,
+    /// Lines the pane had room for when last drawn, i.e. one page.
+    pub viewport: usize,
+    pub bookmarks: BTreeSet<usize>,
+    pub wrap: bool,
+    /// Columns scrolled past on the left, only while not wrapping.
+    pub column: usize,
+    /// Highlighted wherever it appears in the output.
+    pub search: Option<String>,
+    pub streams: StreamFilter,
+    /// Only lines matching this are shown, the rest are kept regardless.
+    pub filter: Option<Filter>,
+    /// The newest lines matching this are kept in view above the rest,
+    /// see [`Job::pinned`].
+    pub pin: Option<Filter>,
+    pub selection: Option<Selection>,
+    /// The cursor and line being typed for the process's stdin, drawn
+    /// under the output while there is one.
+    pub input: Option<(usize, String)>,
+    /// Whether the next exit starts the command again, see
+    /// [`JobConfig::restart`].
+    pub restart: Arc<AtomicBool>,
+    /// Where output is copied to, if the config has a `log_dir`.
+    pub log_file: Option<PathBuf>,
+    pub process: Arc<Process>,
+}
+
+impl Job {
+    pub fn start(config: JobConfig, shared: Shared) -> Self {
+        let Shared { hooks, deps, dirty } = shared;
+        let command = config.command_line();
+        let title = config
+            .name
+            .clone()
+            .unwrap_or_else(|| match command.chars().nth(10) {
+                Some(_) => format!("{}...", command.chars().take(7).collect::<String>()),
+                None => command.clone(),
+            });
+
+        let log_file = config
+            .log_dir
+            .as_ref()
+            .map(|dir| LogFile::path(dir, config.name.as_ref().unwrap_or(&command)));
+
+        let job = Self {
+            title,
+            command,
+            config: config.clone(),
+            logs: Arc::new(RwLock::new(Logs::new(config.retention, config.log_cap))),
+            stderr: Arc::new(RwLock::new(Logs::new(config.retention, config.log_cap))),
+            status: Arc::new(RwLock::new(match deps.waiting_on(&config.after) {
+                on if on.is_empty() => JobStatus::Running { attempt: 0 },
+                on => JobStatus::Waiting { on },
+            })),
+            scroll: None,
+            viewport: 0,
+            bookmarks: BTreeSet::new(),
+            wrap: true,
+            column: 0,
+            search: None,
+            streams: StreamFilter::default(),
+            filter: None,
+            pin: None,
+            selection: None,
+            input: None,
+            restart: Arc::new(AtomicBool::new(config.restart)),
+            log_file,
+            process: Arc::new(Process::default()),
+        };
+
+        let (logs, stderr, status) = (job.logs.clone(), job.stderr.clone(), job.status.clone());
+        let (restart, process) = (job.restart.clone(), job.process.clone());
+        let log_file = RwLock::new(job.log_file.clone().map(LogFile::new));
+        let (title, command) = (job.title.clone(), job.command.clone());
+        spawn(move || {
+            let dispatch = |event: JobEvent| {
+                if let Some(name) = &config.name {
+                    if config.ready.satisfied_by(&event) {
+                        deps.set_ready(name);
+                    }
+                }
+                for e in hooks.dispatch(&title, &command, &event) {
+                    logs.write_or_recover()
+                        .push(LogLine::new(format!("[{e:#}]"), Stream::Stderr));
+                }
+                dirty.mark();
+            };
+            let set_status = |to: JobStatus| {
+                *status.write_or_recover() = to;
+                dirty.mark();
+            };
+
+            let mut waiting = vec![];
+            loop {
+                if process.stopped() {
+                    set_status(JobStatus::Stopped);
+                    return;
+                }
+                let on = deps.waiting_on(&config.after);
+                if on.is_empty() {
+                    break;
+                }
+                if on != waiting {
+                    waiting = on.clone();
+                    set_status(JobStatus::Waiting { on });
+                }
+                sleep(Duration::from_millis(100));
+            }
+
+            if let (Some(name), Readiness::Command { .. }) = (&config.name, &config.ready) {
+                let (name, ready, status) = (name.clone(), config.ready.clone(), status.clone());
+                let deps = deps.clone();
+                spawn(move || {
+                    while !status.read_or_recover().finished() {
+                        if ready.check() {
+                            deps.set_ready(&name);
+                            return;
+                        }
+                        sleep(Duration::from_millis(500));
+                    }
+                });
+            }
+
+            let mut attempt = 0;
+            loop {
+                if process.stopped() {
+                    set_status(JobStatus::Stopped);
+                    return;
+                }
+                set_status(JobStatus::Running { attempt });
+                dispatch(JobEvent::Start);
+                let started = Instant::now();
+                let errors = match config.output {
+                    OutputLayout::Merged => &logs,
+                    OutputLayout::Split => &stderr,
+                };
+                let exit = match Self::run(&config, &process, (&logs, errors), &log_file, &dispatch)
+                {
+                    Ok(exit) => exit,
+                    Err(e) => {
+                        logs.write_or_recover()
+                            .push(LogLine::new(format!("[{e:#}]"), Stream::Stderr));
+                        set_status(JobStatus::Unstartable);
+                        return;
+                    }
+                };
+                dispatch(JobEvent::Exit(exit));
+                if process.stopped() {
+                    logs.write_or_recover().push(LogLine::note("[stopped]"));
+                    set_status(JobStatus::Stopped);
+                    return;
+                }
+                if (exit.success() || attempt >= config.retries) && restart.load(Ordering::Relaxed)
+                {
+                    attempt = 0;
+                    logs.write_or_recover()
+                        .push(LogLine::note(format!("[{exit}, restarting]")));
+                    set_status(JobStatus::Restarting {
+                        at: Instant::now() + config.backoff,
+                    });
+                    process.sleep(config.backoff);
+                    continue;
+                }
+                if exit.success() || attempt >= config.retries {
+                    set_status(JobStatus::Exited {
+                        status: exit,
+                        took: started.elapsed(),
+                    });
+                    return;
+                }
+
+                attempt += 1;
+                let delay = config.backoff_for(attempt);
+                logs.write_or_recover().push(LogLine::note(format!(
+                    "[{exit}, retry {attempt}/{} in {}]",
+                    config.retries,
+                    countdown(delay)
+                )));
+                set_status(JobStatus::Retrying {
+                    attempt,
+                    at: Instant::now() + delay,
+                });
+                process.sleep(delay);
+            }
+        });
+
+        job
+    }
+
+    /// Runs the command to completion, streaming stdout and stderr into the
+    /// two buffers, which may well be the same one.
+    fn run(
+        config: &JobConfig,
+        process: &Process,
+        (logs, errors): (&RwLock<Logs>, &RwLock<Logs>),
+        log_file: &RwLock<Option<LogFile>>,
+        dispatch: &(impl Fn(JobEvent) + Sync),
+    ) -> anyhow::Result<ExitStatus> {
+        // Spawning would only say "No such file or directory", much like a
+        // missing program
+        if let Some(cwd) = config.cwd.as_ref().filter(|cwd| !cwd.is_dir()) {
+            anyhow::bail!("failed to start: `{}` is not a directory", cwd.display());
+        }
+        let mut child = config
+            .command()
+            .stdin(match config.stdin {
+                true => Stdio::piped(),
+                false => Stdio::null(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to start `{}`", config.program))?;
+        process.pid.store(child.id(), Ordering::Relaxed);
+        *process.ran.write_or_recover() = Some((Instant::now(), None));
+        let stdin = child.stdin.take();
+
+        // Both readers push into the same buffer as lines arrive, so the two
+        // streams interleave in roughly the order they were written. Each
+        // reader ends at EOF, once the process closes its end of the pipe.
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            anyhow::bail!("failed to capture the output of `{}`", config.program);
+        };
+        // Held where `Process::stop` can reach it until both pipes close
+        *process.child.write_or_recover() = Some(child);
+        if process.stopped() {
+            process.stop();
+        }
+        thread::scope(|s| {
+            if let Some(mut stdin) = stdin {
+                let (send, lines) = mpsc::channel::<String>();
+                *process.stdin.write_or_recover() = Some(send);
+                s.spawn(move || {
+                    for line in lines {
+                        if let Err(e) = writeln!(stdin, "{line}").and_then(|_| stdin.flush()) {
+                            let note = format!("[failed writing to stdin: {e}]");
+                            errors.write_or_recover().push(LogLine::note(note));
+                            break;
+                        }
+                    }
+                });
+            }
+            s.spawn(|| Self::read(stderr, Stream::Stderr, config, errors, log_file, dispatch));
+            Self::read(stdout, Stream::Stdout, config, logs, log_file, dispatch);
+            // Closed, ending the writer, in case the process is still
+            // waiting on more
+            process.stdin.write_or_recover().take();
+        });
+
+        // Left where `Process::stop` can reach it until reaped, since a child
+        // can close its output well before it exits
+        let exit = loop {
+            let mut child = process.child.write_or_recover();
+            if let Some(exit) = child.as_mut().unwrap().try_wait().transpose() {
+                child.take();
+                break exit.context("failed waiting for the job to exit");
+            }
+            drop(child);
+            sleep(Duration::from_millis(20));
+        };
+        if let Some((_, ended)) = process.ran.write_or_recover().as_mut() {
+            *ended = Some(Instant::now());
+        }
+        exit
+    }
+
+    fn read(
+        from: impl Read,
+        stream: Stream,
+        config: &JobConfig,
+        logs: &RwLock<Logs>,
+        log_file: &RwLock<Option<LogFile>>,
+        dispatch: &impl Fn(JobEvent),
+    ) {
+        // Bytes rather than `lines()`, which gives up on the first line that
+        // isn't UTF-8
+        let mut from = BufReader::new(from);
+        let mut bytes = vec![];
+        loop {
+            bytes.clear();
+            match from.read_until(b'\n', &mut bytes) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // Printing would land in the middle of the TUI
+                    logs.write_or_recover().push(LogLine::new(
+                        format!("[failed reading output: {e}]"),
+                        Stream::Stderr,
+                    ));
+                    break;
+                }
+            }
+
+            let text = String::from_utf8_lossy(&bytes);
+            let l = text.strip_suffix('\n').unwrap_or(&text);
+            let l = l.strip_suffix('\r').unwrap_or(l);
+            let l = match config.collapse_cr {
+                true => collapse_cr(l),
+                false => l,
+            };
+            let mut line = LogLine::parse(l, stream);
+            if config.timestamps {
+                line.time = Some(clock::time_of_day(SystemTime::now()));
+            }
+            dispatch(JobEvent::Line(line.text.clone()));
+            let written = match &mut *log_file.write_or_recover() {
+                Some(file) => {
+                    let text = match &line.time {
+                        Some(time) => format!("{time} {}", line.text),
+                        None => line.text.clone(),
+                    };
+                    file.write(&text).map_err(|e| (file.path.clone(), e))
+                }
+                None => Ok(()),
+            };
+            let mut logs = logs.write_or_recover();
+            logs.push(line);
+            if let Err((path, e)) = written {
+                logs.push(LogLine::new(
+                    format!(
+                        "[failed writing {}: {e}, no longer saving output]",
+                        path.display()
+                    ),
+                    Stream::Stderr,
+                ));
+            }
+        }
+    }
+
+    /// Progress shown next to the title, e.g. "retry 2/3 in 4s" or "exited 0
+    /// in 4.2s".
+    pub fn indicator(&self) -> Option<String> {
+        let retries = self.config.retries;
+        match &*self.status.read_or_recover() {
+            JobStatus::Waiting { on } => Some(format!("waiting for {}", on.join(", "))),
+            JobStatus::Running { attempt: 0 } => None,
+            JobStatus::Running { attempt } => Some(format!("retry {attempt}/{retries}")),
+            JobStatus::Retrying { attempt, at } => Some(format!(
+                "retry {attempt}/{retries} in {}",
+                countdown(at.saturating_duration_since(Instant::now()))
+            )),
+            JobStatus::Restarting { at } => Some(format!(
+                "restarting in {}",
+                countdown(at.saturating_duration_since(Instant::now()))
+            )),
+            JobStatus::Exited { status, took } => {
+                let exit = match status.code() {
+                    Some(code) => format!("exited {code}"),
+                    None => "killed by a signal".into(),
+                };
+                let mut exit = format!("{exit} in {:.1}s", took.as_secs_f32());
+                if !status.success() && retries > 0 {
+                    exit.push_str(&format!(" after {retries} retries"));
+                }
+                Some(exit)
+            }
+            JobStatus::Unstartable => Some("failed to start".into()),
+            JobStatus::Stopped => Some("stopped".into()),
+        }
+    }
+
+    /// One word (or so) for where the job is at, as the summary shows it.
+    pub fn state(&self) -> &'static str {
+        match &*self.status.read_or_recover() {
+            JobStatus::Waiting { .. } => "waiting",
+            JobStatus::Running { .. } => "running",
+            JobStatus::Retrying { .. } => "retrying",
+            JobStatus::Restarting { .. } => "restarting",
+            JobStatus::Exited { .. } => "exited",
+            JobStatus::Unstartable => "failed to start",
+            JobStatus::Stopped => "stopped",
+        }
+    }
+
+    /// The code the last run exited with, or "signal" if one killed it.
+    pub fn exit_code(&self) -> Option<String> {
+        match &*self.status.read_or_recover() {
+            JobStatus::Exited { status, .. } => Some(
+                status
+                    .code()
+                    .map_or_else(|| "signal".into(), |c| c.to_string()),
+            ),
+            _ => None,
+        }
+    }
+
+    /// The newest line the pane would show.
+    pub fn last_line(&self) -> Option<String> {
+        let logs = self.logs.read_or_recover();
+        let last = logs.iter().rev().find(|l| self.shows(l));
+        last.map(|l| l.text.clone())
+    }
+
+    /// Whether the job has exited unsuccessfully for good.
+    pub fn failed(&self) -> bool {
+        match &*self.status.read_or_recover() {
+            JobStatus::Exited { status, .. } => !status.success(),
+            JobStatus::Unstartable => true,
+            _ => false,
+        }
+    }
+
+    pub fn display_title(&self) -> String {
+        let mut title = match self.indicator() {
+            Some(i) => format!("{} — {i}", self.title),
+            None => self.title.clone(),
+        };
+        if !self.wrap {
+            match self.column {
+                0 => title.push_str(" [no wrap]"),
+                column => title.push_str(&format!(" [no wrap, +{column}]")),
+            }
+        }
+        if self.restart.load(Ordering::Relaxed) {
+            title.push_str(" [restart]");
+        }
+        match self.streams {
+            StreamFilter::Both => {}
+            StreamFilter::Stdout => title.push_str(" [stdout]"),
+            StreamFilter::Stderr => title.push_str(" [stderr]"),
+        }
+        match &self.filter {
+            Some(f) if f.is_regex() => title.push_str(&format!(" [filter: /{}/]", f.pattern)),
+            Some(f) => title.push_str(&format!(" [filter: \"{}\"]", f.pattern)),
+            None => {}
+        }
+        title
+    }
+
+    pub fn cycle_streams(&mut self) {
+        self.streams = self.streams.next();
+    }
+
+    /// Whether `line` gets past the stream filter and the line filter.
+    pub fn shows(&self, line: &LogLine) -> bool {
+        self.streams.shows(line.stream)
+            && self.filter.as_ref().is_none_or(|f| f.matches(&line.text))
+    }
+
+    /// Absolute numbers of the lines the pane shows, at most `height` of them.
+    pub fn visible_lines(&self, logs: &Logs, height: usize) -> Vec<usize> {
+        self.lines_from(logs, self.scroll, height)
+    }
+
+    fn lines_from(&self, logs: &Logs, scroll: Option<usize>, height: usize) -> Vec<usize> {
+        let shown = |n: &usize| logs.get(*n).is_some_and(|l| self.shows(l));
+
+        match scroll {
+            Some(top) => logs
+                .numbers(top..logs.end())
+                .filter(shown)
+                .take(height)
+                .collect(),
+            None => {
+                let mut lines = logs
+                    .numbers(0..logs.end())
+                    .rev()
+                    .filter(shown)
+                    .take(height)
+                    .collect::<Vec<_>>();
+                lines.reverse();
+                lines
+            }
+        }
+    }
+
+    /// Copies out what the pane shows, `height` lines tall, holding the
+    /// output's lock no longer than that takes so the reader threads can
+    /// carry on while it's drawn.
+    pub fn snapshot(&self, height: usize) -> Snapshot {
+        let logs = self.logs.read_or_recover();
+        let visible = self.visible_lines(&logs, height);
+        Snapshot {
+            position: self.position(&logs, &visible),
+            extent: self.extent(&logs, &visible),
+            usage: (logs.usage(), logs.full()),
+            lines: visible
+                .iter()
+                .filter_map(|&n| Some((n, logs.get(n)?.clone())))
+                .collect(),
+        }
+    }
+
+    /// "showing 120-140 of 1000" while scrolled away from the tail of a log
+    /// too long for the pane, counting the lines held that it shows.
+    pub fn position(&self, logs: &Logs, visible: &[usize]) -> Option<String> {
+        self.scroll?;
+        let (first, last) = (visible.first()?, visible.last()?);
+        let shown = self.shown_before(logs, logs.end());
+        if visible.len() >= shown {
+            return None;
+        }
+
+        Some(format!(
+            "showing {}-{} of {shown}",
+            self.shown_before(logs, *first) + 1,
+            self.shown_before(logs, *last) + 1,
+        ))
+    }
+
+    /// Where the pane's top line sits among the lines it shows, and how far
+    /// it could go. `None` when everything fits.
+    pub fn extent(&self, logs: &Logs, visible: &[usize]) -> Option<(usize, usize)> {
+        let first = visible.first()?;
+        let max = self
+            .shown_before(logs, logs.end())
+            .checked_sub(visible.len())
+            .filter(|&m| m > 0)?;
+        Some((self.shown_before(logs, *first).min(max), max))
+    }
+
+    /// How many of the lines held before line `n` the pane shows.
+    fn shown_before(&self, logs: &Logs, n: usize) -> usize {
+        // Every line, so no need to look at each one
+        if self.streams == StreamFilter::Both && self.filter.is_none() {
+            return logs.held_before(n);
+        }
+        logs.numbers(0..n)
+            .filter(|&n| logs.get(n).is_some_and(|l| self.shows(l)))
+            .count()
+    }
+
+    pub fn running(&self) -> bool {
+        !self.status.read_or_recover().finished()
+    }
+
+    /// Kills the process and cancels any retry or restart. The status
+    /// becomes [`JobStatus::Stopped`] once the process is gone.
+    pub fn stop(&self) {
+        self.restart.store(false, Ordering::Relaxed);
+        self.process.stop();
+    }
+
+    /// [`Job::stop`], but letting the process clean up after itself first.
+    pub fn terminate(&self) {
+        self.restart.store(false, Ordering::Relaxed);
+        self.process.terminate();
+    }
+
+    /// Drops the output so far, leaving the process running. The pane
+    /// follows whatever comes next.
+    pub fn clear(&mut self) {
+        self.logs.write_or_recover().clear();
+        self.stderr.write_or_recover().clear();
+        self.bookmarks.clear();
+        self.selection = None;
+        self.scroll = None;
+    }
+
+    /// Writes the line typed so far to the process, echoing it into the
+    /// output, and starts on the next one.
+    pub fn send_input(&mut self) -> anyhow::Result<()> {
+        let Some((cursor, line)) = self.input.as_mut() else {
+            return Ok(());
+        };
+        // Queueing the line can't block, and holding the lock until the echo
+        // is in keeps it ahead of any reply
+        let mut logs = self.logs.write_or_recover();
+        self.process.write_line(line)?;
+        logs.push(LogLine::note(format!("> {line}")));
+        *cursor = 0;
+        line.clear();
+        Ok(())
+    }
+
+    /// Turning restart off lets the job stop the next time it exits.
+    pub fn toggle_restart(&mut self) {
+        self.restart.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.column = 0;
+    }
+
+    /// Scrolls unwrapped output sideways by `delta` columns, does nothing
+    /// while wrapping.
+    pub fn scroll_columns(&mut self, delta: isize) {
+        if !self.wrap {
+            self.column = self.column.saturating_add_signed(delta);
+        }
+    }
+
+    //
+
+    /// The line bookmarks are placed at: the top of the pane when scrolled,
+    /// otherwise the newest line.
+    pub fn current_line(&self) -> Option<usize> {
+        self.scroll
+            .or_else(|| self.logs.read_or_recover().end().checked_sub(1))
+    }
+
+    pub fn toggle_bookmark(&mut self) {
+        if let Some(line) = self.current_line() {
+            if !self.bookmarks.remove(&line) {
+                self.bookmarks.insert(line);
+            }
+        }
+    }
+
+    /// Past the last bookmark this goes back to following the tail.
+    pub fn next_bookmark(&mut self) {
+        let from = self.current_line().map_or(0, |l| l + 1);
+        self.scroll = self.bookmarks.range(from..).next().copied();
+    }
+
+    pub fn prev_bookmark(&mut self) {
+        let to = self.current_line().unwrap_or(0);
+        if let Some(&line) = self.bookmarks.range(..to).next_back() {
+            self.scroll = Some(line);
+        }
+    }
+
+    /// Scrolls the next shown line matching [`Job::search`] to the top of
+    /// the pane. Unlike bookmarks, the last match is as far as this goes.
+    pub fn next_match(&mut self) {
+        self.jump_to_match(true);
+    }
+
+    pub fn prev_match(&mut self) {
+        self.jump_to_match(false);
+    }
+
+    fn jump_to_match(&mut self, forward: bool) {
+        let Some(pattern) = &self.search else {
+            return;
+        };
+        let current = self.current_line();
+        let logs = self.logs.read_or_recover();
+        let matches = |n: &usize| {
+            logs.get(*n).is_some_and(|l| {
+                self.shows(l) && !search::find_matches(&l.text, pattern).is_empty()
+            })
+        };
+        let found = match forward {
+            true => logs
+                .numbers(current.map_or(0, |l| l + 1)..logs.end())
+                .find(matches),
+            false => logs.numbers(0..current.unwrap_or(0)).rev().find(matches),
+        };
+        drop(logs);
+        if found.is_some() {
+            self.scroll = found;
+        }
+    }
+
+    //
+
+    /// Starts selecting at the current line, or drops the selection.
+    pub fn toggle_selection(&mut self) {
+        self.selection = match self.selection {
+            Some(_) => None,
+            None => self.current_line().map(|line| Selection {
+                anchor: line,
+                cursor: line,
+            }),
+        };
+    }
+
+    /// Moves the selection's cursor by `delta` shown lines, scrolling to
+    /// keep it in view.
+    pub fn extend_selection(&mut self, delta: isize) {
+        let Some(mut selection) = self.selection else {
+            return;
+        };
+        let logs = self.logs.clone();
+        let logs = logs.read_or_recover();
+        let shown = |n: &usize| logs.get(*n).is_some_and(|l| self.shows(l));
+
+        let from = selection.cursor;
+        let steps = delta.unsigned_abs();
+        selection.cursor = match delta < 0 {
+            true => logs.numbers(0..from).rev().filter(shown).take(steps).last(),
+            false => logs
+                .numbers(from + 1..logs.end())
+                .filter(shown)
+                .take(steps)
+                .last(),
+        }
+        .unwrap_or(from);
+
+        let visible = self.visible_lines(&logs, self.viewport.max(1));
+        let above = visible.first().is_some_and(|&top| selection.cursor < top);
+        let below = visible
+            .last()
+            .filter(|&&bottom| selection.cursor > bottom)
+            .map(|&bottom| {
+                logs.numbers(bottom + 1..selection.cursor + 1)
+                    .filter(shown)
+                    .count()
+            });
+        drop(logs);
+
+        self.selection = Some(selection);
+        if above {
+            self.scroll = Some(selection.cursor);
+        } else if let Some(below) = below {
+            self.scroll_by(below as isize);
+        }
+    }
+
+    /// The selected lines as text, one per line, skipping those the pane
+    /// hides.
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = self.selection?;
+        let logs = self.logs.read_or_recover();
+        let from = selection.anchor.min(selection.cursor);
+        let to = selection.anchor.max(selection.cursor);
+        Some(
+            logs.numbers(from..to + 1)
+                .filter_map(|n| logs.get(n))
+                .filter(|l| self.shows(l))
+                .map(|l| l.text.as_str())
+                .join("\n"),
+        )
+    }
+
+    /// The newest line containing [`JobConfig::error_pattern`], ignoring case.
+    /// Split output is looked through stderr first.
+    pub fn last_error(&self) -> Option<String> {
+        let pattern = self.config.error_pattern.to_lowercase();
+        [&self.stderr, &self.logs].into_iter().find_map(|logs| {
+            logs.read_or_recover()
+                .iter()
+                .rev()
+                .find(|l| l.text.to_lowercase().contains(&pattern))
+                .map(|l| l.text.clone())
+        })
+    }
+
+    /// Writes out the output held, one line each as the log file has it,
+    /// split output's stderr after the rest.
+    pub fn save_output(&self, path: &Path) -> io::Result<()> {
+        let mut file = LogFile::new(path.to_path_buf());
+        // Created even with nothing to write, for the pager to open
+        File::create(path)?;
+        for logs in [&self.logs, &self.stderr] {
+            for line in logs.read_or_recover().iter() {
+                match &line.time {
+                    Some(time) => file.write(&format!("{time} {}", line.text))?,
+                    None => file.write(&line.text)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Up to [`PINNED`] of the newest lines matching [`Job::pin`], oldest
+    /// first, copied out as [`Job::snapshot`] does. `None` without a pin.
+    pub fn pinned(&self) -> Option<Vec<LogLine>> {
+        let pin = self.pin.as_ref()?;
+        let logs = self.logs.read_or_recover();
+        let mut pinned = logs
+            .iter()
+            .rev()
+            .filter(|line| pin.matches(&line.text))
+            .take(PINNED)
+            .cloned()
+            .collect::<Vec<_>>();
+        pinned.reverse();
+        Some(pinned)
+    }
+
+    /// The newest `height` lines of [`Job::stderr`], copied out as
+    /// [`Job::snapshot`] does.
+    pub fn stderr_tail(&self, height: usize) -> Vec<LogLine> {
+        let stderr = self.stderr.read_or_recover();
+        let mut tail = stderr
+            .iter()
+            .rev()
+            .take(height)
+            .cloned()
+            .collect::<Vec<_>>();
+        tail.reverse();
+        tail
+    }
+
+    //
+
+    /// Moves the top of the pane by `delta` lines. Reaching the tail goes
+    /// back to following it.
+    pub fn scroll_by(&mut self, delta: isize) {
+        let logs = self.logs.clone();
+        let logs = logs.read_or_recover();
+        let Some(&tail) = self.tail_view(&logs).first() else {
+            return;
+        };
+
+        // Counted in lines held, stepping over any elided
+        let from = self.scroll.unwrap_or(tail);
+        let steps = delta.unsigned_abs();
+        let top = match delta < 0 {
+            true => logs.numbers(0..from).rev().take(steps).last(),
+            false => logs.numbers(from + 1..logs.end()).take(steps).last(),
+        }
+        .unwrap_or(from)
+        .max(logs.dropped);
+        self.scroll = (top < tail).then_some(top);
+    }
+
+    pub fn page_up(&mut self) {
+        self.scroll_by(-(self.viewport.max(1) as isize));
+    }
+
+    pub fn page_down(&mut self) {
+        self.scroll_by(self.viewport.max(1) as isize);
+    }
+
+    /// Jumps to the oldest line still held.
+    pub fn scroll_home(&mut self) {
+        let logs = self.logs.clone();
+        let logs = logs.read_or_recover();
+        let tail = self.tail_view(&logs).first().copied();
+        self.scroll = tail.filter(|&t| t > logs.dropped).map(|_| logs.dropped);
+    }
+
+    pub fn scroll_end(&mut self) {
+        self.scroll = None;
+    }
+
+    /// What the pane shows while following the tail.
+    fn tail_view(&self, logs: &Logs) -> Vec<usize> {
+        self.lines_from(logs, None, self.viewport.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::wait_until, toml};
+
+    fn filled(retention: Retention, count: usize) -> Logs {
+        filled_to(retention, LOG_CAP, count)
+    }
+
+    fn filled_to(retention: Retention, cap: usize, count: usize) -> Logs {
+        let mut logs = Logs::new(retention, cap);
+        (0..count).for_each(|i| logs.push(LogLine::note(i.to_string())));
+        logs
+    }
+
+    #[test]
+    fn keep_tail_drops_oldest() {
+        let logs = filled(Retention::KeepTail, 1500);
+        assert_eq!(logs.lines.len(), 1000);
+        assert_eq!(logs.lines.front().unwrap().text, "500");
+        assert_eq!(logs.get(1499).unwrap().text, "1499");
+        assert_eq!(logs.end(), 1500);
+    }
+
+    #[test]
+    fn keep_head_drops_newest() {
+        let logs = filled(Retention::KeepHead, 1500);
+        assert_eq!(logs.lines.len(), 1001);
+        assert_eq!(logs.lines[999].text, "999");
+        assert_eq!(logs.lines[1000].text, "[... 500 lines elided ...]");
+    }
+
+    #[test]
+    fn both_ends_elides_middle() {
+        let logs = filled(Retention::BothEnds, 1500);
+        assert_eq!(logs.lines.len(), 501);
+        assert_eq!(logs.lines[499].text, "499");
+        assert_eq!(logs.lines[500].text, "[... 500 lines elided ...]");
+        assert_eq!(logs.tail.len(), 500);
+        assert_eq!(logs.get(1000).unwrap().text, "1000");
+        assert_eq!(logs.get(1499).unwrap().text, "1499");
+        assert_eq!(logs.end(), 1500);
+        assert_eq!(logs.numbers(0..logs.end()).count(), 1001);
+    }
+
+    #[test]
+    fn usage_counts_held_lines_and_says_when_full() {
+        let logs = filled(Retention::KeepTail, 10);
+        assert_eq!(
+            (logs.usage().as_str(), logs.full()),
+            ("10/1000 lines", false)
+        );
+
+        for retention in [
+            Retention::KeepTail,
+            Retention::KeepHead,
+            Retention::BothEnds,
+        ] {
+            let mut logs = filled(retention, 1500);
+            assert_eq!(logs.held(), 1000);
+            assert_eq!(logs.usage(), "1000/1000 lines, full");
+            logs.clear();
+            assert!(!logs.full());
+        }
+    }
+
+    #[test]
+    fn collapses_progress_bars() {
+        let bar = (0..=10)
+            .map(|i| format!("downloading [{:<10}] {:>3}%", "#".repeat(i), i * 10))
+            .collect::<Vec<_>>()
+            .join("\r");
+        assert_eq!(collapse_cr(&bar), "downloading [##########] 100%");
+        assert_eq!(
+            collapse_cr(&format!("{bar}\r")),
+            "downloading [##########] 100%"
+        );
+    }
+
+    #[test]
+    fn collapse_leaves_plain_lines() {
+        assert_eq!(collapse_cr("plain"), "plain");
+        assert_eq!(collapse_cr("crlf\r"), "crlf");
+        assert_eq!(collapse_cr(""), "");
+        assert_eq!(collapse_cr("\r\r"), "");
+    }
+
+    #[test]
+    fn countdowns_round_up() {
+        assert_eq!(countdown(Duration::from_millis(500)), "1s");
+        assert_eq!(countdown(Duration::from_millis(2001)), "3s");
+        assert_eq!(countdown(Duration::from_secs(2)), "2s");
+        assert_eq!(countdown(Duration::ZERO), "0s");
+    }
+
+    #[test]
+    fn stream_filter_hides_other_stream() {
+        let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+        let mut logs = Logs::default();
+        for i in 0..6 {
+            let stream = [Stream::Stdout, Stream::Stderr][i % 2];
+            logs.push(LogLine::new(i.to_string(), stream));
+        }
+
+        assert_eq!(job.visible_lines(&logs, 3), [3, 4, 5]);
+        job.cycle_streams();
+        assert_eq!(job.visible_lines(&logs, 3), [0, 2, 4]);
+        job.cycle_streams();
+        assert_eq!(job.visible_lines(&logs, 2), [3, 5]);
+        job.scroll = Some(0);
+        assert_eq!(job.visible_lines(&logs, 2), [1, 3]);
+        assert!(job.display_title().ends_with("[stderr]"));
+    }
+
+    #[test]
+    fn position_only_while_scrolled_through_long_logs() {
+        let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+        let logs = filled(Retention::KeepTail, 1000);
+
+        let tail = job.visible_lines(&logs, 20);
+        assert_eq!(job.position(&logs, &tail), None);
+
+        job.scroll = Some(119);
+        let visible = job.visible_lines(&logs, 21);
+        assert_eq!(
+            job.position(&logs, &visible).as_deref(),
+            Some("showing 120-140 of 1000")
+        );
+
+        let short = filled(Retention::KeepTail, 10);
+        job.scroll = Some(0);
+        let visible = job.visible_lines(&short, 20);
+        assert_eq!(job.position(&short, &visible), None);
+    }
+
+    #[test]
+    fn pages_through_scrollback_and_back_to_the_tail() {
+        let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+        *job.logs.write_or_recover() = filled(Retention::KeepTail, 100);
+        job.viewport = 20;
+
+        job.page_up();
+        assert_eq!(job.scroll, Some(60));
+        job.page_up();
+        job.page_up();
+        job.page_up();
+        job.page_up();
+        assert_eq!(job.scroll, Some(0));
+
+        job.page_down();
+        assert_eq!(job.scroll, Some(20));
+        job.scroll_by(100);
+        assert_eq!(job.scroll, None);
+
+        job.scroll_home();
+        assert_eq!(job.scroll, Some(0));
+        job.scroll_end();
+        assert_eq!(job.scroll, None);
+
+        // Nothing to scroll through when it all fits
+        *job.logs.write_or_recover() = filled(Retention::KeepTail, 5);
+        job.page_up();
+        job.scroll_home();
+        assert_eq!(job.scroll, None);
+    }
+
+    #[test]
+    fn config_round_trips_through_table() {
+        let config = JobConfig::new("cargo", &["run", "--bin", "sites"])
+            .retries(3, Duration::from_millis(1500))
+            .retention(Retention::BothEnds)
+            .collapse_cr(false)
+            .log_cap(5000)
+            .log_dir(".itask/logs")
+            .restart(true)
+            .detach(true)
+            .shell(true)
+            .timestamps(true)
+            .stdin(true)
+            .error_pattern("panicked")
+            .output(OutputLayout::Split)
+            .color("WARN", Color::Yellow)
+            .color("ERROR", Color::Rgb(0xff, 0x44, 0))
+            .color("DEBUG", Color::DarkGray)
+            .cwd("sites")
+            .env("RUST_LOG", "debug");
+        let back = JobConfig::from_table(&config.to_table()).unwrap();
+        assert_eq!(format!("{back:?}"), format!("{config:?}"));
+
+        let config = JobConfig::new("npm", &["run", "dev"])
+            .name("frontend")
+            .after(&["backend"])
+            .ready_when(Readiness::command("curl", &["-sf", "localhost:5173"]));
+        let back = JobConfig::from_table(&config.to_table()).unwrap();
+        assert_eq!(format!("{back:?}"), format!("{config:?}"));
+
+        assert!(JobConfig::from_table(&Table::new()).is_err());
+    }
+
+    #[test]
+    fn the_first_matching_prefix_colors_a_line() {
+        let config = JobConfig::new("cargo", &[])
+            .color("WARN", Color::Yellow)
+            .color("WARNING", Color::Magenta)
+            .color("ERROR", Color::Red);
+        assert_eq!(config.line_color("WARNING: unused"), Some(Color::Yellow));
+        assert_eq!(config.line_color("  ERROR boom"), Some(Color::Red));
+        assert_eq!(config.line_color("no ERROR here"), None);
+
+        let t = toml::parse("command = \"a\"\ncolors = [{ prefix = \"WARN\" }]\n").unwrap();
+        let err = JobConfig::from_table(&t).unwrap_err();
+        assert_eq!(err.to_string(), "`colors` entries need a `color`");
+    }
+
+    #[test]
+    fn under_cap_keeps_everything() {
+        for retention in [
+            Retention::KeepTail,
+            Retention::KeepHead,
+            Retention::BothEnds,
+        ] {
+            let logs = filled(retention, 1000);
+            assert_eq!(logs.lines.len(), 1000);
+            assert_eq!(logs.elided, 0);
+        }
+    }
+
+    #[test]
+    fn cap_comes_from_the_config() {
+        let logs = filled_to(Retention::KeepTail, 10, 25);
+        assert_eq!(logs.lines.len(), 10);
+        assert_eq!(logs.lines.front().unwrap().text, "15");
+
+        let logs = filled_to(Retention::BothEnds, 10, 25);
+        assert_eq!(logs.held(), 10);
+        assert_eq!(logs.lines[4].text, "4");
+        assert_eq!(logs.lines[5].text, "[... 15 lines elided ...]");
+        assert_eq!(logs.tail.back().unwrap().text, "24");
+
+        let logs = filled_to(Retention::KeepHead, usize::MAX, 3000);
+        assert_eq!(logs.lines.len(), 3000);
+        assert_eq!(logs.elided, 0);
+
+        let mut t = JobConfig::new("sh", &[]).to_table();
+        t.insert("log_cap".into(), Value::Integer(0));
+        assert!(JobConfig::from_table(&t).is_err());
+    }
+
+    #[test]
+    fn selection_extends_and_copies_whole_lines() {
+        let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+        *job.logs.write_or_recover() = filled(Retention::KeepTail, 100);
+        job.viewport = 10;
+        job.scroll = Some(50);
+
+        job.toggle_selection();
+        job.extend_selection(2);
+        assert_eq!(job.selected_text().as_deref(), Some("50\n51\n52"));
+
+        // Moving past the top scrolls up to keep the cursor in view
+        job.extend_selection(-5);
+        assert_eq!(job.scroll, Some(47));
+        assert_eq!(job.selected_text().as_deref(), Some("47\n48\n49\n50"));
+
+        job.extend_selection(20);
+        let logs = job.logs.clone();
+        assert_eq!(
+            job.visible_lines(&logs.read_or_recover(), 10).last(),
+            Some(&67)
+        );
+
+        job.toggle_selection();
+        assert_eq!(job.selected_text(), None);
+    }
+
+    #[test]
+    fn missing_cwd_fails_without_panicking() {
+        let job = Job::start(
+            JobConfig::new("true", &[]).cwd("does/not/exist"),
+            Default::default(),
+        );
+        wait_until(|| !job.running());
+
+        assert!(job.failed());
+        assert_eq!(job.indicator().as_deref(), Some("failed to start"));
+        let logs = job.logs.read_or_recover();
+        let line = logs.lines.back().unwrap();
+        assert_eq!(line.stream, Stream::Stderr);
+        assert_eq!(
+            line.text,
+            "[failed to start: `does/not/exist` is not a directory]"
+        );
+    }
+
+    #[test]
+    fn missing_program_fails_without_panicking() {
+        let job = Job::start(
+            JobConfig::new("itask-no-such-program", &[]),
+            Default::default(),
+        );
+        wait_until(|| !job.running());
+
+        assert!(job.failed());
+        let logs = job.logs.read_or_recover();
+        let line = &logs.lines.back().unwrap().text;
+        assert!(
+            line.starts_with("[failed to start `itask-no-such-program`: "),
+            "{line}"
+        );
+    }
+
+    #[test]
+    fn extent_only_when_the_log_overflows() {
+        let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+        let short = filled(Retention::KeepTail, 10);
+        assert_eq!(job.extent(&short, &job.visible_lines(&short, 10)), None);
+
+        let logs = filled(Retention::KeepTail, 1500);
+        assert_eq!(
+            job.extent(&logs, &job.visible_lines(&logs, 10)),
+            Some((990, 990))
+        );
+        job.scroll = Some(600);
+        assert_eq!(
+            job.extent(&logs, &job.visible_lines(&logs, 10)),
+            Some((100, 990))
+        );
+    }
+
+    #[test]
+    fn snapshots_copy_the_pane_out_from_under_the_lock() {
+        let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+        *job.logs.write_or_recover() = filled(Retention::KeepTail, 1500);
+        job.scroll = Some(600);
+
+        let snapshot = job.snapshot(10);
+        assert!(job.logs.try_write().is_ok());
+        assert_eq!(
+            snapshot.lines.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+            (600..610).collect::<Vec<_>>()
+        );
+        assert_eq!(snapshot.lines[0].1.text, "600");
+        assert_eq!(
+            snapshot.position.as_deref(),
+            Some("showing 101-110 of 1000")
+        );
+        assert_eq!(snapshot.extent, Some((100, 990)));
+    }
+
+    #[test]
+    fn shell_lines_quote_what_a_shell_would_mangle() {
+        let config = JobConfig::new("cargo", &["run", "--bin", "sites"]);
+        assert_eq!(config.shell_line(), "cargo run --bin sites");
+
+        let config = JobConfig::new("echo", &["it's", "", "a b", "$HOME"])
+            .env("RUST_LOG", "debug")
+            .env("GREETING", "hello there")
+            .cwd("/tmp/my dir");
+        assert_eq!(
+            config.shell_line(),
+            r"cd '/tmp/my dir' && GREETING='hello there' RUST_LOG=debug echo 'it'\''s' '' 'a b' '$HOME'"
+        );
+
+        let config = JobConfig::new("ls | wc -l", &[]).shell(true);
+        assert_eq!(config.shell_line(), "ls | wc -l");
+    }
+
+    #[test]
+    fn titles_cut_long_commands_on_char_boundaries() {
+        let job = Job::start(JobConfig::new("echo", &["ééééééé"]), Default::default());
+        assert_eq!(job.title, "echo éé...");
+        assert_eq!(job.command, "echo ééééééé");
+
+        let job = Job::start(JobConfig::new("true", &[]), Default::default());
+        assert_eq!(job.title, "true");
+    }
+
+    #[test]
+    fn output_is_copied_to_the_log_dir() {
+        let dir = std::env::temp_dir().join(format!("itask-log-dir-{}", std::process::id()));
+        let job = Job::start(
+            JobConfig::new("printf", &["one\\ntwo\\n"])
+                .name("build")
+                .log_dir(&dir),
+            Default::default(),
+        );
+        wait_until(|| !job.running());
+
+        let path = job.log_file.clone().unwrap();
+        assert!(path.starts_with(&dir));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn lines_past_the_marker_keep_their_numbers() {
+        let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+        *job.logs.write_or_recover() = filled(Retention::BothEnds, 1500);
+        job.scroll = Some(1200);
+        job.toggle_bookmark();
+
+        let mut logs = job.logs.write_or_recover();
+        (1500..1600).for_each(|i| logs.push(LogLine::note(i.to_string())));
+        assert_eq!(logs.get(1200).unwrap().text, "1200");
+        assert_eq!(logs.end(), 1600);
+        drop(logs);
+
+        job.scroll = None;
+        job.prev_bookmark();
+        assert_eq!(job.scroll, Some(1200));
+
+        // Up from the oldest line past the marker is the marker
+        job.scroll = Some(1100);
+        job.scroll_by(-1);
+        assert_eq!(job.scroll, Some(500));
+    }
+
+    #[test]
+    fn clearing_starts_the_output_fresh() {
+        let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+        *job.logs.write_or_recover() = filled(Retention::BothEnds, 1500);
+        job.scroll = Some(20);
+        job.toggle_bookmark();
+
+        job.clear();
+        assert_eq!(job.scroll, None);
+        assert!(job.bookmarks.is_empty());
+        let mut logs = job.logs.write_or_recover();
+        assert!(logs.lines.is_empty());
+        assert_eq!(logs.end(), 1500);
+
+        // Fills up to the cap again before eliding anything
+        (0..1000).for_each(|i| logs.push(LogLine::note(i.to_string())));
+        assert_eq!(logs.elided, 0);
+        assert_eq!(logs.get(1500).unwrap().text, "0");
+    }
+
+    #[test]
+    fn stopping() {
+        let job = Job::start(
+            JobConfig::new("sleep", &["5"]).restart(true),
+            Shared::default(),
+        );
+        wait_until(|| job.process.child.read_or_recover().is_some());
+
+        let pid = job.process.pid();
+        assert!(pid.is_some() && job.process.alive());
+
+        job.stop();
+        wait_until(|| !job.running());
+        assert!(matches!(*job.status.read_or_recover(), JobStatus::Stopped));
+        assert_eq!(job.process.pid(), pid);
+        assert!(!job.process.alive());
+        assert_eq!(job.indicator().as_deref(), Some("stopped"));
+        assert!(!job.failed());
+    }
+
+    #[test]
+    fn shell_jobs_get_pipes() {
+        let job = Job::start(
+            JobConfig::new("echo one two | tr", &["o", "0"]).shell(true),
+            Shared::default(),
+        );
+        wait_until(|| !job.running());
+        let logs = job.logs.read_or_recover();
+        assert_eq!(logs.lines.back().unwrap().text, "0ne tw0");
+    }
+}
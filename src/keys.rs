@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use ratatui::crossterm::event::KeyCode;
+
+use crate::toml::Table;
+
+/// What a key does while no prompt, palette or help is open. Menu
+/// navigation (arrows, Enter and Esc) isn't remappable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Menu,
+    Palette,
+    NextPane,
+    PrevPane,
+    Summary,
+    Help,
+    Layout,
+    Rerun,
+    Quit,
+    Search,
+    NextMatch,
+    PrevMatch,
+    Filter,
+    Clear,
+    Stop,
+    Wrap,
+    Streams,
+    Restart,
+    Bookmark,
+    PrevBookmark,
+    NextBookmark,
+    Select,
+    Copy,
+    CopyError,
+    Pager,
+    Pin,
+    Input,
+    PageUp,
+    PageDown,
+    ScrollHome,
+    ScrollEnd,
+}
+
+/// Every binding with its name in `[keys]` and its default key.
+const DEFAULTS: &[(&str, Binding, KeyCode)] = &[
+    ("menu", Binding::Menu, KeyCode::Char('j')),
+    ("palette", Binding::Palette, KeyCode::Char(':')),
+    ("next_pane", Binding::NextPane, KeyCode::Tab),
+    ("prev_pane", Binding::PrevPane, KeyCode::BackTab),
+    ("summary", Binding::Summary, KeyCode::Char('d')),
+    ("help", Binding::Help, KeyCode::Char('?')),
+    ("layout", Binding::Layout, KeyCode::Char('t')),
+    ("rerun", Binding::Rerun, KeyCode::Char('R')),
+    ("quit", Binding::Quit, KeyCode::Char('q')),
+    ("search", Binding::Search, KeyCode::Char('/')),
+    ("next_match", Binding::NextMatch, KeyCode::Char('n')),
+    ("prev_match", Binding::PrevMatch, KeyCode::Char('N')),
+    ("filter", Binding::Filter, KeyCode::Char('f')),
+    ("clear", Binding::Clear, KeyCode::Char('c')),
+    ("stop", Binding::Stop, KeyCode::Char('x')),
+    ("wrap", Binding::Wrap, KeyCode::Char('w')),
+    ("streams", Binding::Streams, KeyCode::Char('s')),
+    ("restart", Binding::Restart, KeyCode::Char('r')),
+    ("bookmark", Binding::Bookmark, KeyCode::Char('m')),
+    ("prev_bookmark", Binding::PrevBookmark, KeyCode::Char('[')),
+    ("next_bookmark", Binding::NextBookmark, KeyCode::Char(']')),
+    ("select", Binding::Select, KeyCode::Char('v')),
+    ("copy", Binding::Copy, KeyCode::Char('y')),
+    ("copy_error", Binding::CopyError, KeyCode::Char('e')),
+    ("pager", Binding::Pager, KeyCode::Char('p')),
+    ("pin", Binding::Pin, KeyCode::Char('P')),
+    ("input", Binding::Input, KeyCode::Char('i')),
+    ("page_up", Binding::PageUp, KeyCode::PageUp),
+    ("page_down", Binding::PageDown, KeyCode::PageDown),
+    ("scroll_home", Binding::ScrollHome, KeyCode::Home),
+    ("scroll_end", Binding::ScrollEnd, KeyCode::End),
+];
+
+/// Which key runs which [`Binding`], the defaults with the `[keys]` table of
+/// `itask.toml` on top. Each entry moves a binding to a new key, taking the
+/// key from whatever had it before:
+///
+/// ```toml
+/// [keys]
+/// menu = "m"
+/// bookmark = "b"
+/// next_pane = "F2"
+/// ```
+///
+/// Keys are a single character, or `Tab`, `BackTab`, `Space`, `Enter`,
+/// `Esc`, `Backspace`, `Delete`, `Insert`, `Home`, `End`, `PageUp`,
+/// `PageDown`, an arrow (`Up`, `Down`, `Left`, `Right`) or `F1` to `F12`.
+/// The help overlay and the status bar show the keys as remapped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keys(HashMap<KeyCode, Binding>);
+
+impl Default for Keys {
+    fn default() -> Self {
+        Self(
+            DEFAULTS
+                .iter()
+                .map(|&(_, binding, code)| (code, binding))
+                .collect(),
+        )
+    }
+}
+
+impl Keys {
+    pub fn from_table(t: &Table) -> anyhow::Result<Self> {
+        let mut keys = Self::default();
+        for (name, value) in t {
+            let binding = DEFAULTS
+                .iter()
+                .find(|(n, ..)| n == name)
+                .map(|&(_, binding, _)| binding)
+                .with_context(|| format!("unknown key binding `{name}`"))?;
+            let key = value
+                .as_str()
+                .with_context(|| format!("`{name}` must be a string"))?;
+            let code = parse_key(key).with_context(|| format!("`{key}` isn't a key"))?;
+            keys.0.retain(|_, b| *b != binding);
+            keys.0.insert(code, binding);
+        }
+        Ok(keys)
+    }
+
+    pub fn get(&self, code: KeyCode) -> Option<Binding> {
+        self.0.get(&code).copied()
+    }
+
+    /// The key `binding` is on, if another binding hasn't taken it.
+    pub fn key_for(&self, binding: Binding) -> Option<KeyCode> {
+        self.0
+            .iter()
+            .find(|&(_, b)| *b == binding)
+            .map(|(&code, _)| code)
+    }
+}
+
+/// How the help writes `code`, the way it's typed into `[keys]` except for
+/// a few shorthands.
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".into(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::BackTab => "Shift+Tab".into(),
+        KeyCode::PageUp => "PgUp".into(),
+        KeyCode::PageDown => "PgDn".into(),
+        KeyCode::Up => "↑".into(),
+        KeyCode::Down => "↓".into(),
+        KeyCode::Left => "←".into(),
+        KeyCode::Right => "→".into(),
+        KeyCode::Esc => "Esc".into(),
+        KeyCode::F(n) => format!("F{n}"),
+        code => format!("{code:?}"),
+    }
+}
+
+fn parse_key(key: &str) -> Option<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+
+    Some(match key {
+        "Tab" => KeyCode::Tab,
+        "BackTab" => KeyCode::BackTab,
+        "Space" => KeyCode::Char(' '),
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        "Insert" => KeyCode::Insert,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        _ => match key.strip_prefix('F').and_then(|n| n.parse().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => return None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::toml;
+
+    use super::*;
+
+    fn keys(src: &str) -> anyhow::Result<Keys> {
+        Keys::from_table(&toml::parse(src)?)
+    }
+
+    #[test]
+    fn remapping_moves_a_binding() {
+        let keys = keys("menu = \"m\"\nbookmark = \"b\"\nnext_pane = \"F2\"\n").unwrap();
+        assert_eq!(keys.get(KeyCode::Char('m')), Some(Binding::Menu));
+        assert_eq!(keys.get(KeyCode::Char('j')), None);
+        assert_eq!(keys.get(KeyCode::Char('b')), Some(Binding::Bookmark));
+        assert_eq!(keys.get(KeyCode::F(2)), Some(Binding::NextPane));
+        assert_eq!(keys.get(KeyCode::Tab), None);
+        assert_eq!(keys.get(KeyCode::Char('q')), Some(Binding::Quit));
+    }
+
+    #[test]
+    fn names_the_key_a_binding_is_on() {
+        let keys = keys("menu = \"m\"\nhelp = \"F1\"\nwrap = \"q\"\n").unwrap();
+        let name = |binding| keys.key_for(binding).map(key_name);
+        assert_eq!(name(Binding::Menu).as_deref(), Some("m"));
+        assert_eq!(name(Binding::Help).as_deref(), Some("F1"));
+        assert_eq!(name(Binding::PrevPane).as_deref(), Some("Shift+Tab"));
+        assert_eq!(name(Binding::PageUp).as_deref(), Some("PgUp"));
+        assert_eq!(name(Binding::Quit), None);
+    }
+
+    #[test]
+    fn reports_bad_entries() {
+        let err = |src| format!("{:#}", keys(src).unwrap_err());
+        assert_eq!(err("launch = \"l\""), "unknown key binding `launch`");
+        assert_eq!(err("menu = 1"), "`menu` must be a string");
+        assert_eq!(err("menu = \"F13\""), "`F13` isn't a key");
+    }
+}
@@ -0,0 +1,1756 @@
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use clipboard::Clipboard;
+use itertools::Itertools;
+use job::{Job, LogLine, Shared, Stream};
+use keys::key_name;
+use lock::Recover;
+use logfile::LogFile;
+use ratatui::{
+    crossterm::event::{
+        self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    layout::{Constraint, Direction, Layout, Margin, Position, Rect},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{
+        Block, Cell, Clear, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState, Table,
+        TableState, Tabs, Wrap,
+    },
+    Frame,
+};
+use regex::Regex;
+use search::Filter;
+use ui::{config_menu, key_summary, main_menu, Help, History, Input, Menu, Palette};
+mod ansi;
+mod app;
+mod cli;
+mod clipboard;
+mod clock;
+mod config;
+mod deps;
+mod fuzzy;
+mod hooks;
+mod job;
+mod keys;
+mod lock;
+mod logfile;
+mod regex;
+mod search;
+mod session;
+mod theme;
+mod toml;
+mod ui;
+
+#[cfg(test)]
+mod tests;
+
+pub use app::{App, Pacing};
+pub use cli::{exit_code, job_list, Args, USAGE};
+pub use config::{Config, Group};
+pub use deps::Readiness;
+pub use hooks::{Hook, HookTrigger, JobEvent};
+pub use job::{JobConfig, OutputLayout, Retention};
+pub use keys::{Binding, Keys};
+pub use session::{Session, SessionRestore};
+pub use theme::Theme;
+pub use ui::{Fraction, Progress, Prompt};
+
+const BANNER: &str = include_str!("../banner");
+
+/// Splits `area` into `count` panes, as square a grid as fits them, filled
+/// row by row. A short last row shares its width between fewer panes.
+fn grid(area: Rect, count: usize) -> Vec<Rect> {
+    let cols = (1..=count).find(|c| c * c >= count).unwrap_or(1);
+    let rows = count.div_ceil(cols);
+
+    Layout::vertical(vec![Constraint::Fill(1); rows])
+        .split(area)
+        .iter()
+        .enumerate()
+        .flat_map(|(row, area)| {
+            let cols = cols.min(count - row * cols);
+            Layout::horizontal(vec![Constraint::Fill(1); cols])
+                .split(*area)
+                .to_vec()
+        })
+        .collect()
+}
+
+/// The smallest pane worth drawing, in columns and rows. Below this the
+/// summary table is shown instead.
+const MIN_PANE: (u16, u16) = (20, 5);
+
+/// The narrowest the menu gets. It takes 30% of the screen above this.
+const MIN_MENU_WIDTH: u16 = 28;
+
+/// Left for the panes beside the menu. Any narrower and the menu is drawn
+/// over them instead.
+const MIN_JOBS_WIDTH: u16 = 40;
+
+/// Below this, in columns and rows, nothing is drawn but a note to make the
+/// terminal bigger.
+const MIN_SIZE: (u16, u16) = (32, 8);
+
+/// How job panes share the screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaneLayout {
+    /// All of them at once, in a grid.
+    #[default]
+    Split,
+    /// One at a time, the focused one, under a row of tabs.
+    Tabs,
+}
+
+impl PaneLayout {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "split" => Some(PaneLayout::Split),
+            "tabs" => Some(PaneLayout::Tabs),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to focus when a new job starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FocusPolicy {
+    /// Always move focus to the job that just started.
+    FollowNewest,
+    /// Only focus a new job if nothing is focused yet.
+    #[default]
+    KeepCurrent,
+    /// Move focus to a new job once it prints its first line.
+    FollowFirstOutput,
+}
+
+/// What [`Model::rerun`] does when the last job is still running.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RerunPolicy {
+    /// Leave it be, with a note in its pane.
+    #[default]
+    Refuse,
+    /// Stop it and start it over.
+    Restart,
+}
+
+/// Input for [`Model::update`], the parts of an [`Event`] itask reacts to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Paste(String),
+}
+
+impl Action {
+    /// `None` for events that only need a redraw, like a resize.
+    pub fn from_event(event: Event) -> Option<Self> {
+        match event {
+            Event::Key(key) => Some(Action::Key(key)),
+            Event::Mouse(mouse) => Some(Action::Mouse(mouse)),
+            Event::Paste(text) => Some(Action::Paste(text)),
+            _ => None,
+        }
+    }
+}
+
+/// Set whenever something on screen may have changed, so the event loop
+/// only redraws when there's something new to show.
+#[derive(Clone, Debug, Default)]
+pub struct Dirty(Arc<AtomicBool>);
+
+impl Dirty {
+    pub fn mark(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether anything changed since the last call.
+    pub fn take(&self) -> bool {
+        self.0.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[derive(Default)]
+pub struct Model {
+    jobs: RwLock<Vec<Job>>,
+    focused: RwLock<Option<usize>>,
+    focus_policy: RwLock<FocusPolicy>,
+    pending_focus: RwLock<Option<usize>>,
+    /// The pane and config of the job started last, for [`Model::rerun`].
+    last_run: RwLock<Option<(usize, JobConfig)>>,
+    rerun_policy: RwLock<RerunPolicy>,
+    layout: RwLock<PaneLayout>,
+    /// Split panes narrower than this give way to tabs.
+    min_pane_width: RwLock<Option<u16>>,
+    /// Whether the last frame fell back to tabs for want of width.
+    squeezed: RwLock<bool>,
+    shared: Shared,
+    session: RwLock<Option<Session>>,
+    config: RwLock<Option<Config>>,
+    /// Set on every job started from now on, under the job's own `env`.
+    env: RwLock<BTreeMap<String, String>>,
+    prompt: RwLock<Option<Prompt>>,
+    progress: RwLock<Option<Progress>>,
+    /// What was entered into each kind of prompt, see [`Model::history`].
+    histories: RwLock<BTreeMap<&'static str, History>>,
+    palette: RwLock<Option<Palette>>,
+    menu: RwLock<Option<usize>>,
+    /// Built on first use and kept, see [`Model::main_menu`].
+    built_menu: RwLock<Option<Arc<Menu>>>,
+    help: RwLock<bool>,
+    /// The highlighted row while the summary table replaces the panes.
+    summary: RwLock<Option<usize>>,
+    /// Where each pane and the menu were last drawn, for mouse clicks.
+    panes: RwLock<Vec<Rect>>,
+    menu_area: RwLock<Option<Rect>>,
+    /// A message the status bar shows until it's [`FLASH`] old.
+    flash: RwLock<Option<(String, Instant)>>,
+    quit: RwLock<bool>,
+    /// A file for [`App::run`] to hand the terminal over to a pager for.
+    page: RwLock<Option<PathBuf>>,
+    clipboard: Clipboard,
+}
+
+/// How long a [`Model::flash`] message stays up.
+pub const FLASH: Duration = Duration::from_secs(2);
+
+impl Model {
+    // | ------- | ------- | --- |
+    // |  Job 1  |  Job 2  |  J  |
+    // | ~~~~~~~ | ~~~~~~~ |  O  |
+    // |  Job 3  |  ...    |  B  |
+    // | ------- | ------- | --- |
+
+    /// Launches a job alongside any already running, returning its index.
+    pub fn start_job(&self, config: JobConfig) -> anyhow::Result<usize> {
+        let job = self.launch(config.clone())?;
+        let mut jobs = self.jobs.write_or_recover();
+        jobs.push(job);
+        let idx = jobs.len() - 1;
+        drop(jobs);
+
+        *self.last_run.write_or_recover() = Some((idx, config));
+        self.focus_started(idx);
+        Ok(idx)
+    }
+
+    fn launch(&self, mut config: JobConfig) -> anyhow::Result<Job> {
+        for (key, value) in self.env.read_or_recover().iter() {
+            config
+                .env
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        if let Some(name) = &config.name {
+            self.shared.deps.add(name, &config.after)?;
+        }
+        Ok(Job::start(config, self.shared.clone()))
+    }
+
+    pub fn set_rerun_policy(&self, policy: RerunPolicy) {
+        *self.rerun_policy.write_or_recover() = policy;
+    }
+
+    /// Starts the last job again in its own pane, with fresh output and the
+    /// current env. See [`RerunPolicy`] for when it's still running.
+    pub fn rerun(&self) -> anyhow::Result<()> {
+        let Some((idx, config)) = self.last_run.read_or_recover().clone() else {
+            return Ok(());
+        };
+
+        let mut jobs = self.jobs.write_or_recover();
+        let Some(last) = jobs.get(idx) else {
+            return Ok(());
+        };
+        if last.running() {
+            match *self.rerun_policy.read_or_recover() {
+                RerunPolicy::Refuse => {
+                    last.logs
+                        .write_or_recover()
+                        .push(LogLine::note("[still running, not started again]"));
+                    self.shared.dirty.mark();
+                    return Ok(());
+                }
+                RerunPolicy::Restart => last.stop(),
+            }
+        }
+
+        jobs[idx] = self.launch(config)?;
+        drop(jobs);
+        *self.focused.write_or_recover() = Some(idx);
+        Ok(())
+    }
+
+    /// Stops the job in pane `idx` and starts it again there, with fresh
+    /// output and the env it was started with.
+    pub fn restart_job(&self, idx: usize) -> anyhow::Result<()> {
+        let mut jobs = self.jobs.write_or_recover();
+        let Some(job) = jobs.get(idx) else {
+            return Ok(());
+        };
+        job.stop();
+        let config = job.config.clone();
+        jobs[idx] = self.launch(config)?;
+        self.shared.dirty.mark();
+        Ok(())
+    }
+
+    /// Panes whose job is still running, waiting and retrying included.
+    fn active(&self) -> Vec<usize> {
+        let jobs = self.jobs.read_or_recover();
+        (0..jobs.len()).filter(|&idx| jobs[idx].running()).collect()
+    }
+
+    /// [`Model::restart_job`] for every running job, each keeping its pane.
+    pub fn restart_all(&self) -> anyhow::Result<()> {
+        self.active()
+            .into_iter()
+            .try_for_each(|idx| self.restart_job(idx))
+    }
+
+    /// Stops every running job once confirmed, as quitting would.
+    pub fn stop_all(self: &Arc<Self>) {
+        let active = self.active();
+        if active.is_empty() {
+            return;
+        }
+
+        let model = Arc::downgrade(self);
+        let title = format!("Stop {} running job(s)?", active.len());
+        self.open_prompt(Prompt::confirm(&title, move |stop| {
+            if let Some(model) = model.upgrade().filter(|_| stop) {
+                let jobs = model.jobs.read_or_recover();
+                active
+                    .iter()
+                    .filter_map(|&idx| jobs.get(idx))
+                    .for_each(Job::stop);
+                model.shared.dirty.mark();
+            }
+            Ok(())
+        }));
+    }
+
+    /// Starts every job in the config's group `name`, each in its own pane.
+    pub fn start_group(&self, name: &str) -> anyhow::Result<()> {
+        let jobs = match &*self.config.read_or_recover() {
+            Some(config) => {
+                let group = config
+                    .group(name)
+                    .with_context(|| format!("no group named `{name}`"))?;
+                group
+                    .jobs
+                    .iter()
+                    .filter_map(|title| config.find(title))
+                    .map(|entry| entry.job.clone())
+                    .collect::<Vec<_>>()
+            }
+            None => anyhow::bail!("no group named `{name}`"),
+        };
+        jobs.into_iter()
+            .try_for_each(|job| self.start_job(job).map(|_| ()))
+    }
+
+    pub fn set_env(&self, key: &str, value: &str) {
+        self.env
+            .write_or_recover()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    /// Asks for a `KEY=VALUE` pair to pass to jobs started afterwards.
+    fn env_prompt(self: &Arc<Self>) {
+        let model = Arc::downgrade(self);
+        let history = self.history("env");
+        let prompt = Prompt::new("Set ENV (KEY=VALUE)", move |pair| {
+            let (key, value) = pair
+                .split_once('=')
+                .filter(|(key, _)| !key.trim().is_empty())
+                .ok_or("Expected KEY=VALUE")?;
+            if let Some(model) = model.upgrade() {
+                model.set_env(key.trim(), value);
+            }
+            Ok(())
+        });
+        // Tab completes the names of variables already set, here or outside
+        let known = std::env::vars()
+            .map(|(key, _)| key)
+            .chain(self.env.read_or_recover().keys().cloned())
+            .sorted()
+            .dedup()
+            .collect::<Vec<_>>();
+        self.open_prompt(
+            prompt
+                .history(history)
+                .validate(|pair| match pair.split_once('=') {
+                    Some((key, _)) if !key.trim().is_empty() => None,
+                    _ => Some("Expected KEY=VALUE".into()),
+                })
+                .completer(move |typed| match typed.contains('=') {
+                    true => vec![],
+                    false => known
+                        .iter()
+                        .filter(|key| key.starts_with(typed))
+                        .map(|key| format!("{key}="))
+                        .collect(),
+                }),
+        );
+    }
+
+    /// Sets a variable with its name and value in fields of their own, for
+    /// values awkward to type after an `=`.
+    fn env_form(self: &Arc<Self>) {
+        let model = Arc::downgrade(self);
+        self.open_prompt(Prompt::form("Set ENV", &["KEY", "VALUE"], move |values| {
+            let key = values.get("KEY").map_or("", |key| key.trim());
+            if key.is_empty() {
+                return Err("KEY can't be empty".into());
+            }
+            if let Some(model) = model.upgrade() {
+                model.set_env(key, values.get("VALUE").map_or("", String::as_str));
+            }
+            Ok(())
+        }));
+    }
+
+    pub fn add_hook(&self, hook: Hook) {
+        self.shared.hooks.add(hook);
+    }
+
+    /// Builds the menu from `config` instead of the built-in one.
+    /// Also takes on the config's [`PaneLayout`], minimum pane width and
+    /// hooks.
+    pub fn set_config(&self, config: Config) {
+        for hook in &config.hooks {
+            self.add_hook(hook.clone());
+        }
+        *self.layout.write_or_recover() = config.layout;
+        *self.min_pane_width.write_or_recover() = config.min_pane_width;
+        *self.config.write_or_recover() = Some(config);
+        *self.built_menu.write_or_recover() = None;
+    }
+
+    pub fn set_layout(&self, layout: PaneLayout) {
+        *self.layout.write_or_recover() = layout;
+    }
+
+    pub fn toggle_layout(&self) {
+        let mut layout = self.layout.write_or_recover();
+        *layout = match *layout {
+            PaneLayout::Split => PaneLayout::Tabs,
+            PaneLayout::Tabs => PaneLayout::Split,
+        };
+    }
+
+    /// Starts every job the config marks `autostart`, in the order listed.
+    pub fn autostart(&self) -> anyhow::Result<()> {
+        let jobs = match &*self.config.read_or_recover() {
+            Some(config) => config
+                .jobs
+                .iter()
+                .filter(|entry| entry.autostart)
+                .map(|entry| entry.job.clone())
+                .collect(),
+            None => vec![],
+        };
+        jobs.into_iter()
+            .try_for_each(|job| self.start_job(job).map(|_| ()))
+    }
+
+    /// The menu for the config, built once and then shared until the
+    /// config changes. Dynamic sections only change on
+    /// [`Model::refresh_menu`], so indices into it stay put in between.
+    fn main_menu(&self) -> Arc<Menu> {
+        if let Some(menu) = &*self.built_menu.read_or_recover() {
+            return menu.clone();
+        }
+
+        let mut menu = match &*self.config.read_or_recover() {
+            Some(config) => config_menu(config),
+            None => main_menu(),
+        };
+        menu.refresh(self);
+        let menu = Arc::new(menu);
+        *self.built_menu.write_or_recover() = Some(menu.clone());
+        menu
+    }
+
+    /// Regenerates the menu's dynamic sections, as it or the palette opens.
+    fn refresh_menu(&self) {
+        let mut menu = Menu::clone(&self.main_menu());
+        menu.refresh(self);
+        *self.built_menu.write_or_recover() = Some(Arc::new(menu));
+    }
+
+    /// The config's pacing, or the default one without a config.
+    pub fn pacing(&self) -> Pacing {
+        self.config
+            .read_or_recover()
+            .as_ref()
+            .map_or_else(Pacing::default, |c| c.pacing)
+    }
+
+    /// What `code` is bound to, by the config or by default.
+    fn binding(&self, code: KeyCode) -> Option<Binding> {
+        self.key_map().get(code)
+    }
+
+    /// The config's key bindings, or the defaults without a config.
+    fn key_map(&self) -> Keys {
+        self.config
+            .read_or_recover()
+            .as_ref()
+            .map_or_else(Keys::default, |c| c.keys.clone())
+    }
+
+    /// The config's theme, or the default one without a config.
+    fn theme(&self) -> Theme {
+        self.config
+            .read_or_recover()
+            .as_ref()
+            .map(|c| c.theme.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_session(&self, session: Session) {
+        *self.session.write_or_recover() = Some(session);
+    }
+
+    /// Starts (or offers to start) whatever was running when the session was
+    /// last saved.
+    pub fn restore_session(self: &Arc<Self>) -> anyhow::Result<()> {
+        let Some(session) = self.session.read_or_recover().clone() else {
+            return Ok(());
+        };
+
+        let jobs = session.load()?;
+        if jobs.is_empty() {
+            return Ok(());
+        }
+
+        match session.restore {
+            SessionRestore::Autostart => jobs
+                .into_iter()
+                .try_for_each(|j| self.start_job(j).map(|_| ())),
+            SessionRestore::Prompt => {
+                let model = Arc::downgrade(self);
+                let title = format!("Restore {} job(s) from last session?", jobs.len());
+                self.open_prompt(Prompt::confirm(&title, move |restore| {
+                    if !restore {
+                        return Ok(());
+                    }
+                    let model = model.upgrade().ok_or("itask is shutting down")?;
+                    jobs.iter()
+                        .try_for_each(|j| model.start_job(j.clone()).map(|_| ()))
+                        .map_err(|e| e.to_string())
+                }));
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the jobs still running so the next launch can restore them.
+    pub fn save_session(&self) -> anyhow::Result<()> {
+        let Some(session) = &*self.session.read_or_recover() else {
+            return Ok(());
+        };
+
+        let running = self
+            .jobs
+            .read_or_recover()
+            .iter()
+            .filter(|job| job.running())
+            .map(|job| job.config.clone())
+            .collect::<Vec<_>>();
+        session.save(&running)
+    }
+
+    /// The history shared by prompts asking for `kind` of thing.
+    pub fn history(&self, kind: &'static str) -> History {
+        self.histories
+            .write_or_recover()
+            .entry(kind)
+            .or_default()
+            .clone()
+    }
+
+    /// Opens `prompt` over everything else, replacing any open one.
+    pub fn open_prompt(&self, prompt: Prompt) {
+        *self.prompt.write_or_recover() = Some(prompt);
+    }
+
+    /// Shows `progress` until it finishes or Esc hides it. The work carries
+    /// on either way, in whatever thread writes its fraction.
+    pub fn open_progress(&self, progress: Progress) {
+        *self.progress.write_or_recover() = Some(progress);
+    }
+
+    /// How many jobs haven't finished yet, waiting and retrying included.
+    pub fn running(&self) -> usize {
+        self.jobs
+            .read_or_recover()
+            .iter()
+            .filter(|job| job.running())
+            .count()
+    }
+
+    //
+
+    pub fn focused(&self) -> Option<usize> {
+        *self.focused.read_or_recover()
+    }
+
+    pub fn set_focus_policy(&self, policy: FocusPolicy) {
+        *self.focus_policy.write_or_recover() = policy;
+    }
+
+    /// Puts `idx` in focus, if there's a job there.
+    pub fn focus(&self, idx: usize) {
+        if idx < self.jobs.read_or_recover().len() {
+            *self.focused.write_or_recover() = Some(idx);
+            // Picking a pane by hand beats waiting for one to print
+            *self.pending_focus.write_or_recover() = None;
+        }
+    }
+
+    fn focus_started(&self, idx: usize) {
+        let mut focused = self.focused.write_or_recover();
+        match *self.focus_policy.read_or_recover() {
+            FocusPolicy::FollowNewest => *focused = Some(idx),
+            FocusPolicy::KeepCurrent => {
+                focused.get_or_insert(idx);
+            }
+            FocusPolicy::FollowFirstOutput => *self.pending_focus.write_or_recover() = Some(idx),
+        }
+    }
+
+    /// Moves through the summary table while it's open, returning whether
+    /// `code` was used. Enter closes it with the chosen job focused.
+    fn summary_key(&self, code: KeyCode, binding: Option<Binding>) -> bool {
+        let mut summary = self.summary.write_or_recover();
+        let Some(row) = *summary else {
+            return false;
+        };
+
+        let last = self.jobs.read_or_recover().len().saturating_sub(1);
+        match code {
+            KeyCode::Up => *summary = Some(row.saturating_sub(1)),
+            KeyCode::Down => *summary = Some((row + 1).min(last)),
+            KeyCode::Home => *summary = Some(0),
+            KeyCode::End => *summary = Some(last),
+            KeyCode::Esc => *summary = None,
+            _ if binding == Some(Binding::Summary) => *summary = None,
+            KeyCode::Enter => {
+                *summary = None;
+                *self.focused.write_or_recover() = Some(row.min(last));
+                *self.pending_focus.write_or_recover() = None;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Moves focus `delta` panes along, wrapping around at either end.
+    fn cycle_focus(&self, delta: isize) {
+        let count = self.jobs.read_or_recover().len();
+        if count == 0 {
+            return;
+        }
+
+        let mut focused = self.focused.write_or_recover();
+        *focused = Some(match *focused {
+            Some(idx) => (idx as isize + delta).rem_euclid(count as isize) as usize,
+            None => 0,
+        });
+        // Picking a pane by hand beats waiting for one to print
+        *self.pending_focus.write_or_recover() = None;
+    }
+
+    fn with_focused(&self, f: impl FnOnce(&mut Job)) {
+        if let Some(idx) = self.focused() {
+            self.jobs.write_or_recover().get_mut(idx).map(f);
+        }
+    }
+
+    /// Puts the focused pane's selection on the clipboard and ends it.
+    fn copy_selection(&self) {
+        let mut text = None;
+        self.with_focused(|job| {
+            text = job.selected_text();
+            job.selection = None;
+        });
+        if let Some(text) = text {
+            let lines = text.lines().count().max(1);
+            let done = match lines {
+                1 => "Copied 1 line".to_string(),
+                n => format!("Copied {n} lines"),
+            };
+            self.copy(&text, done);
+        }
+    }
+
+    /// Asks for the focused job's output to be opened in `$PAGER`: its log
+    /// file where it has one, otherwise what's held of it written out to a
+    /// temporary file first.
+    fn page_output(&self) {
+        let Some(idx) = self.focused() else {
+            return;
+        };
+        let jobs = self.jobs.read_or_recover();
+        let Some(job) = jobs.get(idx) else {
+            return;
+        };
+
+        let path = match job.log_file.clone().filter(|path| path.is_file()) {
+            Some(path) => path,
+            None => {
+                let path = LogFile::path(&std::env::temp_dir(), &job.title);
+                if let Err(e) = job.save_output(&path) {
+                    drop(jobs);
+                    return self.flash(format!("Couldn't write {}: {e}", path.display()));
+                }
+                path
+            }
+        };
+        *self.page.write_or_recover() = Some(path);
+    }
+
+    /// The file [`Model::page_output`] asked for, only once.
+    fn take_page(&self) -> Option<PathBuf> {
+        self.page.write_or_recover().take()
+    }
+
+    /// Copies what the menu item at `idx` would run, without running it.
+    fn copy_menu_command(&self, idx: usize) {
+        let menu = self.main_menu();
+        match menu.command(idx) {
+            Some(command) => {
+                self.copy(&command, format!("Copied: {command}"));
+            }
+            None => {
+                if let Some(item) = menu.0.get(idx) {
+                    self.flash(format!("{} doesn't run a command", item.title()));
+                }
+            }
+        }
+    }
+
+    /// Copies the focused job's [`Job::last_error`], saying so either way.
+    fn copy_last_error(&self) {
+        let found = self.focused().and_then(|idx| {
+            let jobs = self.jobs.read_or_recover();
+            let job = jobs.get(idx)?;
+            Some((job.last_error(), job.config.error_pattern.clone()))
+        });
+        match found {
+            Some((Some(line), _)) => {
+                self.copy(&line, format!("Copied: {line}"));
+            }
+            Some((None, pattern)) => self.flash(format!("No line matching \"{pattern}\"")),
+            None => {}
+        }
+    }
+
+    /// Puts `text` on the clipboard, flashing `done` or why it couldn't.
+    fn copy(&self, text: &str, done: String) {
+        match self.clipboard.copy(text) {
+            Ok(()) => self.flash(done),
+            Err(e) => self.flash(format!("Couldn't copy: {e}")),
+        }
+    }
+
+    /// What was last copied. Without [`App::new`]'s terminal to send copies
+    /// to, this is as far as they go.
+    pub fn copied(&self) -> Option<String> {
+        self.clipboard.last()
+    }
+
+    /// Shows `message` in the status bar for a moment.
+    pub fn flash(&self, message: String) {
+        *self.flash.write_or_recover() = Some((message, Instant::now()));
+        self.shared.dirty.mark();
+    }
+
+    fn selecting(&self) -> bool {
+        let mut selecting = false;
+        self.with_focused(|job| selecting = job.selection.is_some());
+        selecting
+    }
+
+    /// Asks for the term to highlight in the focused pane.
+    fn search_prompt(self: &Arc<Self>) {
+        let Some(idx) = self.focused() else {
+            return;
+        };
+
+        let model = Arc::downgrade(self);
+        let history = self.history("search");
+        let prompt = Prompt::new("Search output", move |term| {
+            if let Some(model) = model.upgrade() {
+                if let Some(job) = model.jobs.write_or_recover().get_mut(idx) {
+                    job.search = Some(term).filter(|t| !t.is_empty());
+                }
+            }
+            Ok(())
+        });
+        self.open_prompt(prompt.history(history));
+    }
+
+    /// Narrows the focused pane to lines matching a regex, or plain text if
+    /// it doesn't parse as one. An empty pattern shows everything again.
+    fn filter_prompt(self: &Arc<Self>) {
+        self.pattern_prompt("Filter output (regex)", "filter", |job, filter| {
+            job.filter = filter;
+            job.scroll = None;
+        });
+    }
+
+    /// Asks what the focused pane should keep pinned above its output, an
+    /// empty answer unpinning it.
+    fn pin_prompt(self: &Arc<Self>) {
+        self.pattern_prompt("Pin lines matching (regex)", "pin", |job, pin| {
+            job.pin = pin
+        });
+    }
+
+    /// Asks for a regex, or plain text if it doesn't parse as one, and hands
+    /// it to `set` with the focused job. An empty answer hands over `None`.
+    fn pattern_prompt(
+        self: &Arc<Self>,
+        title: &str,
+        history: &'static str,
+        set: impl Fn(&mut Job, Option<Filter>) + Send + Sync + 'static,
+    ) {
+        let Some(idx) = self.focused() else {
+            return;
+        };
+
+        let model = Arc::downgrade(self);
+        let history = self.history(history);
+        let prompt = Prompt::new(title, move |pattern| {
+            if let Some(model) = model.upgrade() {
+                if let Some(job) = model.jobs.write_or_recover().get_mut(idx) {
+                    set(
+                        job,
+                        Some(Filter::new(&pattern)).filter(|_| !pattern.is_empty()),
+                    );
+                }
+            }
+            Ok(())
+        });
+        self.open_prompt(prompt.history(history).validate(|pattern| {
+            Regex::new(pattern)
+                .err()
+                .map(|e| format!("{e}, matching as plain text"))
+        }));
+    }
+
+    /// Opens a line under the focused pane for typing into its job's stdin,
+    /// if the job keeps it open.
+    fn start_typing(&self) {
+        let mut refused = None;
+        self.with_focused(|job| match job.config.stdin {
+            true => job.input = Some(Default::default()),
+            false => refused = Some(format!("{} doesn't take input, see `stdin`", job.title)),
+        });
+        if let Some(message) = refused {
+            self.flash(message);
+        }
+    }
+
+    fn typing(&self) -> bool {
+        let mut typing = false;
+        self.with_focused(|job| typing = job.input.is_some());
+        typing
+    }
+
+    /// Edits the focused pane's input line. Enter sends it, Esc puts it away.
+    fn input_key(&self, key: KeyEvent) {
+        let mut failed = None;
+        self.with_focused(|job| {
+            let Some((cursor, line)) = job.input.as_mut() else {
+                return;
+            };
+            let byte =
+                |line: &str, c: usize| line.char_indices().nth(c).map_or(line.len(), |(i, _)| i);
+            let len = line.chars().count();
+            match key.code {
+                KeyCode::Esc => job.input = None,
+                KeyCode::Enter => failed = job.send_input().err(),
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    line.insert(byte(line, *cursor), c);
+                    *cursor += 1;
+                }
+                KeyCode::Backspace if *cursor > 0 => {
+                    *cursor -= 1;
+                    line.remove(byte(line, *cursor));
+                }
+                KeyCode::Delete if *cursor < len => {
+                    line.remove(byte(line, *cursor));
+                }
+                KeyCode::Left => *cursor = cursor.saturating_sub(1),
+                KeyCode::Right => *cursor = (*cursor + 1).min(len),
+                KeyCode::Home => *cursor = 0,
+                KeyCode::End => *cursor = len,
+                _ => {}
+            }
+        });
+        if let Some(e) = failed {
+            self.flash(format!("{e:#}"));
+        }
+    }
+
+    /// Hands focus to a job waiting on its first line of output.
+    fn sync_focus(&self) {
+        let Some(idx) = *self.pending_focus.read_or_recover() else {
+            return;
+        };
+
+        let has_output = self
+            .jobs
+            .read_or_recover()
+            .get(idx)
+            .is_some_and(|j| !j.logs.read_or_recover().lines.is_empty());
+
+        if has_output {
+            *self.focused.write_or_recover() = Some(idx);
+            *self.pending_focus.write_or_recover() = None;
+        }
+    }
+
+    //
+
+    /// Waits up to `timeout` for terminal input and passes it on to
+    /// [`Model::update`].
+    pub fn keys(self: &Arc<Self>, timeout: Duration) -> anyhow::Result<()> {
+        if event::poll(timeout)? {
+            match Action::from_event(event::read()?) {
+                Some(action) => self.update(action),
+                None => self.shared.dirty.mark(),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies one input to the model, without touching the terminal, so
+    /// key sequences can be tested as state changes alone.
+    pub fn update(self: &Arc<Self>, action: Action) {
+        match action {
+            Action::Key(key) => self.handle_key(key),
+            Action::Mouse(mouse) => self.handle_mouse(mouse),
+            Action::Paste(text) => self.handle_paste(&text),
+        }
+        self.shared.dirty.mark();
+    }
+
+    /// Routes a key to whatever's on top: a prompt, then the palette, then
+    /// the menu and job panes.
+    fn handle_key(self: &Arc<Self>, key: KeyEvent) {
+        // Out from under the lock, for handlers that open another prompt
+        let prompt = self.prompt.read_or_recover().clone();
+        if let Some(p) = prompt {
+            // Handlers that return `Ok` are done with, so close on that too
+            if key.code == KeyCode::Esc || p.input(key) {
+                let mut prompt = self.prompt.write_or_recover();
+                if prompt.as_ref().is_some_and(|open| open.is(&p)) {
+                    *prompt = None;
+                }
+            } else if let Some(text) = p.take_copied() {
+                self.copy(&text, "Copied".to_string());
+            }
+            return;
+        }
+
+        let mut progress = self.progress.write_or_recover();
+        if progress.is_some() {
+            if key.code == KeyCode::Esc {
+                *progress = None;
+            }
+            return;
+        }
+
+        drop(progress);
+        let mut palette = self.palette.write_or_recover();
+        if let Some(p) = palette.as_mut() {
+            let chosen = match key.code {
+                KeyCode::Esc => None,
+                code => match p.input(code) {
+                    Some(idx) => Some(idx),
+                    None => return,
+                },
+            };
+
+            *palette = None;
+            drop(palette);
+            if let Some(idx) = chosen {
+                self.main_menu().enter(idx, self.clone());
+            }
+            return;
+        }
+
+        drop(palette);
+        let binding = self.binding(key.code);
+        let mut help = self.help.write_or_recover();
+        if *help {
+            if key.code == KeyCode::Esc || binding == Some(Binding::Help) {
+                *help = false;
+            }
+            return;
+        }
+
+        drop(help);
+        let mut menu = self.menu.write_or_recover();
+        if menu.is_none() && self.typing() {
+            return self.input_key(key);
+        }
+        if let Some(idx) = *menu {
+            *menu = Some(self.main_menu().settle(idx));
+        }
+        if menu.is_none() && self.summary_key(key.code, binding) {
+            return;
+        }
+
+        if let Some(binding) = binding {
+            match binding {
+                Binding::Menu => {
+                    if menu.is_none() {
+                        self.refresh_menu();
+                        // An empty menu still opens, on a placeholder
+                        *menu = Some(self.main_menu().first().unwrap_or_default());
+                    } else {
+                        *menu = None
+                    }
+                }
+                Binding::Quit => self.request_quit(),
+                Binding::Summary => *self.summary.write_or_recover() = self.focused().or(Some(0)),
+                Binding::NextPane => self.cycle_focus(1),
+                Binding::PrevPane => self.cycle_focus(-1),
+                Binding::Stop => self.with_focused(|job| job.stop()),
+                Binding::Wrap => self.with_focused(Job::toggle_wrap),
+                Binding::Clear => self.with_focused(Job::clear),
+                Binding::Restart => self.with_focused(Job::toggle_restart),
+                Binding::Rerun => {
+                    self.rerun().ok();
+                }
+                Binding::Streams => self.with_focused(Job::cycle_streams),
+                Binding::Select => self.with_focused(Job::toggle_selection),
+                // The menu's item rather than the pane's selection while open
+                Binding::Copy => match *menu {
+                    Some(idx) => self.copy_menu_command(idx),
+                    None => self.copy_selection(),
+                },
+                Binding::CopyError => self.copy_last_error(),
+                Binding::Pager => self.page_output(),
+                Binding::Bookmark => self.with_focused(Job::toggle_bookmark),
+                Binding::PrevBookmark => self.with_focused(Job::prev_bookmark),
+                Binding::NextBookmark => self.with_focused(Job::next_bookmark),
+                // With the menu open, search filters it rather than the output
+                Binding::Search if menu.is_some() => self.open_palette(),
+                Binding::Search => self.search_prompt(),
+                Binding::NextMatch => self.with_focused(Job::next_match),
+                Binding::PrevMatch => self.with_focused(Job::prev_match),
+                Binding::Filter => self.filter_prompt(),
+                Binding::Pin => self.pin_prompt(),
+                Binding::Input => self.start_typing(),
+                Binding::PageUp => self.with_focused(Job::page_up),
+                Binding::PageDown => self.with_focused(Job::page_down),
+                Binding::ScrollHome => self.with_focused(Job::scroll_home),
+                Binding::ScrollEnd => self.with_focused(Job::scroll_end),
+                Binding::Palette => self.open_palette(),
+                Binding::Help => *self.help.write_or_recover() = true,
+                Binding::Layout => self.toggle_layout(),
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                if let Some(idx) = *menu {
+                    *menu = self.main_menu().back(idx);
+                } else {
+                    self.with_focused(|job| job.selection = None);
+                }
+            }
+
+            // The menu gets the arrows while open, a selection otherwise
+            KeyCode::Up if menu.is_none() && self.selecting() => {
+                self.with_focused(|job| job.extend_selection(-1))
+            }
+            KeyCode::Down if menu.is_none() && self.selecting() => {
+                self.with_focused(|job| job.extend_selection(1))
+            }
+
+            KeyCode::Up => {
+                if let Some(idx) = *menu {
+                    menu.replace(self.main_menu().up(idx));
+                }
+            }
+
+            KeyCode::Down => {
+                if let Some(idx) = *menu {
+                    menu.replace(self.main_menu().down(idx));
+                }
+            }
+
+            // Tabs take the arrows, unwrapped panes scroll sideways otherwise
+            KeyCode::Left | KeyCode::Right if menu.is_none() && self.tabbed() => {
+                self.cycle_focus(if key.code == KeyCode::Left { -1 } else { 1 })
+            }
+            KeyCode::Left if menu.is_none() => self.with_focused(|job| job.scroll_columns(-8)),
+            KeyCode::Right if menu.is_none() => self.with_focused(|job| job.scroll_columns(8)),
+            KeyCode::Char(n @ '1'..='9') if menu.is_none() => self.focus(n as usize - '1' as usize),
+
+            KeyCode::Enter => {
+                if let Some(idx) = *menu {
+                    menu.replace(self.main_menu().enter(idx, self.clone()));
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Pasted text goes to the open prompt, and nowhere else.
+    fn handle_paste(&self, text: &str) {
+        if let Some(prompt) = &*self.prompt.read_or_recover() {
+            prompt.paste(text);
+        }
+    }
+
+    /// Clicks pick menu entries and focus panes, the wheel scrolls the pane
+    /// under the pointer.
+    fn handle_mouse(self: &Arc<Self>, mouse: MouseEvent) {
+        let overlaid = self.prompt.read_or_recover().is_some()
+            || self.progress.read_or_recover().is_some()
+            || self.palette.read_or_recover().is_some()
+            || *self.help.read_or_recover();
+        if overlaid {
+            return;
+        }
+
+        let at = Position::new(mouse.column, mouse.row);
+        let menu_area = *self.menu_area.read_or_recover();
+        if let Some(area) = menu_area.filter(|a| a.contains(at)) {
+            if mouse.kind == MouseEventKind::Down(MouseButton::Left) {
+                self.click_menu(area, at);
+            }
+            return;
+        }
+
+        let pane = self
+            .panes
+            .read_or_recover()
+            .iter()
+            .position(|p| p.contains(at));
+        let Some(idx) = pane else {
+            return;
+        };
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                *self.focused.write_or_recover() = Some(idx);
+                *self.pending_focus.write_or_recover() = None;
+            }
+            MouseEventKind::ScrollUp => self.jobs.write_or_recover()[idx].scroll_by(-3),
+            MouseEventKind::ScrollDown => self.jobs.write_or_recover()[idx].scroll_by(3),
+            _ => {}
+        }
+    }
+
+    /// Selects and enters the menu entry drawn at `at`, one per row inside
+    /// the border.
+    fn click_menu(self: &Arc<Self>, area: Rect, at: Position) {
+        let mut open = self.menu.write_or_recover();
+        let Some(current) = *open else {
+            return;
+        };
+        let menu = self.main_menu();
+        let current = menu.settle(current);
+        let section = menu.siblings(current);
+        let row = at.y.checked_sub(area.y + 1).map(usize::from);
+        if let Some(idx) = row.and_then(|row| section.get(row).copied()) {
+            *open = Some(idx);
+            drop(open);
+            let entered = menu.enter(idx, self.clone());
+            *self.menu.write_or_recover() = Some(entered);
+        }
+    }
+
+    /// Lists every menu item, across all sections, to filter down and run.
+    fn open_palette(&self) {
+        self.refresh_menu();
+        *self.palette.write_or_recover() = Some(Palette::new(self.main_menu().commands()));
+    }
+
+    //
+
+    /// Whether anything changed since this was last asked. An open
+    /// [`Progress`] is written without telling anyone, so it always is.
+    pub fn take_dirty(&self) -> bool {
+        self.shared.dirty.take() | self.progress.read_or_recover().is_some()
+    }
+
+    /// Ends every running job that isn't [`JobConfig::detach`]ed: SIGTERM
+    /// first, then a kill for any still going after `grace`.
+    pub fn shutdown(&self, grace: Duration) {
+        let jobs = self.jobs.read_or_recover();
+        let ending = jobs
+            .iter()
+            .filter(|job| job.running() && !job.config.detach)
+            .collect::<Vec<_>>();
+        ending.iter().for_each(|job| job.terminate());
+
+        let until = Instant::now() + grace;
+        while ending.iter().any(|job| job.running()) && Instant::now() < until {
+            sleep(Duration::from_millis(20));
+        }
+        ending
+            .iter()
+            .filter(|job| job.running())
+            .for_each(|job| job.stop());
+    }
+
+    /// Each job's command, how it ended, how long it ran and where its log
+    /// was kept, as a table to print once the terminal is given back. Empty
+    /// if nothing was run.
+    pub fn run_summary(&self) -> String {
+        let jobs = self.jobs.read_or_recover();
+        if jobs.is_empty() {
+            return String::new();
+        }
+
+        let mut rows = vec![["Command", "Exit", "Took", "Log"].map(String::from)];
+        rows.extend(jobs.iter().map(|job| {
+            [
+                job.command.clone(),
+                job.exit_code().unwrap_or_else(|| job.state().into()),
+                job.process
+                    .ran_for()
+                    .map_or_else(|| "-".into(), |t| format!("{:.1}s", t.as_secs_f32())),
+                job.log_file
+                    .as_ref()
+                    .filter(|path| path.is_file())
+                    .map_or_else(String::new, |path| path.display().to_string()),
+            ]
+        }));
+        let widths = (0..4)
+            .map(|i| {
+                rows.iter()
+                    .map(|row| row[i].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        rows.iter()
+            .map(|row| {
+                let line = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, &width)| format!("{cell:<width$}"))
+                    .join("  ");
+                format!("{}\n", line.trim_end())
+            })
+            .collect()
+    }
+
+    /// Quits straight away when nothing's running, otherwise asks first
+    /// since the running jobs go down with itask, see [`Model::shutdown`].
+    fn request_quit(self: &Arc<Self>) {
+        let running = self
+            .jobs
+            .read_or_recover()
+            .iter()
+            .filter(|job| job.running() && !job.config.detach)
+            .count();
+        if running == 0 {
+            *self.quit.write_or_recover() = true;
+            return;
+        }
+
+        let model = Arc::downgrade(self);
+        let title = format!("Quit and stop {running} running job(s)?");
+        self.open_prompt(Prompt::confirm(&title, move |quit| {
+            if let Some(model) = model.upgrade().filter(|_| quit) {
+                *model.quit.write_or_recover() = true;
+            }
+            Ok(())
+        }));
+    }
+
+    pub fn quit(&self) -> bool {
+        *self.quit.read_or_recover()
+    }
+
+    pub fn render(self: &Arc<Self>, frame: &mut Frame<'_>) {
+        self.sync_focus();
+
+        let area = frame.area();
+        if area.width < MIN_SIZE.0 || area.height < MIN_SIZE.1 {
+            self.panes.write_or_recover().clear();
+            *self.menu_area.write_or_recover() = None;
+            return Self::too_small(area, frame);
+        }
+
+        let [body, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+        let menu = *self.menu.read_or_recover();
+        let (jobs_area, menu_area) = Self::menu_layout(body, menu.is_some());
+
+        let theme = self.theme();
+        frame.render_widget(Block::new().hidden(), area);
+        self.render_jobs(jobs_area, &theme, frame);
+        if let Some((idx, menu_area)) = menu.zip(menu_area) {
+            let menu = self.main_menu();
+            let idx = menu.settle(idx);
+            frame.render_widget(Clear, menu_area);
+            menu.render_themed(menu_area, frame.buffer_mut(), idx, &theme);
+        }
+        *self.menu_area.write_or_recover() = menu_area;
+
+        self.render_status(status, frame);
+        self.render_prompt(&theme, frame);
+    }
+
+    /// Where the panes and an open menu go in `body`: side by side where
+    /// there's room for both, otherwise the menu over the middle of the
+    /// panes.
+    fn menu_layout(body: Rect, open: bool) -> (Rect, Option<Rect>) {
+        if !open {
+            return (body, None);
+        }
+
+        let width = (body.width * 3 / 10).max(MIN_MENU_WIDTH).min(body.width);
+        if body.width - width >= MIN_JOBS_WIDTH {
+            let [jobs, menu] =
+                Layout::horizontal([Constraint::Min(0), Constraint::Length(width)]).areas(body);
+            return (jobs, Some(menu));
+        }
+
+        let width = (body.width * 3 / 4).max(MIN_MENU_WIDTH).min(body.width);
+        let menu = Rect {
+            x: body.x + (body.width - width) / 2,
+            width,
+            ..body
+        };
+        (body, Some(menu))
+    }
+
+    fn too_small(area: Rect, frame: &mut Frame<'_>) {
+        let lines = vec![
+            Line::from("Terminal too small".bold()),
+            Line::from(format!(
+                "{}x{}, needs {}x{}",
+                area.width, area.height, MIN_SIZE.0, MIN_SIZE.1
+            ))
+            .dark_gray(),
+        ];
+        let y = area.y + area.height.saturating_sub(2) / 2;
+        let rect = Rect {
+            y,
+            height: 2.min(area.height),
+            ..area
+        };
+        frame.render_widget(Paragraph::new(lines).centered(), rect);
+    }
+
+    /// The bottom row: how many jobs are running and which pane has focus,
+    /// or a [`Model::flash`] message while there is one.
+    fn render_status(&self, area: Rect, frame: &mut Frame<'_>) {
+        let jobs = self.jobs.read_or_recover();
+        let running = jobs.iter().filter(|job| job.running()).count();
+        let focus = match self.focused().and_then(|idx| jobs.get(idx)) {
+            Some(job) => format!("focus: {}", job.title),
+            None => "no focus".into(),
+        };
+
+        // Left out if `[keys]` gave the help key away
+        let hint = match self.key_map().key_for(Binding::Help) {
+            Some(code) => format!("press {} for help ", key_name(code)),
+            None => String::new(),
+        };
+        let [left, right] = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Length(hint.chars().count() as u16),
+        ])
+        .areas(area);
+        let flash = self.flash.read_or_recover().clone();
+        let status = match flash.filter(|(_, at)| at.elapsed() < FLASH) {
+            Some((message, _)) => Line::from(format!(" {message}")).yellow(),
+            None => Line::from(format!(" {running} running · {focus}")).dark_gray(),
+        };
+        frame.render_widget(status, left);
+        frame.render_widget(Line::from(hint).dark_gray(), right);
+    }
+
+    pub fn render_prompt(self: &Arc<Self>, theme: &Theme, frame: &mut Frame<'_>) {
+        if *self.help.read_or_recover() {
+            let keys = self.key_map();
+            let help = Help(&keys);
+            frame.render_widget(&help, help.area(frame.area()));
+        }
+        if let Some(palette) = &*self.palette.read_or_recover() {
+            frame.render_widget(palette, palette.area(frame.area()));
+        }
+        let mut progress = self.progress.write_or_recover();
+        if progress.as_ref().is_some_and(Progress::finished) {
+            *progress = None;
+        }
+        if let Some(progress) = &*progress {
+            frame.render_widget(progress, progress.area(frame.area()));
+        }
+        if let Some(prompt) = &*self.prompt.read_or_recover() {
+            frame.render_widget(
+                prompt.clone().theme(theme.clone()),
+                prompt.area(frame.area()),
+            );
+        }
+    }
+
+    pub fn render_jobs(self: &Arc<Self>, area: Rect, theme: &Theme, frame: &mut Frame<'_>) {
+        let mut jobs = self.jobs.write_or_recover();
+        if jobs.is_empty() {
+            self.panes.write_or_recover().clear();
+            return Self::banner(&self.key_map(), area, frame);
+        }
+
+        if let Some(row) = *self.summary.read_or_recover() {
+            self.panes.write_or_recover().clear();
+            let hint = "Enter to open, Esc to close";
+            return Self::render_summary(&jobs, row, hint, theme, area, frame);
+        }
+
+        let areas = grid(area, jobs.len());
+        let min_width = *self.min_pane_width.read_or_recover();
+        let squeezed = min_width.is_some_and(|min| areas.iter().any(|a| a.width < min));
+        *self.squeezed.write_or_recover() = squeezed;
+        if self.tabbed() {
+            return self.render_tabs(&mut jobs, theme, area, frame);
+        }
+
+        // Panes too small to read give way to the table, following focus
+        if areas
+            .iter()
+            .any(|a| a.width < MIN_PANE.0 || a.height < MIN_PANE.1)
+        {
+            self.panes.write_or_recover().clear();
+            let row = self.focused().unwrap_or(0);
+            let hint = "Too many to split, t for tabs, d to pick";
+            return Self::render_summary(&jobs, row, hint, theme, area, frame);
+        }
+        let focused = self.focused();
+        for (idx, (job, area)) in jobs.iter_mut().zip(areas.iter()).enumerate() {
+            Self::render_job(job, focused == Some(idx), theme, *area, frame);
+        }
+        *self.panes.write_or_recover() = areas;
+    }
+
+    /// The focused job alone, under a tab for every job. Hidden panes get an
+    /// empty area so clicks still line up with job indices.
+    fn render_tabs(&self, jobs: &mut [Job], theme: &Theme, area: Rect, frame: &mut Frame<'_>) {
+        let [tabs, pane] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        let selected = self.focused().unwrap_or(0).min(jobs.len() - 1);
+        let titles = jobs
+            .iter()
+            .enumerate()
+            .map(|(idx, job)| format!("{} {}", idx + 1, job.title));
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(selected)
+                .highlight_style(theme.selected),
+            tabs,
+        );
+
+        Self::render_job(&mut jobs[selected], true, theme, pane, frame);
+        let mut panes = vec![Rect::default(); jobs.len()];
+        panes[selected] = pane;
+        *self.panes.write_or_recover() = panes;
+    }
+
+    /// Chosen, or fallen back to since split panes would be too narrow.
+    fn tabbed(&self) -> bool {
+        *self.layout.read_or_recover() == PaneLayout::Tabs || *self.squeezed.read_or_recover()
+    }
+
+    /// Every job on a row of its own, in place of the panes.
+    fn render_summary(
+        jobs: &[Job],
+        row: usize,
+        hint: &str,
+        theme: &Theme,
+        area: Rect,
+        frame: &mut Frame<'_>,
+    ) {
+        let rows = jobs.iter().map(|job| {
+            let state = match job.failed() {
+                true => Cell::from(job.state()).style(theme.error),
+                false => Cell::from(job.state()),
+            };
+            Row::new(vec![
+                Cell::from(job.title.clone()),
+                state,
+                Cell::from(job.exit_code().unwrap_or_default()),
+                Cell::from(job.last_line().unwrap_or_default()).dark_gray(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Max(24),
+                Constraint::Length(15),
+                Constraint::Length(6),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(["Job", "State", "Exit", "Last line"]).bold())
+        .highlight_style(theme.selected)
+        .block(
+            Block::bordered()
+                .title("Jobs")
+                .title_bottom(Line::from(hint.to_string()).right_aligned()),
+        );
+        frame.render_stateful_widget(
+            table,
+            area,
+            &mut TableState::new().with_selected(Some(row.min(jobs.len() - 1))),
+        );
+    }
+
+    fn render_job(job: &mut Job, focused: bool, theme: &Theme, area: Rect, frame: &mut Frame<'_>) {
+        let border = match (focused, job.failed()) {
+            (true, _) => theme.focused_border,
+            (false, true) => theme.failed_border,
+            (false, false) => theme.job_border,
+        };
+        // Below stderr too, at the very bottom of the pane
+        let area = match &job.input {
+            Some((cursor, line)) => {
+                let [rest, input] =
+                    Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(area);
+                frame.render_stateful_widget(
+                    Input::new(false).border(theme.prompt_border),
+                    input,
+                    &mut (*cursor, line.clone()),
+                );
+                rest
+            }
+            None => area,
+        };
+        let (area, errors) = match job.config.output {
+            OutputLayout::Merged => (area, None),
+            OutputLayout::Split => {
+                let [out, errors] = Layout::vertical([
+                    Constraint::Min(0),
+                    Constraint::Length((area.height / 3).max(3)),
+                ])
+                .areas(area);
+                (out, Some(errors))
+            }
+        };
+        if let Some(errors) = errors {
+            Self::render_stderr(job, theme, border, errors, frame);
+        }
+        let area = match job.pinned() {
+            Some(pinned) => {
+                let rows = pinned.len().max(1) as u16 + 2;
+                let [pin, rest] =
+                    Layout::vertical([Constraint::Length(rows), Constraint::Min(0)]).areas(area);
+                Self::render_pinned(job, &pinned, theme, border, pin, frame);
+                rest
+            }
+            None => area,
+        };
+
+        let height = area.height.saturating_sub(2) as usize;
+        job.viewport = height;
+        let snapshot = job.snapshot(height);
+
+        let text = snapshot
+            .lines
+            .iter()
+            .map(|(n, line)| {
+                let n = *n;
+                let gutter = match job.bookmarks.contains(&n) {
+                    true => "▶ ".yellow(),
+                    false => "  ".into(),
+                };
+
+                let mut styles = Self::line_styles(job, line, theme);
+                styles.extend(
+                    line.links
+                        .iter()
+                        .map(|l| (l.range.clone(), Style::new().underlined())),
+                );
+                if let Some(pattern) = &job.search {
+                    styles.extend(search::highlights(&line.text, pattern));
+                }
+
+                let mut spans = vec![gutter];
+                if let Some(time) = &line.time {
+                    spans.push(format!("{time} ").dark_gray());
+                }
+                spans.extend(ansi::styled(&line.text, &styles));
+                let line = Line::from(spans);
+                match job.selection.is_some_and(|s| s.contains(n)) {
+                    true => line.on_dark_gray(),
+                    false => line,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let title = job.display_title();
+        let mut block = Block::bordered().title(title.as_str()).border_style(border);
+        // Greyed out once the process is gone, and only where it fits
+        if let Some(pid) = job.process.pid() {
+            let pid = format!("pid {pid}");
+            let fits = title.chars().count() + pid.chars().count() + 3 <= area.width as usize;
+            let pid = match job.process.alive() {
+                true => Line::from(pid),
+                false => Line::from(pid).dark_gray(),
+            };
+            if fits {
+                block = block.title(pid.right_aligned());
+            }
+        }
+        let mut room = area.width.saturating_sub(2) as usize;
+        // The title may be cut short, so the focused pane spells it out
+        if focused && job.command != job.title {
+            let command = format!("$ {}", job.command);
+            room = room.saturating_sub(command.chars().count() + 1);
+            block = block.title_bottom(Line::from(command).dark_gray());
+        }
+        if let Some(position) = snapshot.position {
+            room = room.saturating_sub(position.chars().count() + 1);
+            block = block.title_bottom(Line::from(position).right_aligned());
+        }
+        // Warns once scrollback starts being lost, where there's room
+        let (usage, full) = snapshot.usage;
+        if usage.chars().count() <= room {
+            let usage = match full {
+                true => Line::from(usage).style(theme.error),
+                false => Line::from(usage).dark_gray(),
+            };
+            block = block.title_bottom(usage.right_aligned());
+        }
+        let mut paragraph = Paragraph::new(text).block(block);
+        if job.wrap {
+            paragraph = paragraph.wrap(Wrap { trim: false });
+        } else {
+            let column = job.column.try_into().unwrap_or(u16::MAX);
+            paragraph = paragraph.scroll((0, column));
+        }
+
+        frame.render_widget(paragraph, area);
+        // Drawn over the right border, between the corners
+        if let Some((position, max)) = snapshot.extent {
+            frame.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(None)
+                    .end_symbol(None),
+                area.inner(Margin::new(0, 1)),
+                &mut ScrollbarState::new(max).position(position),
+            );
+        }
+    }
+
+    /// How `line` is coloured before links and search matches: red for
+    /// stderr, then the job's [`JobConfig::colors`], then the program's own.
+    fn line_styles(job: &Job, line: &LogLine, theme: &Theme) -> Vec<(Range<usize>, Style)> {
+        let mut styles = match line.stream {
+            Stream::Stdout => vec![],
+            Stream::Stderr => vec![(0..line.text.len(), theme.error)],
+        };
+        if let Some(color) = job.config.line_color(&line.text) {
+            styles.push((0..line.text.len(), Style::new().fg(color)));
+        }
+        styles.extend(line.styles.iter().cloned());
+        styles
+    }
+
+    /// The newest lines matching the pane's [`Job::pin`], above the rest.
+    fn render_pinned(
+        job: &Job,
+        pinned: &[LogLine],
+        theme: &Theme,
+        border: Style,
+        area: Rect,
+        frame: &mut Frame<'_>,
+    ) {
+        let mut lines = pinned
+            .iter()
+            .map(|line| {
+                Line::from(ansi::styled(
+                    &line.text,
+                    &Self::line_styles(job, line, theme),
+                ))
+            })
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            lines.push(Line::from("nothing matching yet").dark_gray());
+        }
+        let pattern = job.pin.as_ref().map_or("", |pin| pin.pattern.as_str());
+        let block = Block::bordered()
+            .title(format!("pinned: {pattern}"))
+            .border_style(border);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    /// The lower part of a [`OutputLayout::Split`] pane, following the tail
+    /// of stderr.
+    fn render_stderr(job: &Job, theme: &Theme, border: Style, area: Rect, frame: &mut Frame<'_>) {
+        let lines = job
+            .stderr_tail(area.height.saturating_sub(2) as usize)
+            .iter()
+            .map(|line| {
+                Line::from(ansi::styled(
+                    &line.text,
+                    &Self::line_styles(job, line, theme),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let block = Block::bordered().title("stderr").border_style(border);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    pub fn banner(keys: &Keys, area: Rect, frame: &mut Frame<'_>) {
+        let (title, tagline) = BANNER.split("-----").collect_tuple().unwrap();
+        // Generated so the shortcuts shown can't drift from the real ones
+        let mut help = tagline
+            .lines()
+            .map(|l| Line::from(l.trim().to_string()))
+            .collect::<Vec<_>>();
+        help.push(Line::default());
+        help.extend(key_summary(keys).into_iter().map(Line::from));
+
+        let lay = Layout::new(
+            Direction::Vertical,
+            vec![
+                Constraint::Fill(1),
+                Constraint::Length(title.lines().count() as u16),
+                Constraint::Length(help.len() as u16),
+                Constraint::Fill(1),
+            ],
+        )
+        .split(area);
+
+        frame.render_widget(Block::bordered(), area);
+        frame.render_widget(
+            Paragraph::new(title.lines().map(Line::from).collect::<Vec<_>>())
+                .alignment(ratatui::layout::Alignment::Center),
+            lay[1],
+        );
+        frame.render_widget(
+            Paragraph::new(help).alignment(ratatui::layout::Alignment::Center),
+            lay[2],
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Lock access that survives a panic elsewhere.
+///
+/// A thread panicking while it holds a lock poisons it, and unwrapping every
+/// later access would take the whole UI down with it. Whatever the panicking
+/// thread left behind is still the best state there is, so carry on with it.
+pub trait Recover<T> {
+    fn read_or_recover(&self) -> RwLockReadGuard<'_, T>;
+    fn write_or_recover(&self) -> RwLockWriteGuard<'_, T>;
+}
+
+impl<T> Recover<T> for RwLock<T> {
+    fn read_or_recover(&self) -> RwLockReadGuard<'_, T> {
+        self.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_or_recover(&self) -> RwLockWriteGuard<'_, T> {
+        self.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    #[test]
+    fn recovers_after_a_panic_while_locked() {
+        let lock = Arc::new(RwLock::new(1));
+        let held = lock.clone();
+        thread::spawn(move || {
+            let mut value = held.write().unwrap();
+            *value = 2;
+            panic!("reader thread bug");
+        })
+        .join()
+        .unwrap_err();
+
+        assert!(lock.is_poisoned());
+        assert_eq!(*lock.read_or_recover(), 2);
+        *lock.write_or_recover() = 3;
+        assert_eq!(*lock.read_or_recover(), 3);
+    }
+}
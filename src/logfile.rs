@@ -0,0 +1,97 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A copy of a job's output on disk, created with the first line written.
+pub struct LogFile {
+    pub path: PathBuf,
+    file: Option<File>,
+    /// Set after the first failure, so a full disk warns once.
+    failed: bool,
+}
+
+impl LogFile {
+    /// `<dir>/<job>-<unix seconds>.log`, with anything but letters, digits,
+    /// `-` and `_` in the job's name replaced by `-`.
+    pub fn path(dir: &Path, job: &str) -> PathBuf {
+        let name = job
+            .chars()
+            .map(|c| match c.is_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '-',
+            })
+            .collect::<String>();
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        dir.join(format!("{name}-{started}.log"))
+    }
+
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: None,
+            failed: false,
+        }
+    }
+
+    /// Appends `line`. Only the first failure is returned, later lines are
+    /// dropped quietly.
+    pub fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.failed {
+            return Ok(());
+        }
+
+        let result = self.open().and_then(|file| writeln!(file, "{line}"));
+        self.failed = result.is_err();
+        result
+    }
+
+    fn open(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let file = File::options().create(true).append(true).open(&self.path)?;
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn creates_the_file_on_the_first_line() {
+        let dir = env::temp_dir().join(format!("itask-logfile-{}", std::process::id()));
+        let path = LogFile::path(&dir, "cargo run --bin sites");
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("cargo-run---bin-sites-"), "{name}");
+
+        let mut log = LogFile::new(path.clone());
+        assert!(!path.exists());
+        log.write("one").unwrap();
+        log.write("two").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fails_once() {
+        // A file where the directory should be
+        let blocker = env::temp_dir().join(format!("itask-blocker-{}", std::process::id()));
+        fs::write(&blocker, "").unwrap();
+
+        let mut log = LogFile::new(blocker.join("job.log"));
+        assert!(log.write("one").is_err());
+        assert!(log.write("two").is_ok());
+        fs::remove_file(blocker).unwrap();
+    }
+}
@@ -0,0 +1,424 @@
+use anyhow::bail;
+
+/// Enough of regular expressions to filter output with: literals, `.`,
+/// `[...]` classes, `\d` `\w` `\s` and their negations, `*` `+` `?` and
+/// `{n,m}`, `^` and `$`, groups and `|`. Case sensitive. Matching steps
+/// through every way the pattern could go at once rather than backtracking,
+/// so it takes time in proportion to the pattern times the text, however
+/// the pattern is written.
+#[derive(Clone, Debug)]
+pub struct Regex(Vec<Inst>);
+
+/// A step of a compiled [`Regex`].
+#[derive(Clone, Debug)]
+enum Inst {
+    /// One char that a [`Node::Char`], [`Node::Any`] or [`Node::Class`]
+    /// accepts.
+    Take(Node),
+    Start,
+    End,
+    /// Either way on, without taking a char.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    Start,
+    End,
+    /// Alternatives, each a sequence.
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+impl Node {
+    fn repeat(node: Node, (min, max): (usize, Option<usize>)) -> Self {
+        Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        }
+    }
+
+    /// Whether a node that takes a char takes `c`.
+    fn accepts(&self, c: char) -> bool {
+        match self {
+            Node::Char(expected) => *expected == c,
+            Node::Any => true,
+            Node::Class { items, negated } => items.iter().any(|item| item.matches(c)) != *negated,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum ClassItem {
+    Range(char, char),
+    /// `d`, `w` or `s`, upper case for the negation.
+    Escape(char),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Range(from, to) => (from..=to).contains(&c),
+            ClassItem::Escape(e) => {
+                let matched = match e.to_ascii_lowercase() {
+                    'd' => c.is_ascii_digit(),
+                    'w' => c.is_alphanumeric() || c == '_',
+                    _ => c.is_whitespace(),
+                };
+                matched != e.is_ascii_uppercase()
+            }
+        }
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> anyhow::Result<Self> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let alternatives = parser.alternatives()?;
+        if parser.pos < parser.chars.len() {
+            bail!("unmatched `)`");
+        }
+        let mut program = vec![];
+        compile(&Node::Group(alternatives), &mut program);
+        program.push(Inst::Match);
+        Ok(Self(program))
+    }
+
+    /// Whether the pattern matches anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        let text = text.chars().collect::<Vec<_>>();
+        let (mut threads, mut next) = (vec![], vec![]);
+        let mut seen = vec![0; self.0.len()];
+        for i in 0..=text.len() {
+            // A fresh start at every char, for matches anywhere
+            if add(&self.0, &mut threads, &mut seen, 0, (i, text.len())) {
+                return true;
+            }
+            let Some(&c) = text.get(i) else {
+                break;
+            };
+            for &pc in &threads {
+                let Inst::Take(node) = &self.0[pc] else {
+                    continue;
+                };
+                if node.accepts(c)
+                    && add(&self.0, &mut next, &mut seen, pc + 1, (i + 1, text.len()))
+                {
+                    return true;
+                }
+            }
+            threads.clear();
+            std::mem::swap(&mut threads, &mut next);
+        }
+        false
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn alternatives(&mut self) -> anyhow::Result<Vec<Vec<Node>>> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> anyhow::Result<Vec<Node>> {
+        let mut nodes = vec![];
+        while let Some(c) = self.peek().filter(|c| !matches!(c, '|' | ')')) {
+            self.pos += 1;
+            let node = match c {
+                '(' => {
+                    let group = Node::Group(self.alternatives()?);
+                    if self.next() != Some(')') {
+                        bail!("unclosed `(`");
+                    }
+                    group
+                }
+                '[' => self.class()?,
+                '.' => Node::Any,
+                '^' => Node::Start,
+                '$' => Node::End,
+                '\\' => self.escape()?,
+                '*' | '+' | '?' => bail!("nothing to repeat before `{c}`"),
+                c => Node::Char(c),
+            };
+            nodes.push(self.repeat(node));
+        }
+        Ok(nodes)
+    }
+
+    /// Wraps `node` in whatever quantifier follows it. A `{` that doesn't
+    /// start a valid `{n}`, `{n,}` or `{n,m}` is left to be a literal.
+    fn repeat(&mut self, node: Node) -> Node {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.bounds() {
+                Some(bounds) => return Node::repeat(node, bounds),
+                None => return node,
+            },
+            _ => return node,
+        };
+        self.pos += 1;
+        Node::repeat(node, (min, max))
+    }
+
+    /// Parses `{n}`, `{n,}` or `{n,m}` at the cursor, stepping past it.
+    fn bounds(&mut self) -> Option<(usize, Option<usize>)> {
+        let rest = self.chars[self.pos + 1..].iter().collect::<String>();
+        let inner = &rest[..rest.find('}')?];
+        let bounds = match inner.split_once(',') {
+            None => {
+                let n = inner.parse().ok()?;
+                (n, Some(n))
+            }
+            Some((min, "")) => (min.parse().ok()?, None),
+            Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+        };
+        if bounds.1.is_some_and(|max| max < bounds.0) {
+            return None;
+        }
+        self.pos += inner.chars().count() + 2;
+        Some(bounds)
+    }
+
+    fn escape(&mut self) -> anyhow::Result<Node> {
+        Ok(match self.next() {
+            None => bail!("trailing `\\`"),
+            Some(e @ ('d' | 'D' | 'w' | 'W' | 's' | 'S')) => Node::Class {
+                items: vec![ClassItem::Escape(e)],
+                negated: false,
+            },
+            Some('n') => Node::Char('\n'),
+            Some('t') => Node::Char('\t'),
+            Some(c) => Node::Char(c),
+        })
+    }
+
+    fn class(&mut self) -> anyhow::Result<Node> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut items = vec![];
+        loop {
+            let c = match self.next() {
+                None => bail!("unclosed `[`"),
+                // A `]` straight after the `[` is taken literally
+                Some(']') if !items.is_empty() => break,
+                Some('\\') => match self.next() {
+                    None => bail!("unclosed `[`"),
+                    Some(e @ ('d' | 'D' | 'w' | 'W' | 's' | 'S')) => {
+                        items.push(ClassItem::Escape(e));
+                        continue;
+                    }
+                    Some(c) => c,
+                },
+                Some(c) => c,
+            };
+
+            let to = match (self.peek(), self.chars.get(self.pos + 1)) {
+                (Some('-'), Some(&to)) if to != ']' => {
+                    self.pos += 2;
+                    to
+                }
+                _ => c,
+            };
+            if to < c {
+                bail!("bad range `{c}-{to}`");
+            }
+            items.push(ClassItem::Range(c, to));
+        }
+        Ok(Node::Class { items, negated })
+    }
+}
+
+/// Compiles `node` onto the end of `program`. Counted repeats are spelled
+/// out, a copy of `node` each.
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Char(_) | Node::Any | Node::Class { .. } => program.push(Inst::Take(node.clone())),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Group(alternatives) => {
+            let mut jumps = vec![];
+            for (n, sequence) in alternatives.iter().enumerate() {
+                let last = n + 1 == alternatives.len();
+                let split = program.len();
+                if !last {
+                    program.push(Inst::Split(split + 1, 0));
+                }
+                sequence.iter().for_each(|node| compile(node, program));
+                if !last {
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile(node, program);
+            }
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(node, program);
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                Some(max) => {
+                    let mut splits = vec![];
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        compile(node, program);
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Adds the thread at `pc` to `threads`, following jumps and anchors to
+/// the instructions that take a char. `seen` marks what's been added for
+/// position `i` already, so each instruction is there at most once.
+/// Whether the pattern matched along the way.
+fn add(
+    program: &[Inst],
+    threads: &mut Vec<usize>,
+    seen: &mut [usize],
+    pc: usize,
+    (i, len): (usize, usize),
+) -> bool {
+    let mut stack = vec![pc];
+    while let Some(pc) = stack.pop() {
+        if seen[pc] == i + 1 {
+            continue;
+        }
+        seen[pc] = i + 1;
+        match &program[pc] {
+            Inst::Match => return true,
+            Inst::Jump(to) => stack.push(*to),
+            Inst::Split(first, second) => stack.extend([*second, *first]),
+            Inst::Start if i == 0 => stack.push(pc + 1),
+            Inst::End if i == len => stack.push(pc + 1),
+            Inst::Start | Inst::End => {}
+            Inst::Take(_) => threads.push(pc),
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn matches_anywhere_unless_anchored() {
+        assert!(is_match("err", "an error"));
+        assert!(!is_match("^err", "an error"));
+        assert!(is_match("^an e", "an error"));
+        assert!(is_match("or$", "an error"));
+        assert!(!is_match("Err", "an error"));
+        assert!(is_match("", "anything"));
+    }
+
+    #[test]
+    fn classes_and_repeats() {
+        assert!(is_match(r"GET /\w+ \d{3}", "GET /users 200"));
+        assert!(!is_match(r"GET /\w+ \d{3}", "GET /users 20"));
+        assert!(is_match("^[a-c]+x?$", "abcab"));
+        assert!(!is_match("^[^a-c]", "abc"));
+        assert!(is_match("[]x]", "a]"));
+        assert!(is_match("a.*z", "a to z"));
+        assert!(is_match(r"^\S+\s\S+$", "two words"));
+        assert!(is_match("x{2,}", "axxx"));
+        assert!(is_match("a{,", "a{,"));
+    }
+
+    #[test]
+    fn groups_and_alternatives() {
+        assert!(is_match("^(warn|error):", "error: nope"));
+        assert!(!is_match("^(warn|error):", "info: fine"));
+        assert!(is_match("^(ab)+$", "ababab"));
+        assert!(!is_match("^(ab)+$", "ababa"));
+        assert!(is_match("(a*)*b", "aaab"));
+        assert!(!is_match("^(a*)*$", "aaab"));
+    }
+
+    #[test]
+    fn long_lines_match_without_running_out_of_stack() {
+        let line = format!("error: {} timeout", "x".repeat(100_000));
+        assert!(is_match("error.*timeout", &line));
+        assert!(!is_match("error.*timeout$", &format!("{line}!")));
+    }
+
+    #[test]
+    fn nested_repeats_take_linear_time() {
+        let line = "a".repeat(10_000);
+        assert!(!is_match("(a|a)*b", &line));
+        assert!(!is_match("^(a*)*$", &format!("{line}b")));
+        assert!(is_match("^(a|aa)+$", &line));
+    }
+
+    #[test]
+    fn reports_bad_patterns() {
+        let err = |pattern| Regex::new(pattern).unwrap_err().to_string();
+        assert_eq!(err("(a"), "unclosed `(`");
+        assert_eq!(err("a)"), "unmatched `)`");
+        assert_eq!(err("[a"), "unclosed `[`");
+        assert_eq!(err("*a"), "nothing to repeat before `*`");
+        assert_eq!(err("[z-a]"), "bad range `z-a`");
+        assert_eq!(err("a\\"), "trailing `\\`");
+    }
+}
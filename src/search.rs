@@ -0,0 +1,130 @@
+use std::ops::Range;
+
+use ratatui::style::{Style, Stylize};
+
+use crate::regex::Regex;
+
+/// Byte ranges of every non-overlapping, case-insensitive match of
+/// `pattern` in `line`, left to right.
+pub fn find_matches(line: &str, pattern: &str) -> Vec<Range<usize>> {
+    let mut matches = vec![];
+    if pattern.is_empty() {
+        return matches;
+    }
+
+    let mut from = 0;
+    while from < line.len() {
+        match match_at(&line[from..], pattern) {
+            Some(len) => {
+                matches.push(from..from + len);
+                from += len;
+            }
+            None => from += line[from..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    matches
+}
+
+/// Length in bytes of `pattern` matched at the start of `s`, ignoring case.
+fn match_at(s: &str, pattern: &str) -> Option<usize> {
+    let mut chars = s.char_indices();
+    for p in pattern.chars() {
+        let (_, c) = chars.next()?;
+        if !c.to_lowercase().eq(p.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(s.len(), |(i, _)| i))
+}
+
+/// Every match of `pattern` in `line`, styled to stand out, ready to lay
+/// over the line with [`ansi::styled`](crate::ansi::styled).
+pub fn highlights(line: &str, pattern: &str) -> Vec<(Range<usize>, Style)> {
+    find_matches(line, pattern)
+        .into_iter()
+        .map(|m| (m, Style::new().black().on_yellow()))
+        .collect()
+}
+
+/// What a pane's output is narrowed to: a [`Regex`] where `pattern` parses
+/// as one, plain text matched like a search otherwise.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    pub pattern: String,
+    regex: Option<Regex>,
+}
+
+impl Filter {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            regex: Regex::new(pattern).ok(),
+        }
+    }
+
+    pub fn is_regex(&self) -> bool {
+        self.regex.is_some()
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(line),
+            None => !find_matches(line, &self.pattern).is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::text::Span;
+
+    use super::*;
+    use crate::ansi;
+
+    fn highlight(line: &str, pattern: &str) -> Vec<Span<'static>> {
+        ansi::styled(line, &highlights(line, pattern))
+    }
+
+    #[test]
+    fn finds_every_match() {
+        assert_eq!(find_matches("error: an error", "error"), [0..5, 10..15]);
+        assert_eq!(find_matches("aaaa", "aa"), [0..2, 2..4]);
+        assert!(find_matches("nothing here", "error").is_empty());
+        assert!(find_matches("anything", "").is_empty());
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(find_matches("Error ERROR error", "eRRor").len(), 3);
+        assert_eq!(find_matches("CAFÉ café", "café"), [0..5, 6..11]);
+    }
+
+    #[test]
+    fn highlights_between_plain_spans() {
+        let spans = highlight("warn: disk warn", "WARN");
+        let text = spans.iter().map(|s| s.content.as_ref()).collect::<Vec<_>>();
+        assert_eq!(text, ["warn", ": disk ", "warn"]);
+        assert_eq!(spans[0].style, Style::new().black().on_yellow());
+        assert_eq!(spans[1].style, Style::new());
+    }
+
+    #[test]
+    fn leaves_unmatched_lines_whole() {
+        let spans = highlight("🚀 launch", "land");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].content, "🚀 launch");
+    }
+
+    #[test]
+    fn filters_fall_back_to_text() {
+        let regex = Filter::new(r"^GET /\w+ 5\d\d");
+        assert!(regex.is_regex());
+        assert!(regex.matches("GET /users 503"));
+        assert!(!regex.matches("GET /users 200"));
+
+        let text = Filter::new("retry (");
+        assert!(!text.is_regex());
+        assert!(text.matches("Retry (2/3)"));
+        assert!(!text.matches("retry 2"));
+    }
+}
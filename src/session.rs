@@ -0,0 +1,117 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{
+    job::JobConfig,
+    toml::{self, Table, Value},
+};
+
+/// What to do with the jobs left running last time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionRestore {
+    /// Ask before starting them again.
+    Prompt,
+    /// Start them straight away.
+    Autostart,
+}
+
+impl SessionRestore {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "prompt" => Some(SessionRestore::Prompt),
+            "autostart" => Some(SessionRestore::Autostart),
+            _ => None,
+        }
+    }
+}
+
+/// Remembers which jobs were running when itask quit. Nothing is saved or
+/// restored unless one is set on the model.
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub path: PathBuf,
+    pub restore: SessionRestore,
+}
+
+impl Session {
+    pub fn new(path: impl Into<PathBuf>, restore: SessionRestore) -> Self {
+        Self {
+            path: path.into(),
+            restore,
+        }
+    }
+
+    pub fn save(&self, jobs: &[JobConfig]) -> anyhow::Result<()> {
+        if jobs.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+
+        let mut table = Table::new();
+        table.insert(
+            "job".into(),
+            Value::Array(jobs.iter().map(|j| Value::Table(j.to_table())).collect()),
+        );
+
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).context("failed to create session directory")?;
+        }
+        fs::write(&self.path, toml::to_string(&table)).context("failed to write session")
+    }
+
+    pub fn load(&self) -> anyhow::Result<Vec<JobConfig>> {
+        load(&self.path)
+    }
+}
+
+fn load(path: &Path) -> anyhow::Result<Vec<JobConfig>> {
+    let src = match fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e).context("failed to read session"),
+    };
+
+    let table = toml::parse(&src).context("failed to parse session")?;
+    table
+        .get("job")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|job| JobConfig::from_table(job.as_table().context("job must be a table")?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_and_loads_jobs() {
+        let dir = std::env::temp_dir().join(format!("itask-session-{}", std::process::id()));
+        let session = Session::new(dir.join("session.toml"), SessionRestore::Prompt);
+        assert!(session.load().unwrap().is_empty());
+
+        let jobs = [
+            JobConfig::new("cargo", &["run"]),
+            JobConfig::new("npm", &["run", "dev server"]).env("PORT", "5173"),
+            JobConfig::new("cargo", &["watch"]).env("RUST_LOG", "debug"),
+        ];
+        session.save(&jobs).unwrap();
+        let loaded = session.load().unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[1].args, ["run", "dev server"]);
+        assert_eq!(loaded[1].env["PORT"], "5173");
+        assert_eq!(loaded[2].env["RUST_LOG"], "debug");
+        assert!(loaded[0].env.is_empty());
+
+        session.save(&[]).unwrap();
+        assert!(!session.path.exists());
+        fs::remove_dir_all(dir).ok();
+    }
+}
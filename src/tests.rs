@@ -0,0 +1,1920 @@
+use std::{
+    sync::Arc,
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    backend::TestBackend,
+    buffer::Buffer,
+    crossterm::event::{
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    layout::Rect,
+    style::{Color, Modifier},
+    Terminal,
+};
+
+use crate::{
+    job::{Job, JobStatus, LogLine},
+    lock::Recover,
+    search::Filter,
+    ui::{main_menu, Fraction, Menu, Palette, Progress, Prompt, BINDINGS},
+    Action, App, Config, FocusPolicy, JobConfig, Model, OutputLayout, Readiness, RerunPolicy,
+    Theme,
+};
+
+fn render(app: &mut App<TestBackend>) -> Buffer {
+    app.draw().unwrap();
+    app.terminal().backend().buffer().clone()
+}
+
+/// Polls `cond` until it holds or five seconds are up, whether it held.
+pub(crate) fn wait_until(mut cond: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !cond() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        sleep(Duration::from_millis(20));
+    }
+    true
+}
+
+fn app(width: u16, height: u16) -> App<TestBackend> {
+    App::with_terminal(Terminal::new(TestBackend::new(width, height)).unwrap())
+}
+
+fn open_menu(model: &Arc<Model>) {
+    *model.menu.write_or_recover() = model.main_menu().first();
+}
+
+/// Position of the first cell of `text`, searching row by row.
+fn find(buf: &Buffer, text: &str) -> Option<(u16, u16)> {
+    let area = buf.area;
+    (area.top()..area.bottom()).find_map(|y| {
+        let cells = (area.left()..area.right())
+            .map(|x| (x, buf[(x, y)].symbol()))
+            .collect::<Vec<_>>();
+        (0..cells.len()).find_map(|start| {
+            let mut chars = text.chars();
+            let mut i = start;
+            loop {
+                let Some(c) = chars.next() else {
+                    return Some((cells[start].0, y));
+                };
+                if cells.get(i)?.1 != c.to_string() {
+                    return None;
+                }
+                i += 1;
+            }
+        })
+    })
+}
+
+#[test]
+fn banner_visible_when_idle() {
+    let mut app = app(80, 24);
+    let buf = render(&mut app);
+
+    assert!(find(&buf, "Interactive Task Manager").is_some());
+    assert!(find(&buf, "[j]     : Open or close the jobs menu").is_some());
+    assert!(find(&buf, "[?]     : Show or hide this help").is_some());
+    assert!(find(&buf, "Run (Server)").is_none());
+}
+
+#[test]
+fn menu_opens_beside_banner() {
+    let mut app = app(100, 24);
+    open_menu(app.model());
+    let buf = render(&mut app);
+
+    let (title_x, title_y) = find(&buf, "Jobs").unwrap();
+    let (item_x, item_y) = find(&buf, "Run (Server)").unwrap();
+    let (_, next_y) = find(&buf, "Build Frontend").unwrap();
+    let (banner_x, _) = find(&buf, "Interactive Task Manager").unwrap();
+
+    assert_eq!(title_y, 0);
+    assert!(title_x > banner_x);
+    assert_eq!((item_y, next_y), (1, 2));
+    assert!(item_x > banner_x);
+
+    // The first entry is selected
+    assert_eq!(buf[(item_x, item_y)].bg, Color::White);
+    assert_ne!(buf[(item_x, next_y)].bg, Color::White);
+}
+
+#[test]
+fn prompt_is_centered() {
+    let mut app = app(80, 24);
+    *app.model().prompt.write_or_recover() = Some(Prompt::new("Test prompt", |_| Ok(())));
+    let buf = render(&mut app);
+
+    // Modal spans the middle half horizontally, 6 rows tall around the centre
+    assert_eq!(find(&buf, "Test prompt"), Some((21, 9)));
+    assert_eq!(buf[(20, 9)].symbol(), "┌");
+    assert_eq!(buf[(59, 14)].symbol(), "┘");
+}
+
+fn with_policy(policy: FocusPolicy) -> Arc<Model> {
+    let model = Arc::new(Model::default());
+    model.set_focus_policy(policy);
+    model
+}
+
+#[test]
+fn focus_follows_newest() {
+    let model = with_policy(FocusPolicy::FollowNewest);
+    model.start_job(JobConfig::new("true", &[])).unwrap();
+    assert_eq!(model.focused(), Some(0));
+    model.start_job(JobConfig::new("true", &[])).unwrap();
+    assert_eq!(model.focused(), Some(1));
+}
+
+#[test]
+fn focus_keeps_current() {
+    let model = with_policy(FocusPolicy::KeepCurrent);
+    model.start_job(JobConfig::new("true", &[])).unwrap();
+    model.start_job(JobConfig::new("true", &[])).unwrap();
+    assert_eq!(model.focused(), Some(0));
+}
+
+#[test]
+fn focus_follows_first_output() {
+    let model = with_policy(FocusPolicy::FollowFirstOutput);
+    model
+        .start_job(JobConfig::new("sh", &["-c", "sleep 0.2; echo ready"]))
+        .unwrap();
+    model.sync_focus();
+    assert_eq!(model.focused(), None);
+
+    wait_until(|| {
+        model.sync_focus();
+        model.focused().is_some()
+    });
+    assert_eq!(model.focused(), Some(0));
+}
+
+#[test]
+fn bookmarks_jump_between_marked_lines() {
+    let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+    for i in 0..10 {
+        job.logs
+            .write_or_recover()
+            .push(LogLine::note(i.to_string()));
+    }
+
+    job.scroll = Some(2);
+    job.toggle_bookmark();
+    job.scroll = Some(7);
+    job.toggle_bookmark();
+
+    // Following the tail, the newest line is the current one
+    job.scroll = None;
+    job.prev_bookmark();
+    assert_eq!(job.scroll, Some(7));
+    job.prev_bookmark();
+    assert_eq!(job.scroll, Some(2));
+    job.prev_bookmark();
+    assert_eq!(job.scroll, Some(2));
+    job.next_bookmark();
+    assert_eq!(job.scroll, Some(7));
+
+    job.toggle_bookmark();
+    job.next_bookmark();
+    assert_eq!(job.scroll, None);
+    assert_eq!(job.bookmarks.iter().collect::<Vec<_>>(), [&2]);
+}
+
+#[test]
+fn n_and_shift_n_jump_between_search_matches() {
+    let mut job = Job::start(JobConfig::new("true", &[]), Default::default());
+    for text in ["build", "warning: a", "ok", "ok", "WARNING: b", "ok"] {
+        job.logs.write_or_recover().push(LogLine::note(text));
+    }
+
+    // Nothing to find without a search
+    job.prev_match();
+    assert_eq!(job.scroll, None);
+
+    job.search = Some("warning".into());
+    job.prev_match();
+    assert_eq!(job.scroll, Some(4));
+    job.prev_match();
+    assert_eq!(job.scroll, Some(1));
+    job.prev_match();
+    assert_eq!(job.scroll, Some(1));
+    job.next_match();
+    assert_eq!(job.scroll, Some(4));
+    job.next_match();
+    assert_eq!(job.scroll, Some(4));
+
+    // Lines the filter hides don't count
+    job.filter = Some(Filter::new("a$"));
+    job.scroll = None;
+    job.prev_match();
+    assert_eq!(job.scroll, Some(1));
+}
+
+#[test]
+fn hooks_run_on_matching_events() {
+    let dir = std::env::temp_dir().join(format!("itask-hooks-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let out = dir.join("events");
+    let script = dir.join("hook.sh");
+    std::fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\necho \"$ITASK_EVENT $ITASK_EXIT_CODE$ITASK_LINE: $ITASK_COMMAND\" >> {}\n",
+            out.display()
+        ),
+    )
+    .unwrap();
+    std::process::Command::new("chmod")
+        .arg("+x")
+        .arg(&script)
+        .status()
+        .unwrap();
+
+    let model = Arc::new(Model::default());
+    let hooks = format!(
+        "[[hook]]\nscript = \"{0}\"\non = \"failure\"\n\n\
+         [[hook]]\nscript = \"{0}\"\non = \"match\"\npattern = \"oops\"\n",
+        script.display()
+    );
+    model.set_config(Config::parse(&hooks).unwrap());
+    model
+        .start_job(JobConfig::new(
+            "sh",
+            &["-c", "echo fine; echo oops; exit 3"],
+        ))
+        .unwrap();
+
+    let mut events = String::new();
+    wait_until(|| {
+        events = std::fs::read_to_string(&out).unwrap_or_default();
+        events.lines().count() >= 2
+    });
+
+    let mut events = events.lines().collect::<Vec<_>>();
+    events.sort();
+    assert_eq!(
+        events,
+        [
+            "exit 3: sh -c echo fine; echo oops; exit 3",
+            "line oops: sh -c echo fine; echo oops; exit 3"
+        ]
+    );
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn match_hooks_wait_for_the_last_run_and_failures_are_noted() {
+    let dir = std::env::temp_dir().join(format!("itask-hook-runs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let out = dir.join("runs");
+    let script = dir.join("slow.sh");
+    std::fs::write(
+        &script,
+        format!("#!/bin/sh\necho run >> {}\nsleep 1\n", out.display()),
+    )
+    .unwrap();
+    std::process::Command::new("chmod")
+        .arg("+x")
+        .arg(&script)
+        .status()
+        .unwrap();
+
+    let model = Arc::new(Model::default());
+    let hooks = format!(
+        "[[hook]]\nscript = \"{}\"\non = \"match\"\npattern = \"oops\"\n\n\
+         [[hook]]\nscript = \"{}\"\non = \"exit\"\n",
+        script.display(),
+        dir.join("missing.sh").display()
+    );
+    model.set_config(Config::parse(&hooks).unwrap());
+    model
+        .start_job(JobConfig::new(
+            "sh",
+            &["-c", "for i in 1 2 3 4 5; do echo oops; done"],
+        ))
+        .unwrap();
+
+    let failed = format!("[hook `{}` failed:", dir.join("missing.sh").display());
+    assert!(wait_until(|| output(&model, 0)
+        .iter()
+        .any(|l| l.starts_with(&failed))));
+    let runs = || {
+        std::fs::read_to_string(&out)
+            .unwrap_or_default()
+            .lines()
+            .count()
+    };
+    assert!(wait_until(|| runs() > 0));
+    assert_eq!(runs(), 1);
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn menu_items_keep_default_icons_unless_set() {
+    let menu = main_menu();
+    let icon = |title: &str| {
+        let item = menu.0.iter().find(|i| i.title() == title).unwrap();
+        item.icon().to_string()
+    };
+
+    assert_eq!(icon("Sites (bin)"), "🦀");
+    assert_eq!(icon("Sites (wasm)"), "📦");
+    assert_eq!(icon("Set ENV"), "🧭");
+    assert_eq!(icon("Build Frontend"), "📂");
+}
+
+#[test]
+fn palette_ranks_menu_items_fuzzily() {
+    let mut app = app(100, 24);
+    let mut palette = Palette::new(main_menu().commands());
+    for c in "bfe".chars() {
+        palette.input(KeyCode::Char(c));
+    }
+    *app.model().palette.write_or_recover() = Some(palette);
+    let buf = render(&mut app);
+
+    let (_, first) = find(&buf, "Build Frontend › Sites (wasm)").unwrap();
+    let (_, second) = find(&buf, "Build Frontend › Something (wasm+elm)").unwrap();
+    assert!(first < second);
+    assert!(find(&buf, "Set ENV").is_none());
+
+    // Matched chars stand out
+    let (x, y) = find(&buf, "Build").unwrap();
+    assert_eq!(buf[(x, y)].fg, Color::Yellow);
+    assert_ne!(buf[(x + 1, y)].fg, Color::Yellow);
+}
+
+#[test]
+fn dependents_wait_for_readiness() {
+    let model = Arc::new(Model::default());
+    model
+        .start_job(
+            JobConfig::new("sh", &["-c", "sleep 0.3; echo Listening on :8080; sleep 2"])
+                .name("backend")
+                .ready_when(Readiness::Line("Listening on".into())),
+        )
+        .unwrap();
+    let frontend = model
+        .start_job(
+            JobConfig::new("echo", &["built"])
+                .name("frontend")
+                .after(&["backend"]),
+        )
+        .unwrap();
+
+    let indicator = || model.jobs.read_or_recover()[frontend].indicator();
+    assert_eq!(indicator().as_deref(), Some("waiting for backend"));
+
+    let built = || {
+        let jobs = model.jobs.read_or_recover();
+        let logs = jobs[frontend].logs.read_or_recover();
+        logs.lines.iter().any(|l| l.text == "built")
+    };
+    assert!(wait_until(built));
+    assert!(!indicator().unwrap_or_default().starts_with("waiting"));
+}
+
+#[test]
+fn dependency_cycles_are_refused() {
+    let model = Arc::new(Model::default());
+    model
+        .start_job(JobConfig::new("true", &[]).name("a").after(&["b"]))
+        .unwrap();
+    let err = model
+        .start_job(JobConfig::new("true", &[]).name("b").after(&["a"]))
+        .unwrap_err();
+    assert!(err.to_string().contains("waiting on itself"));
+}
+
+#[test]
+fn jobs_lay_out_in_a_grid() {
+    let mut app = app(90, 24);
+    for title in ["server", "watcher", "tests"] {
+        let idx = app
+            .model()
+            .start_job(JobConfig::new("true", &[]).name(title))
+            .unwrap();
+        assert_eq!(app.model().jobs.read_or_recover()[idx].title, title);
+    }
+    let buf = render(&mut app);
+
+    let server = find(&buf, "server").unwrap();
+    let watcher = find(&buf, "watcher").unwrap();
+    let tests = find(&buf, "tests").unwrap();
+    assert_eq!(server.1, watcher.1);
+    assert!(server.0 < watcher.0);
+    assert!(tests.1 > server.1);
+
+    // The short last row gives its one pane the full width
+    assert_eq!(tests.0, server.0);
+}
+
+#[test]
+fn each_pane_shows_its_own_job() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new("echo", &["from the first"]).name("first"))
+        .unwrap();
+    model
+        .start_job(JobConfig::new("echo", &["from the second"]).name("second"))
+        .unwrap();
+
+    wait_until(|| find(&render(&mut app), "from the second").is_some());
+    let buf = render(&mut app);
+
+    let first = find(&buf, "from the first").unwrap();
+    let second = find(&buf, "from the second").unwrap();
+    assert_ne!(first, second);
+    assert_ne!(find(&buf, "first").unwrap(), find(&buf, "second").unwrap());
+}
+
+#[test]
+fn stderr_lines_are_red() {
+    let mut app = app(80, 24);
+    app.model()
+        .start_job(JobConfig::new("sh", &["-c", "echo out; echo err >&2"]))
+        .unwrap();
+
+    // The command itself is shown under the pane, so wait on the output
+    // rather than the screen
+    let model = app.model().clone();
+    wait_until(|| {
+        model.jobs.read_or_recover()[0]
+            .logs
+            .read_or_recover()
+            .lines
+            .len()
+            >= 2
+    });
+
+    let buf = render(&mut app);
+    let out = find(&buf, "out").unwrap();
+    let err = find(&buf, "err").unwrap();
+    assert_eq!(buf[err].fg, Color::Red);
+    assert_ne!(buf[out].fg, Color::Red);
+}
+
+#[test]
+fn split_output_gives_stderr_its_own_area() {
+    let mut app = app(60, 20);
+    let model = app.model().clone();
+    model
+        .start_job(
+            JobConfig::new("sh", &["-c", "echo out; echo o\"\"ops >&2; echo more"])
+                .output(OutputLayout::Split),
+        )
+        .unwrap();
+
+    wait_until(|| {
+        output(&model, 0).len() >= 2 && !model.jobs.read_or_recover()[0].stderr_tail(1).is_empty()
+    });
+    assert_eq!(output(&model, 0), ["out", "more"]);
+
+    let buf = render(&mut app);
+    let (_, out) = find(&buf, "more").unwrap();
+    let (_, title) = find(&buf, "stderr").unwrap();
+    let (x, oops) = find(&buf, "oops").unwrap();
+    assert!(out < title && title < oops, "{out} {title} {oops}");
+    assert_eq!(buf[(x, oops)].fg, Color::Red);
+}
+
+#[test]
+fn ansi_colors_show_in_panes() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new(
+            "printf",
+            &["\\033[1;32mCompiling\\033[0m plain\\033[2K\\n"],
+        ))
+        .unwrap();
+
+    wait_until(|| !output(&model, 0).is_empty());
+    assert_eq!(output(&model, 0), ["Compiling plain"]);
+
+    let buf = render(&mut app);
+    let (x, y) = find(&buf, "Compiling plain").unwrap();
+    assert_eq!(buf[(x, y)].fg, Color::Green);
+    assert!(buf[(x, y)].modifier.contains(Modifier::BOLD));
+    assert_ne!(buf[(x + 10, y)].fg, Color::Green);
+}
+
+#[test]
+fn crlf_and_invalid_utf8_keep_the_output_going() {
+    let model = Arc::new(Model::default());
+    model
+        .start_job(JobConfig::new("printf", &[r"one\r\n\377bad\r\nafter"]))
+        .unwrap();
+
+    wait_until(|| output(&model, 0).len() >= 3);
+    assert_eq!(output(&model, 0), ["one", "\u{fffd}bad", "after"]);
+}
+
+#[test]
+fn job_output_marks_the_model_dirty() {
+    let model = Arc::new(Model::default());
+    assert!(!model.take_dirty());
+
+    model
+        .start_job(JobConfig::new("sh", &["-c", "sleep 0.1; echo hi"]))
+        .unwrap();
+    wait_until(|| !model.jobs.read_or_recover()[0].running());
+
+    assert!(model.take_dirty());
+    assert!(!model.take_dirty());
+}
+
+#[test]
+fn menu_comes_from_config_when_set() {
+    let mut app = app(100, 24);
+    let config = Config::parse(
+        r#"
+        [[job]]
+        name = "API"
+        section = "Backend"
+        command = "cargo"
+        args = ["run"]
+
+        [[job]]
+        name = "Storybook"
+        command = "npm"
+        "#,
+    )
+    .unwrap();
+    app.model().set_config(config);
+    open_menu(app.model());
+    let buf = render(&mut app);
+
+    assert!(find(&buf, "Backend").is_some());
+    assert!(find(&buf, "Storybook").is_some());
+    assert!(find(&buf, "Run (Server)").is_none());
+}
+
+#[test]
+fn y_copies_a_menu_items_command_without_running_it() {
+    let mut app = app(100, 24);
+    let config = Config::parse(
+        r#"
+        [[job]]
+        name = "API"
+        command = "cargo"
+        args = ["run", "--bin", "api server"]
+        env = { RUST_LOG = "debug" }
+
+        [[job]]
+        name = "Lint"
+        section = "Tools"
+        command = "cargo"
+        args = ["clippy"]
+        "#,
+    )
+    .unwrap();
+    app.model().set_config(config);
+    open_menu(app.model());
+    press(app.model(), "y");
+
+    let buf = render(&mut app);
+    assert!(find(&buf, "Copied: RUST_LOG=debug cargo run --bin 'api server'").is_some());
+    assert_eq!(
+        app.model().copied().as_deref(),
+        Some("RUST_LOG=debug cargo run --bin 'api server'")
+    );
+    assert!(app.model().jobs.read_or_recover().is_empty());
+
+    app.model().update(Action::Key(KeyCode::Down.into()));
+    press(app.model(), "y");
+    assert!(find(&render(&mut app), "Tools doesn't run a command").is_some());
+}
+
+#[test]
+fn lines_take_the_colour_of_the_first_matching_prefix() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model
+        .start_job(
+            JobConfig::new("printf", &["WARN careful\\nERROR boom\\nplain\\n"])
+                .color("WARN", Color::Yellow)
+                .color("ERROR", Color::Red),
+        )
+        .unwrap();
+    wait_until(|| output(&model, 0).len() >= 3);
+
+    let buf = render(&mut app);
+    let fg = |text: &str| {
+        let (x, y) = find(&buf, text).unwrap();
+        buf[(x, y)].fg
+    };
+    assert_eq!(fg("WARN careful"), Color::Yellow);
+    assert_eq!(fg("ERROR boom"), Color::Red);
+    assert_eq!(fg("plain"), Color::Reset);
+}
+
+#[test]
+fn pinned_lines_stay_above_the_scrolling_output() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new(
+            "sh",
+            &[
+                "-c",
+                "echo compiled 1; seq 1 40; echo compiled 2; seq 41 80",
+            ],
+        ))
+        .unwrap();
+    wait_until(|| output(&model, 0).len() >= 82);
+    *model.focused.write_or_recover() = Some(0);
+
+    press(&model, "P");
+    press(&model, "compiled");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    let buf = render(&mut app);
+    assert_eq!(find(&buf, "pinned: compiled").map(|(_, y)| y), Some(0));
+    assert!(find(&buf, "compiled 1").is_some_and(|(_, y)| y == 1));
+    assert!(find(&buf, "compiled 2").is_some_and(|(_, y)| y == 2));
+    assert!(find(&buf, "80").is_some());
+
+    press(&model, "P");
+    press(&model, "nope");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    assert!(find(&render(&mut app), "nothing matching yet").is_some());
+
+    press(&model, "P");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    assert!(find(&render(&mut app), "pinned:").is_none());
+}
+
+#[test]
+fn i_types_lines_into_the_jobs_stdin() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model.start_job(JobConfig::new("cat", &[])).unwrap();
+    model
+        .start_job(JobConfig::new("cat", &[]).stdin(true))
+        .unwrap();
+    press(&model, "1i");
+    assert!(model.jobs.read_or_recover()[0].input.is_none());
+    let flash = model.flash.read_or_recover().clone().unwrap().0;
+    assert!(flash.contains("doesn't take input"), "{flash}");
+
+    press(&model, "2ix");
+    model.update(Action::Key(KeyCode::Backspace.into()));
+    press(&model, "hello");
+    model.update(Action::Key(KeyCode::Home.into()));
+    press(&model, "> ");
+    assert!(find(&render(&mut app), "> hello").is_some());
+
+    model.update(Action::Key(KeyCode::Enter.into()));
+    wait_until(|| output(&model, 1).len() >= 2);
+    assert_eq!(output(&model, 1), ["> > hello", "> hello"]);
+    assert_eq!(
+        model.jobs.read_or_recover()[1].input,
+        Some((0, String::new()))
+    );
+
+    // Keys are typed rather than bound until Esc
+    press(&model, "q");
+    assert!(model.prompt.read_or_recover().is_none());
+    model.update(Action::Key(KeyCode::Esc.into()));
+    assert!(model.jobs.read_or_recover()[1].input.is_none());
+    for job in model.jobs.read_or_recover().iter() {
+        job.stop();
+    }
+}
+
+#[test]
+fn input_to_a_job_that_isnt_reading_doesnt_block() {
+    let model = app(80, 24).model().clone();
+    model
+        .start_job(JobConfig::new("sleep", &["5"]).stdin(true))
+        .unwrap();
+    let jobs = model.jobs.read_or_recover();
+    let process = &jobs[0].process;
+    assert!(wait_until(|| process.write_line("").is_ok()));
+
+    // Far more than a pipe holds
+    let started = Instant::now();
+    let line = "x".repeat(1 << 16);
+    for _ in 0..16 {
+        process.write_line(&line).unwrap();
+    }
+    assert!(started.elapsed() < Duration::from_secs(1));
+    jobs[0].stop();
+}
+
+#[test]
+fn panes_show_the_pid_greyed_out_once_it_exits() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model.start_job(JobConfig::new("sleep", &["5"])).unwrap();
+    wait_until(|| model.jobs.read_or_recover()[0].process.alive());
+    let pid = model.jobs.read_or_recover()[0].process.pid().unwrap();
+    let label = format!("pid {pid}");
+
+    let buf = render(&mut app);
+    let (x, y) = find(&buf, &label).unwrap();
+    assert_ne!(buf[(x, y)].fg, Color::DarkGray);
+
+    model.jobs.read_or_recover()[0].stop();
+    wait_until(|| !model.jobs.read_or_recover()[0].process.alive());
+    let buf = render(&mut app);
+    let (x, y) = find(&buf, &label).unwrap();
+    assert_eq!(buf[(x, y)].fg, Color::DarkGray);
+}
+
+#[test]
+fn panes_show_how_full_their_scrollback_is() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new("seq", &["1", "30"]).log_cap(20))
+        .unwrap();
+    wait_until(|| find(&render(&mut app), "20/20 lines, full").is_some());
+    let buf = render(&mut app);
+    let (x, y) = find(&buf, "20/20 lines, full").unwrap();
+    assert_eq!(buf[(x, y)].fg, Theme::default().error.fg.unwrap());
+
+    model.start_job(JobConfig::new("echo", &["one"])).unwrap();
+    wait_until(|| find(&render(&mut app), "1/1000 lines").is_some());
+    assert!(find(&render(&mut app), "1/1000 lines").is_some());
+}
+
+#[test]
+fn finished_jobs_show_exit_and_duration() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new("true", &[]).name("ok"))
+        .unwrap();
+    model
+        .start_job(JobConfig::new("sh", &["-c", "exit 3"]).name("bad"))
+        .unwrap();
+
+    wait_until(|| model.jobs.read_or_recover().iter().all(|j| !j.running()));
+    let buf = render(&mut app);
+
+    assert!(find(&buf, "ok — exited 0 in 0.").is_some());
+    let (x, y) = find(&buf, "bad — exited 3 in 0.").unwrap();
+    assert_eq!(buf[(x - 1, y)].fg, Color::Red);
+    let (x, y) = find(&buf, "ok — exited").unwrap();
+    assert_ne!(buf[(x - 1, y)].fg, Color::Red);
+}
+
+#[test]
+fn nested_section_items_render_from_the_first_row() {
+    let mut app = app(100, 24);
+    let menu = main_menu();
+    let wasm = menu
+        .0
+        .iter()
+        .position(|i| i.title() == "Sites (wasm)")
+        .unwrap();
+    *app.model().menu.write_or_recover() = Some(wasm);
+    let buf = render(&mut app);
+
+    // Rows are local to the section, whatever the items' global indices
+    let (_, title) = find(&buf, "Build Frontend").unwrap();
+    let (_, first) = find(&buf, "Sites (wasm)").unwrap();
+    let (_, second) = find(&buf, "Something (wasm+elm)").unwrap();
+    assert_eq!((title, first, second), (0, 1, 2));
+    assert!(find(&buf, "Run (Server)").is_none());
+}
+
+#[test]
+fn back_selects_the_section_then_closes() {
+    let menu = main_menu();
+    let idx = |title: &str| menu.0.iter().position(|i| i.title() == title).unwrap();
+
+    assert_eq!(menu.back(idx("Sites (wasm)")), Some(idx("Build Frontend")));
+    assert_eq!(menu.back(idx("Build Frontend")), None);
+    assert_eq!(menu.back(menu.first().unwrap()), None);
+}
+
+#[test]
+fn actions_change_state_without_a_terminal() {
+    let model = Arc::new(Model::default());
+    model.set_config(Config::parse("[[job]]\ncommand = \"true\"\nsection = \"Tools\"\n").unwrap());
+    assert_eq!(Action::from_event(Event::Resize(80, 24)), None);
+
+    let first = model.main_menu().first().unwrap();
+    model.update(Action::Key(KeyCode::Char('j').into()));
+    assert_eq!(*model.menu.read_or_recover(), Some(first));
+    model.update(Action::Key(KeyCode::Enter.into()));
+    assert_ne!(*model.menu.read_or_recover(), Some(first));
+    model.update(Action::Key(KeyCode::Esc.into()));
+    assert_eq!(*model.menu.read_or_recover(), Some(first));
+    model.update(Action::Key(KeyCode::Esc.into()));
+    assert_eq!(*model.menu.read_or_recover(), None);
+
+    model.update(Action::Key(KeyCode::Char('?').into()));
+    assert!(*model.help.read_or_recover());
+    model.update(Action::Paste("ignored".into()));
+    model.update(Action::Key(KeyCode::Esc.into()));
+    assert!(!*model.help.read_or_recover());
+}
+
+#[test]
+fn keys_table_remaps_bindings() {
+    let model = Arc::new(Model::default());
+    model.set_config(
+        Config::parse("[[job]]\ncommand = \"true\"\n\n[keys]\nmenu = \"m\"\nhelp = \"F1\"\n")
+            .unwrap(),
+    );
+
+    press(&model, "j");
+    assert_eq!(*model.menu.read_or_recover(), None);
+    press(&model, "m");
+    assert!(model.menu.read_or_recover().is_some());
+    press(&model, "m");
+    assert_eq!(*model.menu.read_or_recover(), None);
+
+    press(&model, "?");
+    assert!(!*model.help.read_or_recover());
+    model.update(Action::Key(KeyCode::F(1).into()));
+    assert!(*model.help.read_or_recover());
+    model.update(Action::Key(KeyCode::F(1).into()));
+    assert!(!*model.help.read_or_recover());
+}
+
+fn press(model: &Arc<Model>, keys: &str) {
+    for c in keys.chars() {
+        model.update(Action::Key(KeyCode::Char(c).into()));
+    }
+}
+
+#[test]
+fn slash_filters_the_open_menu_across_sections() {
+    let model = Arc::new(Model::default());
+    model.set_config(
+        Config::parse(
+            r#"
+            [[job]]
+            name = "Lint"
+            section = "Checks"
+            command = "true"
+
+            [[job]]
+            name = "Echo"
+            section = "Deep"
+            command = "echo"
+            "#,
+        )
+        .unwrap(),
+    );
+    open_menu(&model);
+
+    press(&model, "/ech");
+    assert!(model.palette.read_or_recover().is_some());
+    assert!(model.prompt.read_or_recover().is_none());
+    model.update(Action::Key(KeyCode::Enter.into()));
+
+    assert!(model.palette.read_or_recover().is_none());
+    let jobs = model.jobs.read_or_recover();
+    assert_eq!(
+        jobs.iter().map(|j| j.title.as_str()).collect::<Vec<_>>(),
+        ["Echo"]
+    );
+}
+
+#[test]
+fn restarting_jobs_run_again_until_switched_off() {
+    let model = Arc::new(Model::default());
+    model
+        .start_job(
+            JobConfig::new("echo", &["tick"])
+                .retries(0, Duration::from_millis(50))
+                .restart(true),
+        )
+        .unwrap();
+    model.set_focus_policy(FocusPolicy::FollowNewest);
+    *model.focused.write_or_recover() = Some(0);
+
+    let ticks = || {
+        let jobs = model.jobs.read_or_recover();
+        let logs = jobs[0].logs.read_or_recover();
+        logs.lines.iter().filter(|l| l.text == "tick").count()
+    };
+    wait_until(|| ticks() >= 3);
+    assert!(ticks() >= 3);
+    assert!(model.jobs.read_or_recover()[0]
+        .display_title()
+        .ends_with("[restart]"));
+
+    press(&model, "r");
+    wait_until(|| !model.jobs.read_or_recover()[0].running());
+    assert!(!model.jobs.read_or_recover()[0].running());
+    let logs = model.jobs.read_or_recover()[0].logs.clone();
+    assert!(logs
+        .read_or_recover()
+        .lines
+        .iter()
+        .any(|l| l.text.ends_with("restarting]")));
+}
+
+#[test]
+fn v_selects_lines_that_arrows_extend() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new("printf", &["one\\ntwo\\nthree\\n"]))
+        .unwrap();
+
+    // The footer spells out the command, so wait on the output itself
+    wait_until(|| output(&model, 0).len() >= 3);
+
+    press(&model, "v");
+    model.update(Action::Key(KeyCode::Up.into()));
+    let text = model.jobs.read_or_recover()[0].selected_text();
+    assert_eq!(text.as_deref(), Some("two\nthree"));
+
+    let buf = render(&mut app);
+    assert_eq!(buf[find(&buf, "two").unwrap()].bg, Color::DarkGray);
+    assert_ne!(buf[find(&buf, "one").unwrap()].bg, Color::DarkGray);
+
+    model.update(Action::Key(KeyCode::Esc.into()));
+    assert_eq!(model.jobs.read_or_recover()[0].selection, None);
+
+    press(&model, "v");
+    model.update(Action::Key(KeyCode::Up.into()));
+    let text = model.jobs.read_or_recover()[0].selected_text();
+    press(&model, "y");
+    assert_eq!(model.copied(), text);
+    assert!(find(&render(&mut app), "Copied 2 lines").is_some());
+}
+
+#[test]
+fn unwrapped_panes_scroll_sideways() {
+    let mut app = app(40, 10);
+    let model = app.model().clone();
+    model
+        // Swapping case keeps the command in the footer from matching
+        .start_job(
+            JobConfig::new(
+                "sh",
+                &[
+                    "-c",
+                    "echo ABCDEFGHIJ0123456789abcdefghij0123456789klmn | tr a-zA-Z A-Za-z",
+                ],
+            )
+            .name("t"),
+        )
+        .unwrap();
+
+    wait_until(|| find(&render(&mut app), "abcdef").is_some());
+    let buf = render(&mut app);
+    let start = find(&buf, "abcdef").unwrap();
+    assert!(find(&buf, "KLMN").is_some());
+
+    // Arrows do nothing while wrapping
+    model.update(Action::Key(KeyCode::Right.into()));
+    assert_eq!(find(&render(&mut app), "abcdef"), Some(start));
+
+    press(&model, "w");
+    let buf = render(&mut app);
+    assert_eq!(find(&buf, "abcdef"), Some(start));
+    assert!(find(&buf, "KLMN").is_none());
+
+    model.update(Action::Key(KeyCode::Right.into()));
+    let buf = render(&mut app);
+    assert!(find(&buf, "abcdef").is_none());
+    assert_eq!(find(&buf, "ij0123"), Some(start));
+    assert!(find(&buf, "[no wrap, +8]").is_some());
+
+    model.update(Action::Key(KeyCode::Left.into()));
+    model.update(Action::Key(KeyCode::Left.into()));
+    assert_eq!(find(&render(&mut app), "abcdef"), Some(start));
+}
+
+#[test]
+fn quitting_asks_first_while_jobs_run() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model.start_job(JobConfig::new("sleep", &["5"])).unwrap();
+
+    press(&model, "q");
+    assert!(!model.quit());
+    assert!(find(&render(&mut app), "Quit and stop 1 running job(s)?").is_some());
+    press(&model, "n");
+    assert!(!model.quit());
+    model.update(Action::Key(KeyCode::Esc.into()));
+
+    press(&model, "qy");
+    assert!(model.quit());
+}
+
+#[test]
+fn quitting_is_immediate_once_jobs_finish() {
+    let app = app(80, 24);
+    let model = app.model().clone();
+    model.start_job(JobConfig::new("true", &[])).unwrap();
+
+    wait_until(|| !model.jobs.read_or_recover()[0].running());
+    press(&model, "q");
+    assert!(model.quit());
+}
+
+fn output(model: &Model, idx: usize) -> Vec<String> {
+    let logs = model.jobs.read_or_recover()[idx].logs.clone();
+    let logs = logs.read_or_recover();
+    logs.lines.iter().map(|l| l.text.clone()).collect()
+}
+
+#[test]
+fn shift_r_runs_the_last_job_again_in_its_pane() {
+    let model = Arc::new(Model::default());
+    model
+        .start_job(JobConfig::new("sh", &["-c", "echo run ${RUN:-1}"]))
+        .unwrap();
+
+    wait_until(|| !model.jobs.read_or_recover()[0].running());
+    assert_eq!(output(&model, 0), ["run 1"]);
+
+    model.set_env("RUN", "2");
+    press(&model, "R");
+    wait_until(|| !output(&model, 0).is_empty());
+    assert_eq!(model.jobs.read_or_recover().len(), 1);
+    assert_eq!(output(&model, 0), ["run 2"]);
+}
+
+#[test]
+fn rerunning_a_running_job_follows_the_policy() {
+    let model = Arc::new(Model::default());
+    model.start_job(JobConfig::new("sleep", &["5"])).unwrap();
+
+    press(&model, "R");
+    assert_eq!(output(&model, 0), ["[still running, not started again]"]);
+
+    model.set_rerun_policy(RerunPolicy::Restart);
+    let old = model.jobs.read_or_recover()[0].status.clone();
+    press(&model, "R");
+    wait_until(|| old.read_or_recover().finished());
+    assert!(matches!(*old.read_or_recover(), JobStatus::Stopped));
+    assert!(model.jobs.read_or_recover()[0].running());
+    assert!(output(&model, 0).is_empty());
+}
+
+#[test]
+fn f_filters_the_focused_pane() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new("printf", &[r"\141pple\n\142anana\n(\142)erry\n"]).name("fruit"))
+        .unwrap();
+
+    wait_until(|| find(&render(&mut app), "(b)erry").is_some());
+
+    press(&model, "f^b");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    let buf = render(&mut app);
+    assert!(find(&buf, "banana").is_some());
+    assert!(find(&buf, "apple").is_none());
+    assert!(find(&buf, "[filter: /^b/]").is_some());
+
+    // Not a valid regex, so taken as text
+    press(&model, "f(b");
+    let buf = render(&mut app);
+    assert!(find(&buf, "unclosed `(`, matching as plain text").is_some());
+    model.update(Action::Key(KeyCode::Enter.into()));
+    let buf = render(&mut app);
+    assert!(find(&buf, "(b)erry").is_some());
+    assert!(find(&buf, "banana").is_none());
+
+    press(&model, "f");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    let buf = render(&mut app);
+    assert!(find(&buf, "apple").is_some());
+    assert_eq!(
+        model.jobs.read_or_recover()[0]
+            .logs
+            .read_or_recover()
+            .lines
+            .len(),
+        3
+    );
+}
+
+#[test]
+fn tab_cycles_focus_and_x_stops_the_focused_job() {
+    let mut app = app(90, 12);
+    let model = app.model().clone();
+    for name in ["a", "b", "c"] {
+        model
+            .start_job(JobConfig::new("sleep", &["5"]).name(name))
+            .unwrap();
+    }
+    assert_eq!(model.focused(), Some(0));
+
+    model.update(Action::Key(KeyCode::Tab.into()));
+    assert_eq!(model.focused(), Some(1));
+    let buf = render(&mut app);
+    let border = |name| {
+        let (x, y) = find(&buf, name).unwrap();
+        buf[(x - 1, y)].fg
+    };
+    assert_eq!(border("b"), Color::Cyan);
+    assert_eq!(border("a"), Color::Reset);
+
+    model.update(Action::Key(KeyCode::BackTab.into()));
+    model.update(Action::Key(KeyCode::BackTab.into()));
+    assert_eq!(model.focused(), Some(2));
+    model.update(Action::Key(KeyCode::Tab.into()));
+    assert_eq!(model.focused(), Some(0));
+
+    press(&model, "x");
+    wait_until(|| !model.jobs.read_or_recover()[0].running());
+    let jobs = model.jobs.read_or_recover();
+    assert!(matches!(
+        *jobs[0].status.read_or_recover(),
+        JobStatus::Stopped
+    ));
+    assert!(jobs[1].running() && jobs[2].running());
+}
+
+#[test]
+fn shutdown_terminates_jobs_that_arent_detached() {
+    let model = Model::default();
+    let polite = "trap 'echo bye; exit 0' TERM; while :; do sleep 0.05; done";
+    let stubborn = "trap '' TERM; while :; do sleep 0.05; done";
+    model
+        .start_job(JobConfig::new("sh", &["-c", polite]))
+        .unwrap();
+    model
+        .start_job(JobConfig::new("sh", &["-c", stubborn]))
+        .unwrap();
+    model
+        .start_job(JobConfig::new("sleep", &["5"]).detach(true))
+        .unwrap();
+    // Give the traps time to be set
+    sleep(Duration::from_millis(200));
+
+    model.shutdown(Duration::from_millis(300));
+    wait_until(|| !model.jobs.read_or_recover()[..2].iter().any(Job::running));
+
+    assert!(output(&model, 0).contains(&"bye".to_string()));
+    let jobs = model.jobs.read_or_recover();
+    assert!(matches!(
+        *jobs[1].status.read_or_recover(),
+        JobStatus::Stopped
+    ));
+    assert!(jobs[2].running());
+    jobs[2].stop();
+}
+
+#[test]
+fn shutdown_ends_jobs_that_closed_their_output() {
+    let model = Model::default();
+    let quiet = "exec >/dev/null 2>&1; sleep 30";
+    model
+        .start_job(JobConfig::new("sh", &["-c", quiet]))
+        .unwrap();
+    sleep(Duration::from_millis(200));
+    assert_eq!(model.running(), 1);
+
+    model.shutdown(Duration::from_millis(500));
+    assert!(wait_until(|| model.running() == 0));
+}
+
+#[test]
+fn timestamps_are_dimmed_before_each_line() {
+    let mut app = app(60, 8);
+    let model = app.model().clone();
+    model
+        .start_job(
+            JobConfig::new("echo", &["stamped"])
+                .timestamps(true)
+                .name("t"),
+        )
+        .unwrap();
+
+    wait_until(|| !output(&model, 0).is_empty());
+    let buf = render(&mut app);
+    let (x, y) = find(&buf, "stamped").unwrap();
+    // "  HH:MM:SS.mmm stamped" inside the border
+    assert_eq!(x, 1 + 2 + 13);
+    assert_eq!(buf[(3, y)].fg, Color::DarkGray);
+    assert_eq!(buf[(5, y)].symbol(), ":");
+    assert_eq!(buf[(x, y)].fg, Color::Reset);
+}
+
+#[test]
+fn d_summarises_every_job() {
+    let mut app = app(100, 12);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new("echo", &["hello from a"]).name("a"))
+        .unwrap();
+    model
+        .start_job(JobConfig::new("sh", &["-c", "echo bye; exit 3"]).name("b"))
+        .unwrap();
+    model
+        .start_job(JobConfig::new("sleep", &["5"]).name("c"))
+        .unwrap();
+
+    wait_until(|| !model.jobs.read_or_recover()[..2].iter().any(Job::running));
+
+    press(&model, "d");
+    let buf = render(&mut app);
+    let row = |name: &str| find(&buf, &format!("│{name}  ")).unwrap().1;
+    assert!(find(&buf, "Last line").is_some());
+    assert_eq!(find(&buf, "hello from a").map(|(_, y)| y), Some(row("a")));
+    assert_eq!(find(&buf, "exited").map(|(_, y)| y), Some(row("a")));
+    assert_eq!(find(&buf, "3   ").map(|(_, y)| y), Some(row("b")));
+    assert_eq!(find(&buf, "running").map(|(_, y)| y), Some(row("c")));
+    assert_eq!(buf[find(&buf, "hello from a").unwrap()].bg, Color::White);
+
+    model.update(Action::Key(KeyCode::Down.into()));
+    model.update(Action::Key(KeyCode::Down.into()));
+    model.update(Action::Key(KeyCode::Down.into()));
+    model.update(Action::Key(KeyCode::Enter.into()));
+    assert_eq!(model.focused(), Some(2));
+    assert!(find(&render(&mut app), "Last line").is_none());
+
+    press(&model, "d");
+    model.update(Action::Key(KeyCode::Esc.into()));
+    assert!(find(&render(&mut app), "Last line").is_none());
+    model.jobs.read_or_recover()[2].stop();
+}
+
+#[test]
+fn autostart_jobs_run_without_the_menu() {
+    let model = Model::default();
+    let config = Config::parse(
+        r#"
+        [[job]]
+        name = "backend"
+        command = "sleep"
+        args = ["5"]
+        autostart = true
+
+        [[job]]
+        name = "frontend"
+        command = "sleep"
+        args = ["5"]
+        "#,
+    )
+    .unwrap();
+    model.set_config(config);
+    model.autostart().unwrap();
+
+    let jobs = model.jobs.read_or_recover();
+    assert_eq!(
+        jobs.iter().map(|j| j.title.as_str()).collect::<Vec<_>>(),
+        ["backend"]
+    );
+    jobs[0].stop();
+}
+
+#[test]
+fn set_env_applies_to_later_jobs() {
+    let model = Arc::new(Model::default());
+    model.env_prompt();
+    press(&model, "RUSTFLAGS");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    // Refused, so still open to fix
+    assert!(model.prompt.read_or_recover().is_some());
+    press(&model, "=--cfg wasm");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    assert!(model.prompt.read_or_recover().is_none());
+
+    model
+        .start_job(JobConfig::new("sh", &["-c", "echo \"flags: $RUSTFLAGS\""]))
+        .unwrap();
+    model
+        .start_job(
+            JobConfig::new("sh", &["-c", "echo \"flags: $RUSTFLAGS\""]).env("RUSTFLAGS", "own"),
+        )
+        .unwrap();
+
+    let output = |idx: usize| {
+        let jobs = model.jobs.read_or_recover();
+        let logs = jobs[idx].logs.read_or_recover();
+        logs.lines.front().map(|l| l.text.clone())
+    };
+    wait_until(|| output(0).is_some() && output(1).is_some());
+    assert_eq!(output(0).as_deref(), Some("flags: --cfg wasm"));
+    assert_eq!(output(1).as_deref(), Some("flags: own"));
+}
+
+#[test]
+fn the_env_form_sets_a_variable_from_its_fields() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model.env_form();
+    let buf = render(&mut app);
+    assert!(find(&buf, "KEY").is_some() && find(&buf, "VALUE").is_some());
+
+    model.update(Action::Key(KeyCode::Tab.into()));
+    press(&model, "a=b c");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    assert!(find(&render(&mut app), "KEY can't be empty").is_some());
+
+    model.update(Action::Key(KeyCode::BackTab.into()));
+    press(&model, "GREETING");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    assert!(model.prompt.read_or_recover().is_none());
+    assert_eq!(
+        model
+            .env
+            .read_or_recover()
+            .get("GREETING")
+            .map(String::as_str),
+        Some("a=b c")
+    );
+}
+
+#[test]
+fn progress_shows_until_done() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    let done = Fraction::default();
+    model.open_progress(Progress::new("Setting up", done.clone()));
+    assert!(model.take_dirty());
+
+    *done.write_or_recover() = 0.5;
+    let buf = render(&mut app);
+    assert!(find(&buf, "Setting up").is_some());
+    assert!(find(&buf, "50%").is_some());
+
+    // Keys don't reach what's underneath
+    model.update(Action::Key(KeyCode::Char('j').into()));
+    assert_eq!(*model.menu.read_or_recover(), None);
+
+    let worker = thread::spawn(move || *done.write_or_recover() = 1.0);
+    worker.join().unwrap();
+    let buf = render(&mut app);
+    assert!(find(&buf, "Setting up").is_none());
+    assert!(model.progress.read_or_recover().is_none());
+}
+
+#[test]
+fn an_empty_menu_opens_on_a_placeholder() {
+    let mut app = app(100, 24);
+    let model = app.model().clone();
+    *model.built_menu.write_or_recover() = Some(Arc::new(Menu(vec![])));
+
+    press(&model, "j");
+    assert_eq!(*model.menu.read_or_recover(), Some(0));
+    for code in [KeyCode::Down, KeyCode::Up, KeyCode::Enter] {
+        model.update(Action::Key(code.into()));
+    }
+    press(&model, "y");
+    let buf = render(&mut app);
+    assert!(find(&buf, "Nothing to run yet").is_some());
+    assert!(model.jobs.read_or_recover().is_empty());
+
+    model.update(Action::Key(KeyCode::Esc.into()));
+    assert_eq!(*model.menu.read_or_recover(), None);
+}
+
+#[test]
+fn the_menu_is_built_once_and_refreshed_as_it_opens() {
+    let model = Arc::new(Model::default());
+    let built = model.main_menu();
+    assert!(Arc::ptr_eq(&built, &model.main_menu()));
+    assert!(!built.tree().contains("Active Jobs"));
+
+    model
+        .start_job(JobConfig::new("sleep", &["5"]).name("napper"))
+        .unwrap();
+    assert!(Arc::ptr_eq(&built, &model.main_menu()));
+    press(&model, "j");
+    let refreshed = model.main_menu();
+    assert!(refreshed.tree().ends_with(
+        "Active Jobs\n  Restart all\n  Stop all\n  napper\n    Focus\n    Kill\n    Restart\n    Clear\n"
+    ));
+    assert!(Arc::ptr_eq(&refreshed, &model.main_menu()));
+
+    model.set_config(Config::parse("[[job]]\ncommand = \"true\"\n").unwrap());
+    assert!(!Arc::ptr_eq(&refreshed, &model.main_menu()));
+    model.jobs.read_or_recover()[0].stop();
+}
+
+#[test]
+fn active_jobs_can_be_focused_restarted_and_killed_from_the_menu() {
+    let model = Arc::new(Model::default());
+    for name in ["first", "second"] {
+        model
+            .start_job(JobConfig::new("sh", &["-c", "echo up; exec sleep 5"]).name(name))
+            .unwrap();
+    }
+    model.focus(1);
+    press(&model, "j");
+
+    let menu = model.main_menu();
+    let run = |path: &str| {
+        let (_, idx) = menu
+            .commands()
+            .into_iter()
+            .find(|(p, _)| p == path)
+            .unwrap();
+        menu.enter(idx, model.clone());
+    };
+    run("Active Jobs › first › Focus");
+    assert_eq!(model.focused(), Some(0));
+
+    wait_until(|| !output(&model, 0).is_empty());
+    run("Active Jobs › first › Clear");
+    assert!(output(&model, 0).is_empty());
+
+    run("Active Jobs › second › Kill");
+    wait_until(|| !model.jobs.read_or_recover()[1].running());
+    assert!(!model.jobs.read_or_recover()[1].running());
+
+    run("Active Jobs › second › Restart");
+    assert!(model.jobs.read_or_recover()[1].running());
+
+    // Reopening drops what's no longer running
+    model.jobs.read_or_recover()[0].stop();
+    wait_until(|| !model.jobs.read_or_recover()[0].running());
+    press(&model, "jj");
+    let tree = model.main_menu().tree();
+    assert!(
+        tree.contains("  second\n") && !tree.contains("  first\n"),
+        "{tree}"
+    );
+    model.jobs.read_or_recover()[1].stop();
+}
+
+#[test]
+fn restart_all_and_stop_all_cover_every_running_job() {
+    let model = Arc::new(Model::default());
+    model.start_job(JobConfig::new("true", &[])).unwrap();
+    for name in ["api", "web"] {
+        model
+            .start_job(JobConfig::new("sh", &["-c", "echo up; exec sleep 5"]).name(name))
+            .unwrap();
+    }
+    wait_until(|| !model.jobs.read_or_recover()[0].running());
+    let processes = || {
+        let jobs = model.jobs.read_or_recover();
+        jobs.iter()
+            .map(|job| job.process.clone())
+            .collect::<Vec<_>>()
+    };
+    let before = processes();
+
+    press(&model, "j");
+    let menu = model.main_menu();
+    let run = |path: &str| {
+        let (_, idx) = menu
+            .commands()
+            .into_iter()
+            .find(|(p, _)| p == path)
+            .unwrap();
+        menu.enter(idx, model.clone());
+    };
+    run("Active Jobs › Restart all");
+    let titles = || {
+        let jobs = model.jobs.read_or_recover();
+        jobs.iter().map(|job| job.title.clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(titles(), ["true", "api", "web"]);
+    let after = processes();
+    // Only what was running is started again, each in its own pane
+    assert!(!model.jobs.read_or_recover()[0].running());
+    assert!(Arc::ptr_eq(&before[0], &after[0]));
+    assert!(!Arc::ptr_eq(&before[1], &after[1]) && !Arc::ptr_eq(&before[2], &after[2]));
+
+    run("Active Jobs › Stop all");
+    assert!(model.jobs.read_or_recover()[1].running());
+    press(&model, "y");
+    wait_until(|| !model.jobs.read_or_recover().iter().any(Job::running));
+    assert!(!model.jobs.read_or_recover().iter().any(Job::running));
+}
+
+#[test]
+fn question_mark_lists_every_binding() {
+    let mut app = app(80, 64);
+    let model = app.model().clone();
+    press(&model, "?");
+    let buf = render(&mut app);
+
+    for (context, keys) in BINDINGS {
+        assert!(find(&buf, context).is_some(), "{context}");
+        for (_, action) in *keys {
+            assert!(find(&buf, action).is_some(), "{action}");
+        }
+    }
+
+    // Keys don't reach the panes behind it
+    press(&model, "j");
+    assert_eq!(*model.menu.read_or_recover(), None);
+    press(&model, "?");
+    assert!(find(&render(&mut app), "Toggle wrapping").is_none());
+
+    press(&model, "?");
+    model.update(Action::Key(KeyCode::Esc.into()));
+    assert!(find(&render(&mut app), "Toggle wrapping").is_none());
+}
+
+fn click(model: &Arc<Model>, (column, row): (u16, u16)) {
+    model.update(Action::Mouse(MouseEvent {
+        kind: MouseEventKind::Down(MouseButton::Left),
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    }));
+}
+
+#[test]
+fn clicks_focus_panes_and_enter_menu_entries() {
+    let mut app = app(100, 24);
+    let model = app.model().clone();
+    model.set_focus_policy(FocusPolicy::KeepCurrent);
+    model.start_job(JobConfig::new("true", &[])).unwrap();
+    model.start_job(JobConfig::new("true", &[])).unwrap();
+    render(&mut app);
+    assert_eq!(model.focused(), Some(0));
+
+    // Two panes side by side
+    click(&model, (60, 10));
+    assert_eq!(model.focused(), Some(1));
+
+    open_menu(&model);
+    let buf = render(&mut app);
+    let entry = find(&buf, "Build Frontend").unwrap();
+    click(&model, entry);
+    let menu = main_menu();
+    let selected = model.menu.read_or_recover().unwrap();
+    assert_eq!(menu.0[selected].title(), "Sites (wasm)");
+    assert_eq!(model.focused(), Some(1));
+}
+
+#[test]
+fn narrow_terminals_overlay_the_menu_and_tiny_ones_say_so() {
+    let mut wide = app(100, 24);
+    let model = wide.model().clone();
+    open_menu(&model);
+    render(&mut wide);
+    let side = model.menu_area.read_or_recover().unwrap();
+    assert_eq!((side.x, side.width), (70, 30));
+
+    let mut narrow = app(50, 16);
+    let model = narrow.model().clone();
+    open_menu(&model);
+    let buf = render(&mut narrow);
+    let over = model.menu_area.read_or_recover().unwrap();
+    assert_eq!((over.x, over.width, over.height), (6, 37, 15));
+    assert!(find(&buf, "Build Frontend").is_some());
+    // Still usable by mouse where it's drawn
+    click(&model, find(&buf, "Build Frontend").unwrap());
+    let selected = model.menu.read_or_recover().unwrap();
+    assert_eq!(main_menu().0[selected].title(), "Sites (wasm)");
+
+    let mut tiny = app(20, 6);
+    let model = tiny.model().clone();
+    open_menu(&model);
+    let buf = render(&mut tiny);
+    assert!(find(&buf, "Terminal too small").is_some());
+    assert!(find(&buf, "20x6, needs 32x8").is_some());
+    assert!(model.menu_area.read_or_recover().is_none());
+}
+
+#[test]
+fn help_shows_the_keys_as_remapped() {
+    let mut app = app(80, 64);
+    let model = app.model().clone();
+    model.set_config(Config::parse("[keys]\nhelp = \"h\"\nwrap = \"x\"\n").unwrap());
+    let buf = render(&mut app);
+    assert!(find(&buf, "press h for help").is_some());
+    assert!(find(&buf, "[h]     : Show or hide this help").is_some());
+
+    press(&model, "?");
+    assert!(!*model.help.read_or_recover());
+    press(&model, "h");
+    let buf = render(&mut app);
+    assert!(find(&buf, "h          Show or hide this help").is_some());
+    assert!(find(&buf, "x          Toggle wrapping").is_some());
+    // `x` was taken from stop, which isn't on a key any more
+    assert!(find(&buf, "-          Stop the job").is_some());
+}
+
+#[test]
+fn status_bar_counts_running_jobs_and_names_the_focus() {
+    let mut app = app(80, 24);
+    let buf = render(&mut app);
+    assert_eq!(find(&buf, "0 running · no focus"), Some((1, 23)));
+    assert!(find(&buf, "press ? for help").is_some());
+
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new("sleep", &["5"]).name("nap"))
+        .unwrap();
+    *model.focused.write_or_recover() = Some(0);
+    let buf = render(&mut app);
+    assert!(find(&buf, "1 running · focus: nap").is_some());
+    model.jobs.read_or_recover()[0].stop();
+}
+
+#[test]
+fn prompt_handlers_can_open_another_prompt() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    let follow_up = Arc::downgrade(&model);
+    model.open_prompt(Prompt::new("Name", move |name| {
+        let confirm = Prompt::confirm(&format!("Really {name}?"), |_| Ok(()));
+        follow_up.upgrade().unwrap().open_prompt(confirm);
+        Ok(())
+    }));
+
+    press(&model, "x");
+    model.update(Action::Key(KeyCode::Enter.into()));
+    assert!(find(&render(&mut app), "Really x?").is_some());
+    model.update(Action::Key(KeyCode::Esc.into()));
+    assert!(model.prompt.read_or_recover().is_none());
+}
+
+#[test]
+fn ctrl_c_in_a_prompt_copies_the_selection() {
+    let model = Arc::new(Model::default());
+    let key = |code, modifiers| model.update(Action::Key(KeyEvent::new(code, modifiers)));
+    let copy = |prompt| {
+        model.open_prompt(prompt);
+        press(&model, "abc");
+        key(KeyCode::Home, KeyModifiers::SHIFT);
+        key(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        model.update(Action::Key(KeyCode::Esc.into()));
+        model.copied()
+    };
+    assert_eq!(copy(Prompt::secret("Token", |_| Ok(()))), None);
+    assert_eq!(
+        copy(Prompt::new("Name", |_| Ok(()))).as_deref(),
+        Some("abc")
+    );
+}
+
+#[test]
+fn e_copies_the_last_error_line() {
+    let mut app = app(80, 24);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new(
+            "printf",
+            &["error: one\\nERROR: two\\nwarning: three\\n"],
+        ))
+        .unwrap();
+    wait_until(|| output(&model, 0).len() >= 3);
+
+    assert_eq!(
+        model.jobs.read_or_recover()[0].last_error().as_deref(),
+        Some("ERROR: two")
+    );
+    press(&model, "e");
+    assert!(find(&render(&mut app), "Copied: ERROR: two").is_some());
+    assert_eq!(model.copied().as_deref(), Some("ERROR: two"));
+
+    model
+        .start_job(JobConfig::new("echo", &["fine"]).error_pattern("panicked"))
+        .unwrap();
+    *model.focused.write_or_recover() = Some(1);
+    press(&model, "e");
+    assert!(find(&render(&mut app), "No line matching \"panicked\"").is_some());
+}
+
+#[test]
+fn p_hands_the_output_to_the_pager() {
+    let model = app(80, 24).model().clone();
+    model
+        .start_job(JobConfig::new("printf", &["one\\ntwo\\n"]).name("pager"))
+        .unwrap();
+    wait_until(|| output(&model, 0).len() >= 2);
+
+    assert_eq!(model.take_page(), None);
+    press(&model, "p");
+    let path = model.take_page().unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+    assert_eq!(model.take_page(), None);
+    std::fs::remove_file(path).unwrap();
+
+    let dir = std::env::temp_dir().join(format!("itask-pager-{}", std::process::id()));
+    model
+        .start_job(JobConfig::new("echo", &["logged"]).log_dir(&dir))
+        .unwrap();
+    wait_until(|| !output(&model, 1).is_empty());
+    *model.focused.write_or_recover() = Some(1);
+    press(&model, "p");
+    let log_file = model.jobs.read_or_recover()[1].log_file.clone();
+    assert_eq!(model.take_page(), log_file);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_run_summary_lists_every_job_once_quit() {
+    let model = Arc::new(Model::default());
+    assert_eq!(model.run_summary(), "");
+
+    let dir = std::env::temp_dir().join(format!("itask-summary-{}", std::process::id()));
+    model
+        .start_job(JobConfig::new("echo", &["logged"]).log_dir(&dir))
+        .unwrap();
+    model
+        .start_job(JobConfig::new("sh", &["-c", "exit 3"]))
+        .unwrap();
+    model.start_job(JobConfig::new("sleep", &["5"])).unwrap();
+    wait_until(|| model.running() <= 1);
+    model.shutdown(Duration::from_secs(2));
+
+    let summary = model.run_summary();
+    let rows = summary
+        .lines()
+        .map(|row| row.split_whitespace().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let log_file = model.jobs.read_or_recover()[0].log_file.clone().unwrap();
+    assert_eq!(rows[0], ["Command", "Exit", "Took", "Log"]);
+    assert_eq!(rows[1][..2], ["echo", "logged"]);
+    assert_eq!(rows[1][2], "0");
+    assert!(rows[1][3].ends_with('s'), "{summary}");
+    assert_eq!(rows[1][4], log_file.display().to_string());
+    assert_eq!(rows[2][..5], ["sh", "-c", "exit", "3", "3"]);
+    assert_eq!(rows[3][..3], ["sleep", "5", "stopped"]);
+    assert_eq!(rows.len(), 4);
+
+    // Columns line up
+    let exit = summary.lines().next().unwrap().find("Exit").unwrap();
+    assert!(summary
+        .lines()
+        .all(|row| row[exit - 2..exit].trim().is_empty()));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn groups_start_together_and_overflow_into_the_table() {
+    let mut app = app(50, 16);
+    let model = app.model().clone();
+    let jobs = (1..=9)
+        .map(|n| format!("[[job]]\nname = \"j{n}\"\ncommand = \"sleep\"\nargs = [\"5\"]\n\n"))
+        .collect::<String>();
+    let group = "[[group]]\nname = \"front\"\njobs = [\"j1\", \"j2\"]\n\n\
+                 [[group]]\nname = \"all\"\njobs = [\"j3\", \"j4\", \"j5\", \"j6\", \"j7\", \"j8\", \"j9\"]\n";
+    model.set_config(Config::parse(&format!("{jobs}{group}")).unwrap());
+
+    let menu = model.main_menu();
+    let (_, front) = menu
+        .commands()
+        .into_iter()
+        .find(|(path, _)| path == "Groups › front")
+        .unwrap();
+    menu.enter(front, model.clone());
+    let titles = || {
+        model
+            .jobs
+            .read_or_recover()
+            .iter()
+            .map(|j| j.title.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(titles(), ["j1", "j2"]);
+    assert!(find(&render(&mut app), "Too many").is_none());
+
+    model.start_group("all").unwrap();
+    assert_eq!(titles().len(), 9);
+    let buf = render(&mut app);
+    assert!(find(&buf, "Too many to split").is_some());
+    assert!(find(&buf, "│j9").is_some());
+    assert!(model.panes.read_or_recover().is_empty());
+    assert!(model.start_group("nope").is_err());
+
+    model.jobs.read_or_recover().iter().for_each(|j| j.stop());
+}
+
+#[test]
+fn tabs_show_one_job_at_a_time() {
+    let mut app = app(60, 16);
+    let model = app.model().clone();
+    for n in ["alpha", "beta", "gamma"] {
+        model
+            .start_job(JobConfig::new("sh", &["-c", "echo out; sleep 5"]).name(n))
+            .unwrap();
+    }
+    *model.focused.write_or_recover() = Some(0);
+    press(&model, "t");
+
+    let buf = render(&mut app);
+    assert_eq!(find(&buf, "1 alpha"), Some((1, 0)));
+    assert!(find(&buf, "3 gamma").is_some());
+    // Only the focused job gets a pane, the others' areas are empty
+    assert!(find(&buf, "┌alpha").is_some());
+    assert!(find(&buf, "┌beta").is_none());
+    assert_eq!(model.panes.read_or_recover()[1], Rect::default());
+
+    model.update(Action::Key(KeyCode::Right.into()));
+    assert!(find(&render(&mut app), "┌beta").is_some());
+    model.update(Action::Key(KeyCode::Left.into()));
+    model.update(Action::Key(KeyCode::Left.into()));
+    assert_eq!(model.focused(), Some(2));
+    press(&model, "1");
+    assert_eq!(model.focused(), Some(0));
+    press(&model, "9");
+    assert_eq!(model.focused(), Some(0));
+
+    press(&model, "t");
+    let buf = render(&mut app);
+    assert!(find(&buf, "┌beta").is_some() && find(&buf, "┌gamma").is_some());
+    model.jobs.read_or_recover().iter().for_each(|j| j.stop());
+}
+
+#[test]
+fn narrow_panes_fall_back_to_tabs() {
+    let model = Arc::new(Model::default());
+    let render = |width| {
+        let mut terminal = Terminal::new(TestBackend::new(width, 16)).unwrap();
+        terminal.draw(|f| model.render(f)).unwrap();
+        terminal.backend().buffer().clone()
+    };
+    model.set_config(Config::parse("min_pane_width = 30").unwrap());
+    for n in ["alpha", "beta", "gamma"] {
+        model
+            .start_job(JobConfig::new("sleep", &["5"]).name(n))
+            .unwrap();
+    }
+    *model.focused.write_or_recover() = Some(0);
+
+    // Two columns of 40 fit
+    let buf = render(80);
+    assert!(find(&buf, "┌alpha").is_some() && find(&buf, "┌beta").is_some());
+    assert!(find(&buf, "1 alpha").is_none());
+
+    let buf = render(50);
+    assert!(find(&buf, "1 alpha").is_some());
+    assert!(find(&buf, "┌beta").is_none());
+    model.update(Action::Key(KeyCode::Right.into()));
+    assert_eq!(model.focused(), Some(1));
+
+    let buf = render(80);
+    assert!(find(&buf, "┌alpha").is_some() && find(&buf, "┌beta").is_some());
+    model.jobs.read_or_recover().iter().for_each(|j| j.stop());
+}
+
+#[test]
+fn scrollbar_shows_once_output_overflows() {
+    let mut app = app(40, 12);
+    let model = app.model().clone();
+    model
+        .start_job(JobConfig::new(
+            "sh",
+            &["-c", "echo one; sleep 0.3; seq 120"],
+        ))
+        .unwrap();
+
+    // The last row is the status bar, the one above it the pane's corner
+    let border = |buf: &Buffer| {
+        (1..10)
+            .map(|y| buf[(39, y)].symbol().to_string())
+            .collect::<String>()
+    };
+    wait_until(|| find(&render(&mut app), "one").is_some());
+    let buf = render(&mut app);
+    assert_eq!(border(&buf), "│".repeat(9));
+
+    wait_until(|| find(&render(&mut app), "118").is_some());
+    let buf = render(&mut app);
+    // Following the tail puts the thumb at the bottom
+    assert_ne!(border(&buf), "│".repeat(9));
+    assert_eq!(buf[(39, 9)].symbol(), "█");
+}
+
+#[test]
+fn focused_pane_shows_the_whole_command() {
+    let mut app = app(100, 12);
+    let model = app.model().clone();
+    model.set_focus_policy(FocusPolicy::KeepCurrent);
+    model
+        .start_job(JobConfig::new("sleep", &["5", "# --bin sites"]))
+        .unwrap();
+    model
+        .start_job(JobConfig::new("sleep", &["5", "# --bin docs"]))
+        .unwrap();
+
+    let buf = render(&mut app);
+    assert_eq!(find(&buf, "sleep 5...").map(|(_, y)| y), Some(0));
+    assert!(find(&buf, "$ sleep 5 # --bin sites").is_some());
+    assert!(find(&buf, "$ sleep 5 # --bin docs").is_none());
+}
+
+#[test]
+fn menu_takes_colours_from_the_theme() {
+    let mut app = app(100, 24);
+    let config = Config::parse(
+        r#"
+        [[job]]
+        name = "API"
+        command = "cargo"
+
+        [theme]
+        selected = { fg = "black", bg = "cyan" }
+        "#,
+    )
+    .unwrap();
+    app.model().set_config(config);
+    open_menu(app.model());
+    let buf = render(&mut app);
+
+    assert_eq!(buf[find(&buf, "API").unwrap()].bg, Color::Cyan);
+}
@@ -0,0 +1,144 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use ratatui::style::{Color, Modifier, Style, Stylize};
+
+use crate::toml::{Table, Value};
+
+/// Colours for everything itask draws itself, from the `[theme]` table of
+/// `itask.toml`. Each entry is a colour name (`"cyan"`, `"#ff8800"`) for the
+/// foreground, or a table taking `fg`, `bg`, `bold`, `italic` and
+/// `underlined`, which replaces the default style outright:
+///
+/// ```toml
+/// [theme]
+/// selected = { fg = "black", bg = "cyan", bold = true }
+/// prompt_border = "magenta"
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// The highlighted menu entry.
+    pub selected: Style,
+    /// Every other menu entry.
+    pub unselected: Style,
+    /// Around prompt text fields.
+    pub prompt_border: Style,
+    /// Prompt errors and stderr output.
+    pub error: Style,
+    pub job_border: Style,
+    /// Around the pane keys go to.
+    pub focused_border: Style,
+    /// Around jobs that exited unsuccessfully, unless focused.
+    pub failed_border: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            selected: Style::new().on_white().black().bold(),
+            unselected: Style::new().bold().white(),
+            prompt_border: Style::new().yellow(),
+            error: Style::new().red(),
+            job_border: Style::new(),
+            focused_border: Style::new().cyan(),
+            failed_border: Style::new().red(),
+        }
+    }
+}
+
+impl Theme {
+    /// The default theme with whatever `t` sets on top.
+    pub fn from_table(t: &Table) -> anyhow::Result<Self> {
+        let mut theme = Self::default();
+        for (key, value) in t {
+            let style = match key.as_str() {
+                "selected" => &mut theme.selected,
+                "unselected" => &mut theme.unselected,
+                "prompt_border" => &mut theme.prompt_border,
+                "error" => &mut theme.error,
+                "job_border" => &mut theme.job_border,
+                "focused_border" => &mut theme.focused_border,
+                "failed_border" => &mut theme.failed_border,
+                _ => anyhow::bail!("unknown theme entry `{key}`"),
+            };
+            *style = parse_style(value).with_context(|| format!("in theme entry `{key}`"))?;
+        }
+        Ok(theme)
+    }
+}
+
+pub fn parse_color(value: &Value) -> anyhow::Result<Color> {
+    let name = value.as_str().context("colours must be strings")?;
+    Color::from_str(name)
+        .ok()
+        .with_context(|| format!("`{name}` isn't a colour"))
+}
+
+fn parse_style(value: &Value) -> anyhow::Result<Style> {
+    let Some(t) = value.as_table() else {
+        return Ok(Style::new().fg(parse_color(value)?));
+    };
+
+    let mut style = Style::new();
+    for (key, value) in t {
+        let modifier = match key.as_str() {
+            "fg" => {
+                style = style.fg(parse_color(value)?);
+                continue;
+            }
+            "bg" => {
+                style = style.bg(parse_color(value)?);
+                continue;
+            }
+            "bold" => Modifier::BOLD,
+            "italic" => Modifier::ITALIC,
+            "underlined" => Modifier::UNDERLINED,
+            _ => anyhow::bail!("unknown style key `{key}`"),
+        };
+        if value
+            .as_bool()
+            .with_context(|| format!("`{key}` must be a boolean"))?
+        {
+            style = style.add_modifier(modifier);
+        }
+    }
+    Ok(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toml;
+
+    #[test]
+    fn overrides_only_what_is_set() {
+        let t = toml::parse(
+            r##"
+            selected = { fg = "black", bg = "cyan", bold = true }
+            prompt_border = "#ff8800"
+            "##,
+        )
+        .unwrap();
+        let theme = Theme::from_table(&t).unwrap();
+
+        assert_eq!(theme.selected, Style::new().black().on_cyan().bold());
+        assert_eq!(
+            theme.prompt_border,
+            Style::new().fg(Color::Rgb(0xff, 0x88, 0))
+        );
+        assert_eq!(theme.error, Theme::default().error);
+    }
+
+    #[test]
+    fn reports_bad_entries() {
+        let err = |src: &str| {
+            let t = toml::parse(src).unwrap();
+            format!("{:#}", Theme::from_table(&t).unwrap_err())
+        };
+        assert_eq!(
+            err("error = \"reddish\""),
+            "in theme entry `error`: `reddish` isn't a colour"
+        );
+        assert_eq!(err("border = \"red\""), "unknown theme entry `border`");
+    }
+}
@@ -0,0 +1,506 @@
+//! Just enough TOML for itask's own files: tables, arrays of tables, and
+//! `key = value` pairs holding strings, integers, floats, booleans, arrays
+//! and inline tables. Dotted keys, dates and multi-line strings aren't
+//! supported.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use anyhow::{anyhow, bail, Context};
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(f) => Some(*f),
+            Value::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse(src: &str) -> anyhow::Result<Table> {
+    let mut root = Table::new();
+    // Path of the table `key = value` lines currently land in, and whether
+    // its last segment is an array of tables.
+    let mut path: Vec<String> = vec![];
+    let mut array = false;
+
+    let mut lines = src.lines().enumerate();
+    while let Some((n, line)) = lines.next() {
+        let context = || format!("line {}", n + 1);
+        let mut line = strip_comment(line).trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix("[[") {
+            let header = header
+                .strip_suffix("]]")
+                .ok_or_else(|| anyhow!("unclosed [["));
+            path = split_key(header.with_context(context)?);
+            array = true;
+            let (last, parents) = path.split_last().unwrap();
+            let parent = table_at(&mut root, parents, false).with_context(context)?;
+            match parent
+                .entry(last.clone())
+                .or_insert_with(|| Value::Array(vec![]))
+            {
+                Value::Array(items) => items.push(Value::Table(Table::new())),
+                _ => bail!("{}: {last} is not an array of tables", context()),
+            }
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header
+                .strip_suffix(']')
+                .ok_or_else(|| anyhow!("unclosed ["));
+            path = split_key(header.with_context(context)?);
+            array = false;
+            table_at(&mut root, &path, false).with_context(context)?;
+            continue;
+        }
+
+        // Arrays may span lines until their brackets balance
+        while !balanced(&line) {
+            let (_, next) = lines.next().ok_or_else(|| anyhow!("unclosed array"))?;
+            line.push(' ');
+            line.push_str(strip_comment(next).trim());
+        }
+
+        let mut rest = line.as_str();
+        let key = parse_key(&mut rest).with_context(context)?;
+        let value = parse_value(&mut rest).with_context(context)?;
+        if !rest.trim().is_empty() {
+            bail!("{}: unexpected `{}`", context(), rest.trim());
+        }
+
+        let table = table_at(&mut root, &path, array).with_context(context)?;
+        if table.insert(key.clone(), value).is_some() {
+            bail!("{}: duplicate key {key}", context());
+        }
+    }
+
+    Ok(root)
+}
+
+fn table_at<'a>(
+    root: &'a mut Table,
+    path: &[String],
+    array: bool,
+) -> anyhow::Result<&'a mut Table> {
+    let mut table = root;
+    for (i, key) in path.iter().enumerate() {
+        let last = i == path.len() - 1;
+        let value = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = match value {
+            Value::Table(t) => t,
+            Value::Array(items) if array || !last => match items.last_mut() {
+                Some(Value::Table(t)) => t,
+                _ => bail!("{key} is not a table"),
+            },
+            _ => bail!("{key} is not a table"),
+        };
+    }
+    Ok(table)
+}
+
+fn split_key(key: &str) -> Vec<String> {
+    key.split('.').map(|k| unquote(k.trim())).collect()
+}
+
+fn unquote(key: &str) -> String {
+    key.trim_matches('"').to_string()
+}
+
+/// The characters of `line` outside its strings, basic or literal, with
+/// their byte offsets. The quotes themselves are left out.
+fn unquoted(line: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut quote = None;
+    let mut escaped = false;
+    line.char_indices().filter(move |&(_, c)| {
+        match quote {
+            // Only basic strings have escapes
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => return true,
+        }
+        false
+    })
+}
+
+/// Drops a trailing `# comment` that isn't inside a string.
+fn strip_comment(line: &str) -> &str {
+    match unquoted(line).find(|&(_, c)| c == '#') {
+        Some((i, _)) => &line[..i],
+        None => line,
+    }
+}
+
+fn balanced(line: &str) -> bool {
+    let depth = unquoted(line)
+        .map(|(_, c)| match c {
+            '[' | '{' => 1,
+            ']' | '}' => -1,
+            _ => 0,
+        })
+        .sum::<i32>();
+    depth <= 0
+}
+
+/// A bare or quoted key, and the `=` after it.
+fn parse_key(s: &mut &str) -> anyhow::Result<String> {
+    *s = s.trim_start();
+    let key = match s.starts_with(['"', '\'']) {
+        true => parse_string(s)?,
+        false => {
+            let end = s
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(s.len());
+            let (key, rest) = s.split_at(end);
+            if key.is_empty() {
+                bail!("expected `key = value`");
+            }
+            *s = rest;
+            key.to_string()
+        }
+    };
+    match s.trim_start().strip_prefix('=') {
+        Some(rest) => {
+            *s = rest;
+            Ok(key)
+        }
+        _ => bail!("expected `key = value`"),
+    }
+}
+
+/// A basic (`"`) or literal (`'`) string.
+fn parse_string(s: &mut &str) -> anyhow::Result<String> {
+    if let Some(rest) = s.strip_prefix('\'') {
+        let end = rest.find('\'').context("unclosed string")?;
+        let out = rest[..end].to_string();
+        *s = &rest[end + 1..];
+        return Ok(out);
+    }
+
+    let mut out = String::new();
+    let mut chars = s[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                *s = &s[i + 2..];
+                return Ok(out);
+            }
+            '\\' => match chars.next().map(|(_, c)| c) {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('u') => {
+                    let hex = chars.by_ref().take(4).map(|(_, c)| c).collect::<String>();
+                    let code = u32::from_str_radix(&hex, 16).context("bad \\u escape")?;
+                    out.push(char::from_u32(code).context("bad \\u escape")?);
+                }
+                other => bail!("unknown escape \\{}", other.unwrap_or(' ')),
+            },
+            c => out.push(c),
+        }
+    }
+    bail!("unclosed string")
+}
+
+fn parse_value(s: &mut &str) -> anyhow::Result<Value> {
+    *s = s.trim_start();
+    let Some(first) = s.chars().next() else {
+        bail!("missing value");
+    };
+
+    match first {
+        '"' | '\'' => parse_string(s).map(Value::String),
+
+        '[' => {
+            *s = &s[1..];
+            let mut items = vec![];
+            loop {
+                *s = s.trim_start();
+                if let Some(rest) = s.strip_prefix(']') {
+                    *s = rest;
+                    return Ok(Value::Array(items));
+                }
+                items.push(parse_value(s)?);
+                *s = s.trim_start();
+                match s.chars().next() {
+                    Some(',') => *s = &s[1..],
+                    Some(']') => {}
+                    _ => bail!("expected `,` or `]` in array"),
+                }
+            }
+        }
+
+        '{' => {
+            *s = &s[1..];
+            let mut table = Table::new();
+            loop {
+                *s = s.trim_start();
+                if let Some(rest) = s.strip_prefix('}') {
+                    *s = rest;
+                    return Ok(Value::Table(table));
+                }
+                let key = parse_key(s)?;
+                table.insert(key, parse_value(s)?);
+                *s = s.trim_start();
+                match s.chars().next() {
+                    Some(',') => *s = &s[1..],
+                    Some('}') => {}
+                    _ => bail!("expected `,` or `}}` in inline table"),
+                }
+            }
+        }
+
+        _ => {
+            let end = s
+                .find(|c: char| c == ',' || c == ']' || c == '}' || c.is_whitespace())
+                .unwrap_or(s.len());
+            let (word, rest) = s.split_at(end);
+            *s = rest;
+            match word {
+                "true" => Ok(Value::Boolean(true)),
+                "false" => Ok(Value::Boolean(false)),
+                _ => {
+                    let digits = word.replace('_', "");
+                    digits
+                        .parse()
+                        .map(Value::Integer)
+                        .or_else(|_| digits.parse().map(Value::Float))
+                        .map_err(|_| anyhow!("unknown value `{word}`"))
+                }
+            }
+        }
+    }
+}
+
+//
+
+/// Writes `table` back out, nested tables and arrays of tables as sections.
+pub fn to_string(table: &Table) -> String {
+    let mut out = String::new();
+    write_table(&mut out, table, &[]);
+    out
+}
+
+fn is_section(value: &Value) -> bool {
+    match value {
+        Value::Table(_) => true,
+        Value::Array(items) => {
+            !items.is_empty() && items.iter().all(|v| matches!(v, Value::Table(_)))
+        }
+        _ => false,
+    }
+}
+
+fn write_table(out: &mut String, table: &Table, path: &[String]) {
+    for (key, value) in table.iter().filter(|(_, v)| !is_section(v)) {
+        writeln!(out, "{} = {}", write_key(key), write_value(value)).unwrap();
+    }
+
+    for (key, value) in table.iter().filter(|(_, v)| is_section(v)) {
+        let path = [path, &[write_key(key)]].concat();
+        match value {
+            Value::Table(t) => {
+                writeln!(out, "\n[{}]", path.join(".")).unwrap();
+                write_table(out, t, &path);
+            }
+            Value::Array(items) => {
+                for item in items.iter().filter_map(Value::as_table) {
+                    writeln!(out, "\n[[{}]]", path.join(".")).unwrap();
+                    write_table(out, item, &path);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn write_key(key: &str) -> String {
+    match !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        true => key.to_string(),
+        false => write_value(&Value::String(key.to_string())),
+    }
+}
+
+fn write_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => {
+            let mut out = String::from('"');
+            for c in s.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    '\t' => out.push_str("\\t"),
+                    '\r' => out.push_str("\\r"),
+                    c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+            out
+        }
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => format!("{f:?}"),
+        Value::Boolean(b) => b.to_string(),
+        Value::Array(items) => format!(
+            "[{}]",
+            items.iter().map(write_value).collect::<Vec<_>>().join(", ")
+        ),
+        Value::Table(t) => format!(
+            "{{ {} }}",
+            t.iter()
+                .map(|(k, v)| format!("{} = {}", write_key(k), write_value(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tables_and_values() {
+        let table = parse(
+            r#"
+            # itask config
+            title = "itask"   # trailing comment
+            port = 8_080
+            ratio = 0.5
+            enabled = true
+
+            [server]
+            args = [
+                "run",  # multi-line
+                "--bin", 'sites',
+            ]
+            env = { RUST_LOG = "debug", "KEY WITH SPACE" = "a\"b" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(table["title"].as_str(), Some("itask"));
+        assert_eq!(table["port"].as_integer(), Some(8080));
+        assert_eq!(table["ratio"].as_float(), Some(0.5));
+        assert_eq!(table["enabled"].as_bool(), Some(true));
+
+        let server = table["server"].as_table().unwrap();
+        let args = server["args"].as_array().unwrap();
+        assert_eq!(args.len(), 3);
+        assert_eq!(args[2].as_str(), Some("sites"));
+        let env = server["env"].as_table().unwrap();
+        assert_eq!(env["RUST_LOG"].as_str(), Some("debug"));
+        assert_eq!(env["KEY WITH SPACE"].as_str(), Some("a\"b"));
+    }
+
+    #[test]
+    fn parses_arrays_of_tables() {
+        let table = parse("[[job]]\nname = \"a\"\n\n[[job]]\nname = \"b\"\n[job.env]\nX = \"1\"\n")
+            .unwrap();
+        let jobs = table["job"].as_array().unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[1].as_table().unwrap()["name"].as_str(), Some("b"));
+        let env = jobs[1].as_table().unwrap()["env"].as_table().unwrap();
+        assert_eq!(env["X"].as_str(), Some("1"));
+    }
+
+    #[test]
+    fn reports_line_of_errors() {
+        let err = parse("a = 1\nb = nope\n").unwrap_err();
+        assert!(format!("{err:#}").contains("line 2"));
+        assert!(parse("a = 1\na = 2").is_err());
+        assert!(parse("a = \"open").is_err());
+    }
+
+    #[test]
+    fn quotes_hide_what_they_hold() {
+        let table = parse(
+            r#"
+            a = 'echo a#b'
+            b = 'say "hi'  # note
+            c = ["x]", 'y]']
+            env = { "A=B" = "1", 'C' = "2" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(table["a"].as_str(), Some("echo a#b"));
+        assert_eq!(table["b"].as_str(), Some("say \"hi"));
+        assert_eq!(table["c"].as_array().unwrap().len(), 2);
+        let env = table["env"].as_table().unwrap();
+        assert_eq!(env["A=B"].as_str(), Some("1"));
+        assert_eq!(env["C"].as_str(), Some("2"));
+        assert_eq!(parse("\"x=y\" = 1").unwrap()["x=y"].as_integer(), Some(1));
+        assert!(parse("= 1").is_err());
+    }
+
+    #[test]
+    fn round_trips() {
+        let src = "name = \"a \\\"quoted\\\"\\nline\"\nn = -3\n\n[[job]]\nargs = [\"x\", \"y\"]\nenv = { K = \"v\" }\n";
+        let table = parse(src).unwrap();
+        assert_eq!(parse(&to_string(&table)).unwrap(), table);
+    }
+}
@@ -0,0 +1,198 @@
+use itertools::Itertools;
+use ratatui::{
+    layout::{Margin, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Clear, Paragraph, Widget},
+};
+
+use crate::{keys::key_name, Binding, Keys};
+
+use self::HelpKey::{Bound, Fixed};
+
+/// A key as the help shows it: whichever key a remappable [`Binding`] is on
+/// at the moment, or one that's fixed.
+#[derive(Clone, Copy, Debug)]
+pub enum HelpKey {
+    Bound(Binding),
+    Fixed(&'static str),
+}
+
+/// The keys shown for an action, and what it does.
+pub type HelpEntry = (&'static [HelpKey], &'static str);
+
+/// Every key binding, grouped by where it applies. The help overlay and the
+/// idle banner are drawn from this, so add new bindings here as well as to
+/// `Model::handle_key`.
+pub const BINDINGS: &[(&str, &[HelpEntry])] = &[
+    (
+        "Global",
+        &[
+            (&[Bound(Binding::Menu)], "Open or close the jobs menu"),
+            (&[Bound(Binding::Palette)], "Command palette"),
+            (
+                &[Bound(Binding::NextPane), Bound(Binding::PrevPane)],
+                "Next pane, previous",
+            ),
+            (&[Bound(Binding::Layout)], "Switch between split and tabs"),
+            (&[Fixed("1-9")], "Focus that job"),
+            (&[Bound(Binding::Summary)], "Summary of every job"),
+            (&[Bound(Binding::Help)], "Show or hide this help"),
+            (&[Bound(Binding::Rerun)], "Run the last job again"),
+            (&[Bound(Binding::Quit)], "Quit, asking while jobs run"),
+            (&[Fixed("Click")], "Focus a pane, pick a menu entry"),
+            (&[Fixed("Wheel")], "Scroll the pane under the pointer"),
+        ],
+    ),
+    (
+        "Menu",
+        &[
+            (&[Fixed("↑ ↓")], "Move"),
+            (&[Fixed("Enter")], "Open section or run job"),
+            (&[Fixed("Esc")], "Back"),
+            (&[Bound(Binding::Search)], "Filter across sections"),
+            (&[Bound(Binding::Copy)], "Copy the command, don't run it"),
+        ],
+    ),
+    (
+        "Summary",
+        &[
+            (&[Fixed("↑ ↓")], "Move"),
+            (&[Fixed("Enter")], "Focus that job's pane"),
+            (
+                &[Fixed("Esc"), Bound(Binding::Summary)],
+                "Back to the panes",
+            ),
+        ],
+    ),
+    (
+        "Job pane",
+        &[
+            (
+                &[Bound(Binding::PageUp), Bound(Binding::PageDown)],
+                "Scroll a page",
+            ),
+            (
+                &[Bound(Binding::ScrollHome), Bound(Binding::ScrollEnd)],
+                "Oldest line, follow the tail",
+            ),
+            (&[Bound(Binding::Search)], "Search the output"),
+            (
+                &[Bound(Binding::NextMatch), Bound(Binding::PrevMatch)],
+                "Next, previous match",
+            ),
+            (&[Bound(Binding::Filter)], "Filter lines, empty to clear"),
+            (&[Bound(Binding::Pin)], "Pin matching lines on top"),
+            (&[Bound(Binding::Input)], "Type into stdin, Enter sends"),
+            (&[Bound(Binding::Clear)], "Clear the output"),
+            (&[Bound(Binding::Stop)], "Stop the job"),
+            (&[Bound(Binding::Wrap)], "Toggle wrapping"),
+            (&[Fixed("← →")], "Switch tabs, else scroll sideways"),
+            (&[Bound(Binding::Streams)], "Cycle stdout/stderr"),
+            (&[Bound(Binding::Restart)], "Toggle restart on exit"),
+            (&[Bound(Binding::Bookmark)], "Toggle bookmark"),
+            (
+                &[Bound(Binding::PrevBookmark), Bound(Binding::NextBookmark)],
+                "Previous, next bookmark",
+            ),
+            (&[Bound(Binding::Select)], "Start or drop a selection"),
+            (&[Fixed("↑ ↓")], "Extend the selection"),
+            (&[Bound(Binding::Copy)], "Copy the selection"),
+            (&[Bound(Binding::CopyError)], "Copy the last error line"),
+            (&[Bound(Binding::Pager)], "Read the output in $PAGER"),
+            (&[Fixed("Esc")], "Drop the selection"),
+        ],
+    ),
+    (
+        "Prompt",
+        &[
+            (&[Fixed("Enter")], "Submit"),
+            (&[Fixed("Esc")], "Cancel"),
+            (&[Fixed("↑ ↓")], "Previous, next entry"),
+            (&[Fixed("Shift+← →")], "Select text"),
+            (&[Fixed("Ctrl+← →")], "Move by word, also Alt+B Alt+F"),
+            (&[Fixed("Ctrl+W")], "Delete the previous word"),
+            (&[Fixed("Tab")], "Complete, or next form field"),
+            (&[Fixed("Shift+Tab")], "Previous form field"),
+            (&[Fixed("Ctrl+C")], "Copy the selection"),
+            (&[Fixed("y n ← →")], "Answer a Yes/No question"),
+        ],
+    ),
+];
+
+/// The keys of a [`BINDINGS`] entry as `keys` has them, `-` if none are
+/// bound.
+pub fn key_label(keys: &Keys, entry: &[HelpKey]) -> String {
+    let label = entry
+        .iter()
+        .filter_map(|key| match key {
+            Bound(binding) => keys.key_for(*binding).map(key_name),
+            Fixed(key) => Some(key.to_string()),
+        })
+        .join(" ");
+    match label.is_empty() {
+        true => "-".into(),
+        false => label,
+    }
+}
+
+/// The global bindings as `[key] : action`, padded to the same width so
+/// they line up when centered, as the idle banner shows them.
+pub fn key_summary(keys: &Keys) -> Vec<String> {
+    let global = BINDINGS
+        .iter()
+        .find(|(context, _)| *context == "Global")
+        .map_or(&[][..], |(_, entries)| entries);
+    let lines = global
+        .iter()
+        .map(|(key, action)| {
+            let key = format!("[{}]", key_label(keys, key));
+            format!("{key:<7} : {action}")
+        })
+        .collect::<Vec<_>>();
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    lines.into_iter().map(|l| format!("{l:<width$}")).collect()
+}
+
+/// The `?` overlay listing [`BINDINGS`], under the keys they're on.
+pub struct Help<'a>(pub &'a Keys);
+
+impl Help<'_> {
+    fn lines(&self) -> Vec<Line<'static>> {
+        let mut lines = vec![];
+        for (i, (context, keys)) in BINDINGS.iter().enumerate() {
+            if i > 0 {
+                lines.push(Line::default());
+            }
+            lines.push(Line::from(context.bold()));
+            lines.extend(keys.iter().map(|(key, action)| {
+                let key = key_label(self.0, key);
+                Line::from(vec![format!("  {key:<10} ").yellow(), (*action).into()])
+            }));
+        }
+        lines
+    }
+
+    /// Centered, tall enough for every binding where the screen allows.
+    pub fn area(&self, area: Rect) -> Rect {
+        let width = 48.min(area.width);
+        let height = (self.lines().len() as u16 + 2).min(area.height);
+        Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+impl Widget for &Help<'_> {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        Clear.render(area, buf);
+        Block::bordered()
+            .title("Keys")
+            .title_bottom(Line::from("Esc or ? to close").right_aligned())
+            .render(area, buf);
+        Paragraph::new(self.lines()).render(area.inner(Margin::new(1, 1)), buf);
+    }
+}
@@ -1,42 +1,195 @@
+use std::ops::Range;
+
+use itertools::Itertools;
 use ratatui::{
     style::{Color, Style, Stylize},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, StatefulWidget, Widget},
 };
 
-pub fn add_cursor<'a>(s: String, c: usize) -> Line<'a> {
-    Line::from(vec![
-        Span::raw(s[..c].to_string()),
-        Span::styled(
-            s.clone().chars().nth(c).unwrap_or(' ').to_string(),
-            Style::new().bg(Color::Yellow),
-        ),
-        Span::raw(match c == s.len() {
-            true => String::new(),
-            false => s[c + 1..].to_string(),
-        }),
-    ])
+use unicode_width::UnicodeWidthChar;
+
+use crate::Theme;
+
+/// Highlights the `c`th char of `s` (or a trailing space when `c` is past the
+/// end) and the chars in `selected`.
+pub fn add_cursor<'a>(s: String, c: usize, selected: Range<usize>) -> Line<'a> {
+    let cc = s.chars().nth(c).unwrap_or(' ');
+    add_reveal_cursor(s, c, cc, selected)
+}
+
+/// Like [`add_cursor`], but draws `cc` under the cursor instead of the char in `s`.
+pub fn add_reveal_cursor<'a>(s: String, c: usize, cc: char, selected: Range<usize>) -> Line<'a> {
+    let mut chars = s.chars().collect::<Vec<_>>();
+    if c >= chars.len() {
+        chars.resize(c + 1, ' ');
+    }
+    chars[c] = cc;
+    highlight(chars, Some(c), selected)
+}
+
+fn highlight<'a>(chars: Vec<char>, cursor: Option<usize>, selected: Range<usize>) -> Line<'a> {
+    let style = |i: usize| match i {
+        _ if Some(i) == cursor => Style::new().bg(Color::Yellow),
+        _ if selected.contains(&i) => Style::new().reversed(),
+        _ => Style::new(),
+    };
+
+    Line::from(
+        chars
+            .into_iter()
+            .enumerate()
+            .chunk_by(|(i, _)| style(*i))
+            .into_iter()
+            .map(|(style, chars)| Span::styled(chars.map(|(_, c)| c).collect::<String>(), style))
+            .collect::<Vec<_>>(),
+    )
 }
 
-pub fn add_reveal_cursor<'a>(s: String, c: usize, cc: char) -> Line<'a> {
-    Line::from(vec![
-        Span::raw(s[..c].to_string()),
-        Span::styled(cc.to_string(), Style::new().bg(Color::Yellow)),
-        Span::raw(match c == s.len() {
-            true => String::new(),
-            false => s[c + 1..].to_string(),
-        }),
-    ])
+/// Row and column of the `cursor`th char of a multi-line `value`.
+pub fn position(value: &str, cursor: usize) -> (usize, usize) {
+    let before = value.chars().take(cursor).collect::<String>();
+    let row = before.matches('\n').count();
+    let col = before.rsplit('\n').next().unwrap_or("").chars().count();
+    (row, col)
+}
+
+/// Char index of `col` on `row`, clamped to the end of that row (or the last).
+pub fn index_at(value: &str, row: usize, col: usize) -> usize {
+    let mut start = 0;
+    let lines = value.split('\n').collect::<Vec<_>>();
+    for line in lines.iter().take(row.min(lines.len() - 1)) {
+        start += line.chars().count() + 1;
+    }
+    start + col.min(lines[row.min(lines.len() - 1)].chars().count())
+}
+
+/// Index of the first char shown in a field `width` chars wide, scrolled just
+/// enough that the cursor stays on the last column once it runs off the right.
+pub fn scroll_offset(cursor: usize, width: usize) -> usize {
+    (cursor + 1).saturating_sub(width)
+}
+
+/// Columns `c` takes up on screen, 2 for most CJK and emoji.
+fn columns(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// Index of the first char shown in a field `width` columns wide, scrolled
+/// just enough to keep the `cursor`th char of `chars` in view. Like
+/// [`scroll_offset`] but for chars of any width.
+pub fn fit_offset(chars: &[char], cursor: usize, width: usize) -> usize {
+    let cursor = cursor.min(chars.len());
+    let mut used = chars.get(cursor).map_or(1, |&c| columns(c).max(1));
+    let mut offset = cursor;
+    while offset > 0 && used + columns(chars[offset - 1]) <= width {
+        used += columns(chars[offset - 1]);
+        offset -= 1;
+    }
+    offset
+}
+
+/// The chars from `offset` on that fit in `width` columns.
+fn fit(chars: &[char], offset: usize, width: usize) -> Vec<char> {
+    let mut used = 0;
+    chars
+        .iter()
+        .skip(offset)
+        .take_while(|&&c| {
+            used += columns(c);
+            used <= width
+        })
+        .copied()
+        .collect()
 }
 
 #[derive(Clone)]
 pub struct Input {
     pub secret: bool,
+    /// Char range to highlight as selected.
+    pub selection: Option<Range<usize>>,
+    /// Render every `\n`-separated line of the value, scrolling vertically.
+    pub multiline: bool,
+    pub border: Style,
+    /// Whether the cursor is drawn, off for a form's other fields.
+    pub focused: bool,
+    /// Shown as a count against the limit in the bottom border.
+    pub max_len: Option<usize>,
 }
 
 impl Input {
     pub fn new(secret: bool) -> Self {
-        Self { secret }
+        Self {
+            secret,
+            selection: None,
+            multiline: false,
+            border: Theme::default().prompt_border,
+            focused: true,
+            max_len: None,
+        }
+    }
+
+    pub fn border(mut self, border: Style) -> Self {
+        self.border = border;
+        self
+    }
+
+    pub fn multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
+    pub fn max_len(mut self, max_len: Option<usize>) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+
+    fn render_lines<'a>(
+        &self,
+        value: &str,
+        cursor: usize,
+        width: usize,
+        height: usize,
+    ) -> Vec<Line<'a>> {
+        let (row, col) = position(value, cursor);
+        let top = scroll_offset(row, height);
+        let current = value.split('\n').nth(row).unwrap_or("");
+        let left = fit_offset(&current.chars().collect::<Vec<_>>(), col, width);
+
+        let mut start = 0;
+        value
+            .split('\n')
+            .map(|line| {
+                let line_start = start;
+                start += line.chars().count() + 1;
+                (line_start, line)
+            })
+            .enumerate()
+            .skip(top)
+            .take(height)
+            .map(|(r, (start, line))| {
+                let chars = fit(&line.chars().collect::<Vec<_>>(), left, width);
+                let from = start + left;
+                let selected = self.selection.clone().map_or(0..0, |s| {
+                    s.start.saturating_sub(from)..s.end.saturating_sub(from)
+                });
+
+                match r == row {
+                    true => add_cursor(chars.into_iter().collect(), col - left, selected),
+                    false => highlight(chars, None, selected),
+                }
+            })
+            .collect()
+    }
+
+    pub fn selection(mut self, selection: Option<Range<usize>>) -> Self {
+        self.selection = selection;
+        self
     }
 }
 
@@ -51,34 +204,183 @@ impl StatefulWidget for Input {
     ) {
         let (cursor, value) = state;
         let cursor = *cursor;
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.border);
+        if let Some(max) = self.max_len {
+            let count = format!("{}/{max}", value.chars().count());
+            block = block.title_bottom(Line::from(count).right_aligned());
+        }
+
+        if self.multiline {
+            let (width, height) = (
+                area.width.saturating_sub(2) as usize,
+                area.height.saturating_sub(2) as usize,
+            );
+            Paragraph::new(self.render_lines(value, cursor, width, height))
+                .block(block)
+                .render(area, buf);
+            return;
+        }
 
         let val = match self.secret {
-            true => "*".repeat(value.len()),
+            true => "*".repeat(value.chars().count()),
             false => value.clone(),
         };
 
-        let mut offset = 0;
-        let slice = match area.columns().count() < val.len() + 3 && cursor > area.columns().count()
-        {
-            false => val.clone(),
-            true => {
-                // slice with the cursor at the end
-                offset = cursor - area.columns().count() + 3;
-                val[offset..].to_string()
-            }
-        };
-
-        let block = Block::default().borders(Borders::ALL).yellow();
+        // Inside the borders
+        let width = area.width.saturating_sub(2) as usize;
+        let chars = val.chars().collect::<Vec<_>>();
+        let offset = fit_offset(&chars, cursor, width);
+        let slice = fit(&chars, offset, width).into_iter().collect::<String>();
+        let selected = self.selection.map_or(0..0, |s| {
+            s.start.saturating_sub(offset)..s.end.saturating_sub(offset)
+        });
 
         Paragraph::new(match self.secret {
-            false => add_cursor(slice, cursor - offset),
+            _ if !self.focused => highlight(slice.chars().collect(), None, selected),
+            false => add_cursor(slice, cursor - offset, selected),
             true => add_reveal_cursor(
                 slice,
                 cursor - offset,
-                value.clone().chars().nth(cursor - offset).unwrap_or(' '),
+                value.chars().nth(cursor).unwrap_or(' '),
+                selected,
             ),
         })
         .block(block)
         .render(area, buf);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{buffer::Buffer, layout::Rect, widgets::StatefulWidget};
+
+    use super::*;
+
+    /// Renders a 10 column field (8 inside the borders), returning the inner
+    /// row and the column the cursor landed on.
+    fn render(value: &str, cursor: usize, secret: bool) -> (String, usize) {
+        let area = Rect::new(0, 0, 10, 3);
+        let mut buf = Buffer::empty(area);
+        Input::new(secret).render(area, &mut buf, &mut (cursor, value.to_string()));
+
+        let row = (1..9).map(|x| buf[(x, 1)].symbol()).collect::<String>();
+        let col = (1..9)
+            .position(|x| buf[(x, 1)].bg == Color::Yellow)
+            .unwrap();
+        (row, col)
+    }
+
+    #[test]
+    fn scrolls_only_once_cursor_leaves_field() {
+        assert_eq!(scroll_offset(0, 8), 0);
+        assert_eq!(scroll_offset(7, 8), 0);
+        assert_eq!(scroll_offset(8, 8), 1);
+        assert_eq!(scroll_offset(20, 8), 13);
+        assert_eq!(scroll_offset(3, 0), 4);
+    }
+
+    #[test]
+    fn short_ascii() {
+        assert_eq!(render("abc", 3, false), ("abc     ".into(), 3));
+        assert_eq!(render("abc", 0, false), ("abc     ".into(), 0));
+    }
+
+    #[test]
+    fn long_ascii() {
+        let value = "abcdefghijklmnopqrstuvwxyz";
+        assert_eq!(render(value, 0, false), ("abcdefgh".into(), 0));
+        assert_eq!(render(value, 7, false), ("abcdefgh".into(), 7));
+        assert_eq!(render(value, 8, false), ("bcdefghi".into(), 7));
+        assert_eq!(render(value, 26, false), ("tuvwxyz ".into(), 7));
+    }
+
+    #[test]
+    fn long_multibyte() {
+        let value = "àéîõüçñßøåæœ";
+        assert_eq!(render(value, 2, false), ("àéîõüçñß".into(), 2));
+        assert_eq!(render(value, 10, false), ("õüçñßøåæ".into(), 7));
+        assert_eq!(render(value, 12, false), ("çñßøåæœ ".into(), 7));
+    }
+
+    #[test]
+    fn long_wide_chars() {
+        // Two columns each, so four fit in the 8 column field
+        let value = "日本語のテキスト入力";
+        let (row, _) = render(value, 0, false);
+        assert_eq!(row.replace(' ', ""), "日本語の");
+        assert_eq!(render(value, 5, false).1, 6);
+
+        // Three chars plus the cursor after them at the end
+        let (row, col) = render(value, 10, false);
+        assert_eq!(row.replace(' ', ""), "ト入力");
+        assert_eq!(col, 6);
+
+        assert_eq!(fit_offset(&value.chars().collect::<Vec<_>>(), 4, 8), 1);
+    }
+
+    #[test]
+    fn mixed_width_never_overflows() {
+        let value = "a日b本c語d";
+        for cursor in 0..=value.chars().count() {
+            let chars = value.chars().collect::<Vec<_>>();
+            let offset = fit_offset(&chars, cursor, 4);
+            let shown = fit(&chars, offset, 4);
+            assert!(shown.iter().map(|&c| columns(c)).sum::<usize>() <= 4);
+            assert!(offset <= cursor && cursor <= offset + shown.len());
+        }
+    }
+
+    #[test]
+    fn positions_across_lines() {
+        let value = "ab\ncdé\n\nf";
+        assert_eq!(position(value, 0), (0, 0));
+        assert_eq!(position(value, 2), (0, 2));
+        assert_eq!(position(value, 3), (1, 0));
+        assert_eq!(position(value, 6), (1, 3));
+        assert_eq!(position(value, 7), (2, 0));
+        assert_eq!(position(value, 9), (3, 1));
+
+        assert_eq!(index_at(value, 1, 1), 4);
+        assert_eq!(index_at(value, 2, 5), 7);
+        assert_eq!(index_at(value, 0, 9), 2);
+        assert_eq!(index_at(value, 9, 0), 8);
+    }
+
+    #[test]
+    fn multiline_scrolls_to_cursor_row() {
+        let area = Rect::new(0, 0, 10, 4);
+        let mut buf = Buffer::empty(area);
+        let value = "one\ntwo\nthree".to_string();
+        Input::new(false)
+            .multiline(true)
+            .render(area, &mut buf, &mut (9, value));
+
+        let row = |y| (1..9).map(|x| buf[(x, y)].symbol()).collect::<String>();
+        assert_eq!((row(1), row(2)), ("two     ".into(), "three   ".into()));
+        assert_eq!(buf[(2, 2)].bg, Color::Yellow);
+    }
+
+    #[test]
+    fn cursor_stays_in_view_at_the_limit() {
+        let area = Rect::new(0, 0, 10, 3);
+        let mut buf = Buffer::empty(area);
+        Input::new(false)
+            .max_len(Some(8))
+            .render(area, &mut buf, &mut (8, "abcdefgh".to_string()));
+
+        let row = (1..9).map(|x| buf[(x, 1)].symbol()).collect::<String>();
+        assert_eq!(row, "bcdefgh ");
+        assert_eq!(buf[(8, 1)].bg, Color::Yellow);
+        let bottom = (0..10).map(|x| buf[(x, 2)].symbol()).collect::<String>();
+        assert!(bottom.contains("8/8"));
+    }
+
+    #[test]
+    fn secret_reveals_char_under_cursor() {
+        let value = "hunter2hunter2";
+        assert_eq!(render(value, 3, true), ("***t****".into(), 3));
+        assert_eq!(render(value, 12, true), ("*******r".into(), 7));
+    }
+}
@@ -1,34 +1,56 @@
-use std::{
-    fmt::Debug,
-    ops::Deref,
-    sync::{Arc, RwLock},
-    vec,
-};
+use std::{fmt::Debug, sync::Arc, time::Duration, vec};
 
+use itertools::Itertools;
 use ratatui::{
-    buffer::Buffer,
-    crossterm::event::KeyCode,
-    layout::{Constraint, Direction, Layout, Margin, Rect},
-    style::{Style, Stylize},
-    widgets::{Block, Clear, Paragraph, StatefulWidget, Widget, WidgetRef},
+    layout::{Constraint, Layout, Margin},
+    widgets::{Block, Paragraph, StatefulWidget, Widget, Wrap},
+};
+
+use crate::{
+    config::Config,
+    job::{Job, JobConfig},
+    lock::Recover,
+    Model, Theme,
 };
 
-use crate::Model;
+/// What a menu item does when entered. It's handed the model rather than
+/// capturing one, see [`Menu::with_dynamic_section`].
+pub type MenuHandler = Arc<Box<dyn Fn(Arc<Model>) + Send + Sync>>;
 
-use super::Prompt;
+/// Lists the entries of a dynamic section, see [`Menu::with_dynamic_section`].
+pub type Generator = Arc<Box<dyn Fn(&Model) -> Vec<MenuEntry> + Send + Sync>>;
+
+/// One entry of a dynamic section, which can hold more of them.
+pub enum MenuEntry {
+    Item(String, MenuHandler),
+    Section(String, Vec<MenuEntry>),
+}
+
+impl MenuEntry {
+    pub fn item(title: &str, handler: impl Fn(Arc<Model>) + Send + Sync + 'static) -> Self {
+        Self::Item(title.to_string(), Arc::new(Box::new(handler)))
+    }
+}
 
 #[derive(Clone)]
 pub enum MenuItem {
     Section {
         title: String,
+        icon: Option<String>,
         children: Vec<usize>,
         parent: Option<usize>,
+        /// Set for sections whose children [`Menu::refresh`] fills in.
+        generate: Option<Generator>,
     },
 
     Item {
         title: String,
-        handler: Arc<Box<dyn Fn(Arc<Model>)>>,
+        icon: Option<String>,
+        handler: MenuHandler,
         parent: Option<usize>,
+        /// The job the handler starts, for copying its command without
+        /// running it. Set by [`Menu::with_job`].
+        job: Option<Arc<JobConfig>>,
     },
 }
 
@@ -37,19 +59,31 @@ impl Debug for MenuItem {
         match self {
             Self::Section {
                 title,
+                icon,
                 children,
                 parent,
+                generate,
             } => f
                 .debug_struct("Section")
                 .field("title", title)
+                .field("icon", icon)
                 .field("children", children)
                 .field("parent", parent)
+                .field("dynamic", &generate.is_some())
                 .finish(),
-            Self::Item { title, parent, .. } => f
+            Self::Item {
+                title,
+                icon,
+                parent,
+                job,
+                ..
+            } => f
                 .debug_struct("Item")
                 .field("title", title)
+                .field("icon", icon)
                 .field("handler", &"|| {{}}")
                 .field("parent", parent)
+                .field("job", &job.as_ref().map(|job| job.shell_line()))
                 .finish(),
         }
     }
@@ -58,8 +92,8 @@ impl Debug for MenuItem {
 impl MenuItem {
     pub fn parent(&self) -> Option<usize> {
         match self {
-            MenuItem::Section { parent, .. } => parent.clone(),
-            MenuItem::Item { parent, .. } => parent.clone(),
+            MenuItem::Section { parent, .. } => *parent,
+            MenuItem::Item { parent, .. } => *parent,
         }
     }
 
@@ -76,6 +110,15 @@ impl MenuItem {
             MenuItem::Section { title, .. } => title.clone(),
         }
     }
+
+    /// The icon shown before the title, 📂 for sections and 🧭 for items
+    /// unless one was set.
+    pub fn icon(&self) -> &str {
+        match self {
+            MenuItem::Item { icon, .. } => icon.as_deref().unwrap_or("🧭"),
+            MenuItem::Section { icon, .. } => icon.as_deref().unwrap_or("📂"),
+        }
+    }
 }
 
 impl PartialEq for MenuItem {
@@ -84,27 +127,38 @@ impl PartialEq for MenuItem {
             (
                 Self::Section {
                     title: l_title,
+                    icon: l_icon,
                     children: l_children,
                     parent: l_parent,
+                    ..
                 },
                 Self::Section {
                     title: r_title,
+                    icon: r_icon,
                     children: r_children,
                     parent: r_parent,
+                    ..
                 },
-            ) => l_title == r_title && l_children == r_children && l_parent == r_parent,
+            ) => {
+                l_title == r_title
+                    && l_icon == r_icon
+                    && l_children == r_children
+                    && l_parent == r_parent
+            }
             (
                 Self::Item {
                     title: l_title,
+                    icon: l_icon,
                     parent: l_parent,
                     ..
                 },
                 Self::Item {
                     title: r_title,
+                    icon: r_icon,
                     parent: r_parent,
                     ..
                 },
-            ) => l_title == r_title && l_parent == r_parent,
+            ) => l_title == r_title && l_icon == r_icon && l_parent == r_parent,
             _ => false,
         }
     }
@@ -112,18 +166,21 @@ impl PartialEq for MenuItem {
 
 //
 
+#[derive(Clone)]
 pub struct Menu(pub Vec<MenuItem>);
 impl Menu {
     pub fn with_item(
         &mut self,
         title: &str,
-        handler: impl Fn(Arc<Model>) + 'static,
+        handler: impl Fn(Arc<Model>) + Send + Sync + 'static,
         p: Option<usize>,
     ) -> usize {
         self.0.push(MenuItem::Item {
             title: title.to_string(),
+            icon: None,
             handler: Arc::new(Box::new(handler)),
             parent: p,
+            job: None,
         });
 
         let len = self.0.len() - 1;
@@ -137,11 +194,29 @@ impl Menu {
         len
     }
 
+    /// An item that starts `job`, which [`Menu::command`] can then show.
+    pub fn with_job(&mut self, title: &str, job: JobConfig, p: Option<usize>) -> usize {
+        let config = job.clone();
+        let idx = self.with_item(
+            title,
+            move |m| {
+                m.start_job(config.clone()).ok();
+            },
+            p,
+        );
+        if let MenuItem::Item { job: j, .. } = &mut self.0[idx] {
+            *j = Some(Arc::new(job));
+        }
+        idx
+    }
+
     pub fn with_section(&mut self, title: &str, p: Option<usize>) -> usize {
         self.0.push(MenuItem::Section {
             title: title.to_string(),
+            icon: None,
             children: Vec::new(),
             parent: p,
+            generate: None,
         });
 
         let len = self.0.len() - 1;
@@ -155,66 +230,263 @@ impl Menu {
         len
     }
 
+    /// A section whose entries come from `generate`, called again by every
+    /// [`Menu::refresh`] so they can follow what's going on, like the jobs
+    /// running right now.
+    ///
+    /// The menu is kept on the [`Model`], so neither `generate` nor the
+    /// handlers it returns should hold an `Arc<Model>`, which would keep the
+    /// model alive forever. `generate` borrows the model while it runs, and
+    /// handlers are handed one when entered, which is all either needs. Use
+    /// a `Weak` for anything else.
+    pub fn with_dynamic_section(
+        &mut self,
+        title: &str,
+        generate: impl Fn(&Model) -> Vec<MenuEntry> + Send + Sync + 'static,
+        p: Option<usize>,
+    ) -> usize {
+        let idx = self.with_section(title, p);
+        if let MenuItem::Section { generate: g, .. } = &mut self.0[idx] {
+            *g = Some(Arc::new(Box::new(generate)));
+        }
+        idx
+    }
+
+    /// Regenerates the entries of every dynamic section. Slots from earlier
+    /// refreshes are reused, so indices before a section's entries stay put
+    /// and the menu doesn't keep growing; ones no longer needed are left
+    /// out of the section, and out of [`Menu::commands`].
+    pub fn refresh(&mut self, model: &Model) {
+        for idx in 0..self.0.len() {
+            let MenuItem::Section {
+                generate: Some(generate),
+                ..
+            } = &self.0[idx]
+            else {
+                continue;
+            };
+
+            let entries = generate.clone()(model);
+            let mut slots = (0..self.0.len())
+                .filter(|&i| i != idx && self.ancestors(i).any(|a| a == idx))
+                .collect::<Vec<_>>()
+                .into_iter();
+            let children = self.place(entries, idx, &mut slots);
+            if let MenuItem::Section { children: c, .. } = &mut self.0[idx] {
+                *c = children;
+            }
+        }
+    }
+
+    /// Puts `entries` under `parent`, in `slots` while there are any left.
+    fn place(
+        &mut self,
+        entries: Vec<MenuEntry>,
+        parent: usize,
+        slots: &mut impl Iterator<Item = usize>,
+    ) -> Vec<usize> {
+        let mut placed = vec![];
+        for entry in entries {
+            let item = match &entry {
+                MenuEntry::Item(title, handler) => MenuItem::Item {
+                    title: title.clone(),
+                    icon: None,
+                    handler: handler.clone(),
+                    parent: Some(parent),
+                    job: None,
+                },
+                MenuEntry::Section(title, _) => MenuItem::Section {
+                    title: title.clone(),
+                    icon: None,
+                    children: vec![],
+                    parent: Some(parent),
+                    generate: None,
+                },
+            };
+            let slot = match slots.next() {
+                Some(slot) => {
+                    self.0[slot] = item;
+                    slot
+                }
+                None => {
+                    self.0.push(item);
+                    self.0.len() - 1
+                }
+            };
+            if let MenuEntry::Section(_, entries) = entry {
+                let children = self.place(entries, slot, slots);
+                if let MenuItem::Section { children: c, .. } = &mut self.0[slot] {
+                    *c = children;
+                }
+            }
+            placed.push(slot);
+        }
+        placed
+    }
+
+    /// `idx` and the sections above it, stopping short of going round in
+    /// circles through slots a refresh left behind.
+    fn ancestors(&self, idx: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(idx), |&i| self.0.get(i)?.parent()).take(self.0.len() + 1)
+    }
+
+    /// Whether `idx` can be got to from the root, rather than being left
+    /// over from before a refresh.
+    fn reachable(&self, idx: usize) -> bool {
+        idx < self.0.len()
+            && self
+                .ancestors(idx)
+                .tuple_windows()
+                .all(|(child, parent)| self.0[parent].items().contains(&child))
+    }
+
+    /// Swaps the default icon of the entry at `idx` for `icon`.
+    pub fn with_icon(&mut self, idx: usize, icon: &str) -> usize {
+        match self.0.get_mut(idx).unwrap() {
+            MenuItem::Section { icon: i, .. } | MenuItem::Item { icon: i, .. } => {
+                *i = Some(icon.to_string())
+            }
+        }
+        idx
+    }
+
     //
 
+    /// The entries of the section holding `idx`, none for the root or
+    /// anything past the end.
+    pub fn siblings(&self, idx: usize) -> Vec<usize> {
+        self.0
+            .get(idx)
+            .and_then(MenuItem::parent)
+            .and_then(|p| self.0.get(p))
+            .map_or(vec![], MenuItem::items)
+    }
+
     pub fn up(&self, idx: usize) -> usize {
-        let items = self
-            .0
-            .get(self.0.get(idx).unwrap().parent().unwrap())
-            .unwrap()
-            .items();
+        let items = self.siblings(idx);
         items
-            .get(
-                items
-                    .iter()
-                    .position(|i| *i == idx)
-                    .unwrap()
-                    .saturating_sub(1),
-            )
+            .iter()
+            .position(|i| *i == idx)
+            .and_then(|pos| items.get(pos.saturating_sub(1)))
             .copied()
             .unwrap_or(idx)
     }
 
     pub fn down(&self, idx: usize) -> usize {
-        let items = self
-            .0
-            .get(self.0.get(idx).unwrap().parent().unwrap())
-            .unwrap()
-            .items();
+        let items = self.siblings(idx);
         items
-            .get(items.iter().position(|i| *i == idx).unwrap() + 1)
+            .iter()
+            .position(|i| *i == idx)
+            .and_then(|pos| items.get(pos + 1))
             .copied()
             .unwrap_or(idx)
     }
 
     pub fn enter(&self, idx: usize, model: Arc<Model>) -> usize {
-        match self.0.get(idx).unwrap() {
-            MenuItem::Section { children, .. } => children.first().copied().unwrap_or(idx),
-            MenuItem::Item { handler, .. } => {
+        match self.0.get(idx) {
+            Some(MenuItem::Section { children, .. }) => children.first().copied().unwrap_or(idx),
+            Some(MenuItem::Item { handler, .. }) => {
                 handler(model);
                 idx
             }
+            None => idx,
         }
     }
 
+    /// What the item at `idx` would run, as [`JobConfig::shell_line`] has
+    /// it, if it starts a job.
+    pub fn command(&self, idx: usize) -> Option<String> {
+        match self.0.get(idx)? {
+            MenuItem::Item { job: Some(job), .. } => Some(job.shell_line()),
+            _ => None,
+        }
+    }
+
+    /// `idx` while it's still in its section, otherwise the first entry
+    /// left there, or the section itself once it's empty. Dynamic sections
+    /// can lose the selected entry between one refresh and the next.
+    pub fn settle(&self, idx: usize) -> usize {
+        if self.reachable(idx) {
+            return idx;
+        }
+        let Some(section) = self.ancestors(idx).skip(1).find(|&s| self.reachable(s)) else {
+            return self.first().unwrap_or(idx);
+        };
+        match (self.0[section].items().first(), self.0[section].parent()) {
+            (Some(&first), _) => first,
+            (None, Some(_)) => section,
+            (None, None) => self.first().unwrap_or(idx),
+        }
+    }
+
+    /// Steps out of the section holding `idx`, selecting that section in
+    /// its own parent, so each Esc goes up exactly one level. Entries in a
+    /// top-level section go back to the root list where that section is
+    /// selected. From the root list there's nowhere left to go, so this
+    /// gives `None` and the menu closes.
     pub fn back(&self, idx: usize) -> Option<usize> {
-        let parent = self.0.get(idx).unwrap().parent();
-        parent
-            .map(|p| self.0.get(p).unwrap().parent().map(|_| p))
-            .flatten()
+        let section = self.0.get(idx)?.parent()?;
+        // The root itself is never selected, only its entries
+        self.0.get(section)?.parent().map(|_| section)
+    }
+
+    /// Every runnable item with the path of sections leading to it, e.g.
+    /// `Build Frontend › Sites (wasm)`. The root section is left out.
+    pub fn commands(&self) -> Vec<(String, usize)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(idx, item)| matches!(item, MenuItem::Item { .. }) && self.reachable(*idx))
+            .map(|(idx, item)| {
+                let mut path = vec![item.title()];
+                let mut parent = item.parent();
+                while let Some(p) = parent {
+                    let section = self.0.get(p).unwrap();
+                    parent = section.parent();
+                    if parent.is_some() {
+                        path.push(section.title());
+                    }
+                }
+                path.reverse();
+                (path.join(" › "), idx)
+            })
+            .collect()
+    }
+
+    /// Every entry below the root, one per line and indented two spaces per
+    /// level, sections before what's in them. Dynamic sections are left out
+    /// while they're empty.
+    pub fn tree(&self) -> String {
+        fn walk(menu: &Menu, idx: usize, depth: usize, out: &mut String) {
+            for child in menu.0[idx].items() {
+                if let MenuItem::Section {
+                    children,
+                    generate: Some(_),
+                    ..
+                } = &menu.0[child]
+                {
+                    if children.is_empty() {
+                        continue;
+                    }
+                }
+                out.push_str(&format!("{:depth$}{}\n", "", menu.0[child].title()));
+                walk(menu, child, depth + 2, out);
+            }
+        }
+
+        let mut out = String::new();
+        if let Some(root) = self.0.iter().position(|item| item.parent().is_none()) {
+            walk(self, root, 0, &mut out);
+        }
+        out
     }
 
     //
 
-    pub fn first(&self) -> usize {
-        *self
-            .0
-            .iter()
-            .find(|i| !i.items().is_empty())
-            .unwrap()
-            .items()
-            .first()
-            .unwrap()
+    /// The first entry of the first section that has any, `None` when
+    /// there's nothing in the menu at all.
+    pub fn first(&self) -> Option<usize> {
+        self.0.iter().find_map(|i| i.items().first().copied())
     }
 }
 
@@ -227,8 +499,29 @@ impl StatefulWidget for Menu {
         buf: &mut ratatui::prelude::Buffer,
         state: &mut Self::State,
     ) {
-        let containter = self.0.get(*state).unwrap().parent();
-        let container = self.0.get(containter.unwrap()).unwrap();
+        self.render_themed(area, buf, *state, &Theme::default());
+    }
+}
+
+impl Menu {
+    /// Draws the section holding `selected`, in `theme`'s colours, or a
+    /// placeholder when it isn't in one, as in a menu with nothing in it.
+    pub fn render_themed(
+        &self,
+        area: ratatui::prelude::Rect,
+        buf: &mut ratatui::prelude::Buffer,
+        selected: usize,
+        theme: &Theme,
+    ) {
+        let parent = self.0.get(selected).and_then(MenuItem::parent);
+        let Some(container) = parent.and_then(|p| self.0.get(p)) else {
+            Paragraph::new("Nothing to run yet, add jobs to itask.toml")
+                .style(theme.unselected)
+                .wrap(Wrap { trim: true })
+                .block(Block::bordered().title("Jobs"))
+                .render(area, buf);
+            return;
+        };
 
         Block::bordered().title(container.title()).render(area, buf);
         let area = Layout::new(ratatui::layout::Direction::Vertical, {
@@ -248,7 +541,7 @@ impl StatefulWidget for Menu {
             .enumerate()
             .filter(|(i, _)| container.items().contains(i))
             .enumerate()
-            .for_each(|(idx, (i, e))| e.clone().render(area[idx], buf, &mut (i == *state)));
+            .for_each(|(idx, (i, e))| e.render_themed(area[idx], buf, i == selected, theme));
     }
 }
 
@@ -260,16 +553,25 @@ impl StatefulWidget for MenuItem {
         buf: &mut ratatui::prelude::Buffer,
         state: &mut Self::State,
     ) {
-        let name = match self {
-            MenuItem::Section { title, .. } => format!("📂 {title}"),
-            MenuItem::Item { title, .. } => format!("🧭 {title}"),
-        };
+        self.render_themed(area, buf, *state, &Theme::default());
+    }
+}
+
+impl MenuItem {
+    pub fn render_themed(
+        &self,
+        area: ratatui::prelude::Rect,
+        buf: &mut ratatui::prelude::Buffer,
+        selected: bool,
+        theme: &Theme,
+    ) {
+        let name = format!("{} {}", self.icon(), self.title());
 
         Paragraph::new(name)
             .alignment(ratatui::layout::Alignment::Left)
-            .style(match state {
-                true => Style::new().on_white().black().bold(),
-                false => Style::new().bold().white(),
+            .style(match selected {
+                true => theme.selected,
+                false => theme.unselected,
             })
             .render(area, buf);
     }
@@ -277,33 +579,51 @@ impl StatefulWidget for MenuItem {
 
 macro_rules! menu {
     // Rule to create a root menu section
-    ($menu:ident, $name:literal => { $($sub:tt)* }) => {
+    ($menu:ident, $name:literal $([$icon:literal])? => { $($sub:tt)* }) => {
         let root = $menu.with_section($name, None);
+        $($menu.with_icon(root, $icon);)?
         menu!(@subsections $menu, root, $($sub)*);
     };
 
     // Rule to create a nested menu section within a parent
-    (@subsections $menu:ident, $parent:expr, $name:literal => { $($sub:tt)* } , $($rest:tt)*) => {
+    (@subsections $menu:ident, $parent:expr, $name:literal $([$icon:literal])? => { $($sub:tt)* } , $($rest:tt)*) => {
         let section = $menu.with_section($name, Some($parent));
+        $($menu.with_icon(section, $icon);)?
         menu!(@subsections $menu, section, $($sub)*);
         menu!(@subsections $menu, $parent, $($rest)*);
     };
 
+    // Rule to create an item starting a job, see `Menu::with_job`
+    (@subsections $menu:ident, $parent:expr, $name:literal $([$icon:literal])? => job $job:expr, $($rest:tt)*) => {
+        let _item = $menu.with_job($name, $job, Some($parent));
+        $($menu.with_icon(_item, $icon);)?
+        menu!(@subsections $menu, $parent, $($rest)*);
+    };
+
     // Rule to create a single menu item within a section
-    (@subsections $menu:ident, $parent:expr, $name:literal => $action:expr, $($rest:tt)*) => {
-        $menu.with_item($name, $action, Some($parent));
+    (@subsections $menu:ident, $parent:expr, $name:literal $([$icon:literal])? => $action:expr, $($rest:tt)*) => {
+        let _item = $menu.with_item($name, $action, Some($parent));
+        $($menu.with_icon(_item, $icon);)?
         menu!(@subsections $menu, $parent, $($rest)*);
     };
 
     // End of a section without more subsections
-    (@subsections $menu:ident, $parent:expr, $name:literal => { $($sub:tt)* }) => {
+    (@subsections $menu:ident, $parent:expr, $name:literal $([$icon:literal])? => { $($sub:tt)* }) => {
         let section = $menu.with_section($name, Some($parent));
+        $($menu.with_icon(section, $icon);)?
         menu!(@subsections $menu, section, $($sub)*);
     };
 
+    // End of an item starting a job
+    (@subsections $menu:ident, $parent:expr, $name:literal $([$icon:literal])? => job $job:expr) => {
+        let _item = $menu.with_job($name, $job, Some($parent));
+        $($menu.with_icon(_item, $icon);)?
+    };
+
     // End of an item without more subsections
-    (@subsections $menu:ident, $parent:expr, $name:literal => $action:expr) => {
-        $menu.with_item($name, $action, Some($parent));
+    (@subsections $menu:ident, $parent:expr, $name:literal $([$icon:literal])? => $action:expr) => {
+        let _item = $menu.with_item($name, $action, Some($parent));
+        $($menu.with_icon(_item, $icon);)?
     };
 
     // Empty rule to stop recursion
@@ -317,24 +637,222 @@ pub fn main_menu() -> Menu {
         menu,
         "Jobs" => {
             "Run (Server)" => {
-                "Sites (bin)" => |_| {},
+                "Sites (bin)" ["🦀"] => job JobConfig::new("cargo", &["run", "--bin", "sites"])
+                    .retries(3, Duration::from_secs(2)),
             },
             "Build Frontend" => {
-                "Sites (wasm)" => |_| {},
+                "Sites (wasm)" ["📦"] => |_| {},
                 "Something (wasm+elm)" => |_| {},
             },
             "Configure iTask" => {
-                "Set ENV" => |m| {
-                    *m.prompt.write().unwrap() =
-                        Some(Prompt::secret("Enter your Yubikey pin", |_pin| {
-                            return Err("Invalid pin".to_string());
-                        }));
-                },
+                "Set ENV" => |m| m.env_prompt(),
+                "Edit ENV" => |m| m.env_form(),
             },
         }
     };
+    menu.with_dynamic_section("Active Jobs", active_jobs, Some(0));
+
+    menu
+}
+
+/// A section per running job, to focus, kill, restart or clear it, after
+/// entries restarting or stopping them all.
+pub fn active_jobs(model: &Model) -> Vec<MenuEntry> {
+    let jobs = model.jobs.read_or_recover();
+    let mut entries = vec![];
+    if jobs.iter().any(Job::running) {
+        entries.push(MenuEntry::item("Restart all", |m| {
+            m.restart_all().ok();
+        }));
+        entries.push(MenuEntry::item("Stop all", |m| m.stop_all()));
+    }
+    let each = jobs
+        .iter()
+        .enumerate()
+        .filter(|(_, job)| job.running())
+        .map(|(idx, job)| {
+            let on_job = |f: fn(&mut Job)| {
+                move |m: Arc<Model>| {
+                    if let Some(job) = m.jobs.write_or_recover().get_mut(idx) {
+                        f(job);
+                    }
+                    m.shared.dirty.mark();
+                }
+            };
+            MenuEntry::Section(
+                job.title.clone(),
+                vec![
+                    MenuEntry::item("Focus", move |m| m.focus(idx)),
+                    MenuEntry::item("Kill", on_job(|job| job.stop())),
+                    MenuEntry::item("Restart", move |m| {
+                        m.restart_job(idx).ok();
+                    }),
+                    MenuEntry::item("Clear", on_job(Job::clear)),
+                ],
+            )
+        });
+    entries.extend(each);
+    entries
+}
+
+/// The menu for jobs defined in `itask.toml`, grouped by their `section`.
+pub fn config_menu(config: &Config) -> Menu {
+    let mut menu = Menu(vec![]);
+    let root = menu.with_section("Jobs", None);
+
+    let mut sections = Vec::<(String, usize)>::new();
+    for entry in &config.jobs {
+        let parent = match &entry.section {
+            None => root,
+            Some(title) => match sections.iter().find(|(t, _)| t == title) {
+                Some((_, idx)) => *idx,
+                None => {
+                    let idx = menu.with_section(title, Some(root));
+                    sections.push((title.clone(), idx));
+                    idx
+                }
+            },
+        };
+
+        let item = menu.with_job(&entry.title(), entry.job.clone(), Some(parent));
+        if let Some(icon) = &entry.icon {
+            menu.with_icon(item, icon);
+        }
+    }
+
+    menu.with_dynamic_section("Active Jobs", active_jobs, Some(root));
+
+    if !config.groups.is_empty() {
+        let section = menu.with_section("Groups", Some(root));
+        for group in &config.groups {
+            let name = group.name.clone();
+            let item = menu.with_item(
+                &group.name,
+                move |m| {
+                    m.start_group(&name).ok();
+                },
+                Some(section),
+            );
+            menu.with_icon(item, "🗂");
+        }
+    }
 
     menu
 }
 
 //
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// root › Build › Web › wasm, with `native` beside Web and `Quit`
+    /// beside Build.
+    fn three_levels() -> (Menu, [usize; 5]) {
+        let mut menu = Menu(vec![]);
+        let root = menu.with_section("root", None);
+        let build = menu.with_section("Build", Some(root));
+        let web = menu.with_section("Web", Some(build));
+        let wasm = menu.with_item("wasm", |_| {}, Some(web));
+        menu.with_item("native", |_| {}, Some(build));
+        let quit = menu.with_item("Quit", |_| {}, Some(root));
+        (menu, [root, build, web, wasm, quit])
+    }
+
+    #[test]
+    fn back_goes_up_one_level_at_a_time() {
+        let (menu, [_, build, web, wasm, quit]) = three_levels();
+        assert_eq!(menu.back(wasm), Some(web));
+        assert_eq!(menu.back(web), Some(build));
+        // Build is in the root list, which has nothing above it
+        assert_eq!(menu.back(build), None);
+        assert_eq!(menu.back(quit), None);
+    }
+
+    #[test]
+    fn enter_and_back_retrace_the_same_path() {
+        let (menu, [_, build, web, wasm, quit]) = three_levels();
+        let model = Arc::new(Model::default());
+        assert_eq!(menu.first(), Some(build));
+        assert_eq!(menu.down(build), quit);
+
+        let mut idx = menu.enter(build, model.clone());
+        assert_eq!(idx, web);
+        idx = menu.enter(idx, model.clone());
+        assert_eq!(idx, wasm);
+        assert_eq!(menu.enter(idx, model), wasm);
+
+        let path = std::iter::successors(Some(wasm), |&idx| menu.back(idx)).collect::<Vec<_>>();
+        assert_eq!(path, [wasm, web, build]);
+    }
+
+    #[test]
+    fn tree_indents_by_level() {
+        let (menu, _) = three_levels();
+        assert_eq!(menu.tree(), "Build\n  Web\n    wasm\n  native\nQuit\n");
+    }
+
+    #[test]
+    fn dynamic_sections_follow_the_model() {
+        let (mut menu, [root, ..]) = three_levels();
+        let names = Arc::new(std::sync::RwLock::new(vec!["one", "two"]));
+        let running = menu.with_dynamic_section(
+            "Running",
+            {
+                let names = names.clone();
+                move |_| {
+                    names
+                        .read_or_recover()
+                        .iter()
+                        .map(|name| MenuEntry::item(name, |_| {}))
+                        .collect()
+                }
+            },
+            Some(root),
+        );
+        let model = Model::default();
+        assert!(menu.0[running].items().is_empty());
+
+        menu.refresh(&model);
+        assert!(menu.tree().ends_with("Running\n  one\n  two\n"));
+        let len = menu.0.len();
+
+        *names.write_or_recover() = vec!["three"];
+        menu.refresh(&model);
+        assert!(menu.tree().ends_with("Running\n  three\n"));
+        let three = menu.0[running].items()[0];
+        assert_eq!(menu.settle(three), three);
+        assert_eq!(menu.settle(len - 1), three);
+        let commands = menu.commands();
+        assert!(commands.iter().any(|(path, _)| path == "Running › three"));
+        assert!(!commands.iter().any(|(path, _)| path == "Running › two"));
+
+        *names.write_or_recover() = vec!["a", "b"];
+        menu.refresh(&model);
+        assert!(menu.tree().ends_with("Running\n  a\n  b\n"));
+        assert_eq!(menu.0.len(), len);
+    }
+
+    #[test]
+    fn empty_menus_have_no_first_entry() {
+        let model = Arc::new(Model::default());
+        for menu in [Menu(vec![]), {
+            let mut menu = Menu(vec![]);
+            menu.with_section("Jobs", None);
+            menu
+        }] {
+            assert_eq!(menu.first(), None);
+            assert_eq!(menu.up(0), 0);
+            assert_eq!(menu.down(0), 0);
+            assert_eq!(menu.enter(0, model.clone()), 0);
+            assert_eq!(menu.settle(0), 0);
+            assert_eq!(menu.back(0), None);
+        }
+    }
+
+    #[test]
+    fn back_from_nowhere_closes() {
+        let (menu, _) = three_levels();
+        assert_eq!(menu.back(99), None);
+    }
+}
@@ -5,4 +5,13 @@ mod input;
 pub use input::*;
 
 mod prompt;
-pub use prompt::*;
\ No newline at end of file
+pub use prompt::*;
+
+mod palette;
+pub use palette::*;
+
+mod help;
+pub use help::*;
+
+mod progress;
+pub use progress::*;
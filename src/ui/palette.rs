@@ -0,0 +1,108 @@
+use ratatui::{
+    crossterm::event::KeyCode,
+    layout::{Margin, Rect},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, Clear, Paragraph, Widget},
+};
+
+use crate::{
+    ansi,
+    fuzzy::{self, Match},
+};
+
+/// Jumps straight to any menu item by typing part of its path.
+pub struct Palette {
+    query: String,
+    selected: usize,
+    /// Path and menu index of every item on offer.
+    commands: Vec<(String, usize)>,
+}
+
+impl Palette {
+    pub fn new(commands: Vec<(String, usize)>) -> Self {
+        Self {
+            query: String::new(),
+            selected: 0,
+            commands,
+        }
+    }
+
+    /// Commands matching the query, best first.
+    pub fn matches(&self) -> Vec<(Match, &(String, usize))> {
+        fuzzy::rank(&self.query, &self.commands, |(path, _)| path)
+    }
+
+    /// Handles a key, returning the menu index to run once one is chosen.
+    pub fn input(&mut self, key: KeyCode) -> Option<usize> {
+        match key {
+            KeyCode::Enter => {
+                return self.matches().get(self.selected).map(|(_, (_, idx))| *idx);
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => {
+                self.selected = (self.selected + 1).min(self.matches().len().saturating_sub(1))
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Where the palette sits: centred, a little above the middle.
+    pub fn area(&self, area: Rect) -> Rect {
+        let width = (area.width / 2).max(40).min(area.width);
+        let height = 12.min(area.height);
+        Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 3,
+            width,
+            height,
+        }
+    }
+}
+
+impl Widget for &Palette {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        Clear.render(area, buf);
+        Block::bordered().title("Commands").render(area, buf);
+
+        let inner = area.inner(Margin::new(1, 1));
+        let mut lines = vec![Line::from(vec![
+            "> ".yellow(),
+            self.query.clone().into(),
+            " ".on_yellow(),
+        ])];
+
+        lines.extend(
+            self.matches()
+                .into_iter()
+                .take(inner.height.saturating_sub(1) as usize)
+                .enumerate()
+                .map(|(i, (m, (path, _)))| {
+                    let matched = m
+                        .indices
+                        .iter()
+                        .map(|&at| {
+                            let len = path[at..].chars().next().map_or(1, char::len_utf8);
+                            (at..at + len, Style::new().yellow().bold())
+                        })
+                        .collect::<Vec<_>>();
+                    let line = Line::from(ansi::styled(path, &matched));
+                    match i == self.selected {
+                        true => line.style(Style::new().on_white().black()),
+                        false => line,
+                    }
+                }),
+        );
+
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
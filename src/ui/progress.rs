@@ -0,0 +1,94 @@
+use std::sync::{Arc, RwLock};
+
+use ratatui::{
+    layout::Rect,
+    style::{Style, Stylize},
+    widgets::{Block, Clear, Gauge, Widget},
+};
+
+use crate::lock::Recover;
+
+/// How far along something is, from 0.0 to 1.0, written by whatever's doing
+/// the work.
+pub type Fraction = Arc<RwLock<f64>>;
+
+/// A modal gauge for long operations, shown until its [`Fraction`] reaches
+/// 1.0.
+#[derive(Clone)]
+pub struct Progress {
+    title: String,
+    done: Fraction,
+}
+
+impl Progress {
+    pub fn new(title: &str, done: Fraction) -> Self {
+        Self {
+            title: title.to_string(),
+            done,
+        }
+    }
+
+    /// Clamped, so a worker overshooting or starting below zero still draws.
+    pub fn fraction(&self) -> f64 {
+        let done = *self.done.read_or_recover();
+        match done.is_nan() {
+            true => 0.0,
+            false => done.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.fraction() >= 1.0
+    }
+
+    /// Centred, as wide as a prompt and one bar tall.
+    pub fn area(&self, area: Rect) -> Rect {
+        let width = area.width / 2;
+        let height = 3.min(area.height);
+        Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+impl Widget for &Progress {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let fraction = self.fraction();
+        Clear.render(area, buf);
+        Gauge::default()
+            .block(
+                Block::bordered()
+                    .title(self.title.clone())
+                    .title_bottom("Esc to hide"),
+            )
+            .gauge_style(Style::new().cyan())
+            .ratio(fraction)
+            .label(format!("{:.0}%", fraction * 100.0))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_what_the_worker_writes() {
+        let done = Fraction::default();
+        let progress = Progress::new("Setup", done.clone());
+        assert_eq!(progress.fraction(), 0.0);
+
+        *done.write_or_recover() = 0.25;
+        assert_eq!(progress.fraction(), 0.25);
+        assert!(!progress.finished());
+
+        *done.write_or_recover() = f64::NAN;
+        assert_eq!(progress.fraction(), 0.0);
+        *done.write_or_recover() = 1.5;
+        assert_eq!(progress.fraction(), 1.0);
+        assert!(progress.finished());
+    }
+}
@@ -1,82 +1,659 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::BTreeMap,
+    num::IntErrorKind,
+    ops::Range,
+    sync::{Arc, RwLock},
+};
 
 use ratatui::{
-    crossterm::event::KeyCode,
-    layout::{Constraint, Direction, Layout, Margin},
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
     style::Stylize,
+    text::Line,
     widgets::{Block, Clear, Paragraph, StatefulWidget, Widget},
 };
 
-use super::Input;
+use crate::{lock::Recover, Theme};
+
+use super::{index_at, position, scroll_offset, Input};
+
+type TextHandler = Arc<Box<dyn Fn(String) -> Result<(), String> + Send + Sync>>;
+type ConfirmHandler = Arc<Box<dyn Fn(bool) -> Result<(), String> + Send + Sync>>;
+type Validator = Arc<Box<dyn Fn(&str) -> Option<String> + Send + Sync>>;
+type Completer = Arc<Box<dyn Fn(&str) -> Vec<String> + Send + Sync>>;
+type SelectHandler = Arc<Box<dyn Fn(usize, String) -> Result<(), String> + Send + Sync>>;
+type FormHandler = Arc<Box<dyn Fn(BTreeMap<String, String>) -> Result<(), String> + Send + Sync>>;
+
+/// Past submissions, oldest first, shared by every prompt given it.
+pub type History = Arc<RwLock<Vec<String>>>;
+
+#[derive(Clone)]
+enum Handler {
+    Text(TextHandler),
+    /// A Yes/No choice instead of a text field.
+    Confirm(ConfirmHandler),
+    /// One of a list of options instead of a text field.
+    Select(SelectHandler, Vec<String>),
+    /// A text field for each label, all submitted together.
+    Form(FormHandler, Vec<String>),
+}
+
+#[derive(Default)]
+struct PromptState {
+    /// In chars, not bytes.
+    cursor: usize,
+    /// Where a Shift+arrow selection started, the other end being the cursor.
+    anchor: Option<usize>,
+    value: String,
+    error: String,
+    /// Which button a confirmation has selected, No to start with.
+    yes: bool,
+    /// Index of the highlighted option in a select prompt.
+    choice: usize,
+    /// The history entry shown, with what was typed before recalling it.
+    recalled: Option<(usize, String)>,
+    /// What the last Tab offered when it had more than one answer.
+    completions: Vec<String>,
+    /// Which field of a form is being edited.
+    field: usize,
+    /// The cursor and value of each field of a form. The one being edited
+    /// lives in `cursor` and `value` until another is picked.
+    fields: Vec<(usize, String)>,
+    /// Set when the last key or paste was cut short by [`Prompt::max_len`],
+    /// until the next one.
+    at_limit: bool,
+    /// Text Ctrl+C copied, for [`Prompt::take_copied`].
+    copied: Option<String>,
+}
+
+impl PromptState {
+    fn byte(&self, c: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(c)
+            .map_or(self.value.len(), |(i, _)| i)
+    }
+
+    fn len(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        let range = anchor.min(self.cursor)..anchor.max(self.cursor);
+        (!range.is_empty()).then_some(range)
+    }
+
+    fn selected(&self) -> Option<&str> {
+        let range = self.selection()?;
+        Some(&self.value[self.byte(range.start)..self.byte(range.end)])
+    }
+
+    /// Removes the selected text, returning whether there was any.
+    fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            return false;
+        };
+
+        self.value
+            .replace_range(self.byte(range.start)..self.byte(range.end), "");
+        self.cursor = range.start;
+        self.anchor = None;
+        true
+    }
+
+    /// Moves the cursor, extending the selection if `select` is held.
+    fn move_to(&mut self, c: usize, select: bool) {
+        match select {
+            true => {
+                self.anchor.get_or_insert(self.cursor);
+            }
+            false => self.anchor = None,
+        }
+        self.cursor = c.min(self.len());
+    }
+
+    /// Where the word before `c` starts, skipping any gap before it.
+    fn word_before(&self, c: usize) -> usize {
+        let chars = self.value.chars().take(c).collect::<Vec<_>>();
+        let gap = chars.iter().rev().take_while(|c| !is_word(**c)).count();
+        let word = chars[..chars.len() - gap]
+            .iter()
+            .rev()
+            .take_while(|c| is_word(**c))
+            .count();
+        c - gap - word
+    }
+
+    /// Where the word after `c` ends, skipping any gap before it.
+    fn word_after(&self, c: usize) -> usize {
+        let chars = self.value.chars().skip(c).collect::<Vec<_>>();
+        let gap = chars.iter().take_while(|c| !is_word(**c)).count();
+        let word = chars[gap..].iter().take_while(|c| is_word(**c)).count();
+        c + gap + word
+    }
+
+    /// Moves the cursor to the next word's end or the previous one's start.
+    fn move_word(&mut self, forward: bool, select: bool) {
+        let to = match forward {
+            true => self.word_after(self.cursor),
+            false => self.word_before(self.cursor),
+        };
+        self.move_to(to, select);
+    }
+
+    /// Shows `value` with the cursor at its end.
+    fn replace(&mut self, value: String) {
+        self.value = value;
+        self.anchor = None;
+        self.cursor = self.len();
+    }
+
+    /// Puts the field being edited away and picks up field `to`.
+    fn edit_field(&mut self, to: usize) {
+        if let Some(field) = self.fields.get_mut(self.field) {
+            *field = (self.cursor, std::mem::take(&mut self.value));
+        }
+        (self.cursor, self.value) = self.fields.get(to).cloned().unwrap_or_default();
+        self.field = to;
+        self.anchor = None;
+    }
+
+    /// The field at `i` as typed so far.
+    fn field_value(&self, i: usize) -> &str {
+        match i == self.field {
+            true => &self.value,
+            false => self.fields.get(i).map_or("", |(_, value)| value),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Prompt {
     title: String,
     secret: bool,
-    handler: Arc<Box<dyn Fn(String) -> Result<(), String>>>,
-    state: Arc<RwLock<(usize, String, String)>>,
+    /// Enter inserts a newline, Ctrl+Enter submits.
+    multiline: bool,
+    /// Only digits (and a leading `-`) can be typed, see [`Prompt::number`].
+    numeric: bool,
+    /// The most chars the value can hold, see [`Prompt::max_len`].
+    max_len: Option<usize>,
+    /// Modal width in columns, half the screen if unset.
+    width: Option<u16>,
+    handler: Handler,
+    history: Option<History>,
+    validator: Option<Validator>,
+    completer: Option<Completer>,
+    theme: Theme,
+    state: Arc<RwLock<PromptState>>,
 }
 
 impl Prompt {
-    pub fn new(title: &str, handler: impl Fn(String) -> Result<(), String> + 'static) -> Self {
+    pub fn new(
+        title: &str,
+        handler: impl Fn(String) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
         Self {
             secret: false,
+            multiline: false,
+            numeric: false,
+            max_len: None,
+            width: None,
             title: title.to_string(),
-            handler: Arc::new(Box::new(handler)),
+            handler: Handler::Text(Arc::new(Box::new(handler))),
+            history: None,
+            validator: None,
+            completer: None,
+            theme: Theme::default(),
             state: Default::default(),
         }
     }
 
-    pub fn secret(title: &str, handler: impl Fn(String) -> Result<(), String> + 'static) -> Self {
+    /// Asks a Yes/No question, answered with y/n or Left/Right and Enter.
+    pub fn confirm(
+        title: &str,
+        handler: impl Fn(bool) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            handler: Handler::Confirm(Arc::new(Box::new(handler))),
+            ..Self::new(title, |_| Ok(()))
+        }
+    }
+
+    /// Asks to pick one of `options` with Up/Down and Enter, passing on its
+    /// index and text.
+    pub fn select(
+        title: &str,
+        options: Vec<String>,
+        handler: impl Fn(usize, String) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            handler: Handler::Select(Arc::new(Box::new(handler)), options),
+            ..Self::new(title, |_| Ok(()))
+        }
+    }
+
+    /// Asks for a whole number. Anything but digits and a leading `-` is
+    /// ignored as it's typed, and values that don't fit an `i64` are refused
+    /// on submit.
+    pub fn number(
+        title: &str,
+        handler: impl Fn(i64) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            numeric: true,
+            ..Self::new(title, move |value| {
+                let n = value.parse::<i64>().map_err(|e| match e.kind() {
+                    IntErrorKind::Empty => "Enter a number".to_string(),
+                    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                        format!("Must be between {} and {}", i64::MIN, i64::MAX)
+                    }
+                    _ => "Not a number".to_string(),
+                })?;
+                handler(n)
+            })
+        }
+    }
+
+    /// Asks for several values at once, a labelled field each. Tab and
+    /// Shift+Tab (or Down and Up) move between them, and Enter passes every
+    /// value on, keyed by its label.
+    pub fn form(
+        title: &str,
+        labels: &[&str],
+        handler: impl Fn(BTreeMap<String, String>) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        let state = PromptState {
+            fields: vec![Default::default(); labels.len()],
+            ..Default::default()
+        };
+        Self {
+            handler: Handler::Form(
+                Arc::new(Box::new(handler)),
+                labels.iter().map(|l| l.to_string()).collect(),
+            ),
+            state: Arc::new(RwLock::new(state)),
+            ..Self::new(title, |_| Ok(()))
+        }
+    }
+
+    pub fn secret(
+        title: &str,
+        handler: impl Fn(String) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
         Self {
             secret: true,
-            title: title.to_string(),
-            handler: Arc::new(Box::new(handler)),
-            state: Default::default(),
+            ..Self::new(title, handler)
+        }
+    }
+
+    pub fn multiline(mut self) -> Self {
+        self.multiline = true;
+        self
+    }
+
+    /// Lets Up and Down go through `history`, which submissions are added
+    /// to. Secret prompts never record.
+    pub fn history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Checks the value after every edit, showing what `validator` returns
+    /// as the error. Typing carries on either way, and Enter still submits.
+    pub fn validate(
+        mut self,
+        validator: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.validator = Some(Arc::new(Box::new(validator)));
+        self
+    }
+
+    /// Completes the value on Tab with what `completer` returns for it: a
+    /// single answer replaces the value, several are listed under the input.
+    pub fn completer(
+        mut self,
+        completer: impl Fn(&str) -> Vec<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.completer = Some(Arc::new(Box::new(completer)));
+        self
+    }
+
+    /// Refuses to type or paste past `max_len` chars, saying so in place of
+    /// the error until the next key. Selected text being replaced doesn't
+    /// count towards it.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// How many more chars fit, once any selection is typed over.
+    fn room(&self, state: &PromptState) -> usize {
+        let selected = state.selection().map_or(0, |s| s.len());
+        self.max_len.map_or(usize::MAX, |max| {
+            (max + selected).saturating_sub(state.len())
+        })
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn width(mut self, width: u16) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Multiline and select prompts get room for several rows.
+    fn tall(&self) -> bool {
+        self.multiline || matches!(self.handler, Handler::Select(..))
+    }
+
+    /// Rows given to the fields: three for an input, and a label over each
+    /// one in a form.
+    fn field_rows(&self, area: Rect) -> u16 {
+        match (&self.handler, self.tall()) {
+            (Handler::Form(_, labels), _) => 4 * labels.len() as u16,
+            (_, true) => area.height.saturating_sub(4),
+            (_, false) => 3,
         }
     }
 
-    pub fn input(&self, k: KeyCode) {
-        let mut state = self.state.write().unwrap();
-        let (cursor, value, error) = &mut *state;
+    /// Where the modal sits, centred in `area`, growing to list completions.
+    pub fn area(&self, area: Rect) -> Rect {
+        let width = self.width.unwrap_or(area.width / 2).min(area.width);
+        let listed = self.state.read_or_recover().completions.len().min(6) as u16;
+        let height = match (&self.handler, self.tall()) {
+            (Handler::Form(..), _) => self.field_rows(area) + 3,
+            (_, true) => 12,
+            (_, false) => 6 + listed,
+        }
+        .min(area.height);
 
-        match k {
-            KeyCode::Backspace => {
-                if *cursor > 0 {
-                    value.remove(*cursor - 1);
-                    *cursor -= 1;
+        Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        }
+    }
+
+    /// Whether `other` is a clone of this same prompt, typed into together.
+    pub fn is(&self, other: &Prompt) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+
+    /// What Ctrl+C last copied, only once, for the model to put on its
+    /// clipboard.
+    pub fn take_copied(&self) -> Option<String> {
+        self.state.write_or_recover().copied.take()
+    }
+
+    /// Whether the prompt is done with: its handler returned `Ok` for what
+    /// was submitted. An `Err` is shown instead and the prompt stays open.
+    pub fn input(&self, k: KeyEvent) -> bool {
+        match &self.handler {
+            Handler::Confirm(handler) => return self.choose(handler, k.code),
+            Handler::Select(handler, options) => return self.pick(handler, options, k.code),
+            Handler::Text(_) | Handler::Form(..) => {}
+        }
+
+        let mut state = self.state.write_or_recover();
+        state.at_limit = false;
+        if let Handler::Form(handler, labels) = &self.handler {
+            let count = labels.len().max(1);
+            match k.code {
+                KeyCode::Tab | KeyCode::Down => {
+                    let to = (state.field + 1) % count;
+                    state.edit_field(to);
+                    return false;
+                }
+                KeyCode::BackTab | KeyCode::Up => {
+                    let to = (state.field + count - 1) % count;
+                    state.edit_field(to);
+                    return false;
+                }
+                KeyCode::Enter => {
+                    let values = labels
+                        .iter()
+                        .enumerate()
+                        .map(|(i, label)| (label.clone(), state.field_value(i).to_string()))
+                        .collect();
+                    return match handler(values) {
+                        Ok(()) => true,
+                        Err(e) => {
+                            state.error = e;
+                            false
+                        }
+                    };
                 }
+                _ => {}
             }
-            KeyCode::Left => {
-                if *cursor > 0 {
-                    *cursor -= 1;
+        }
+        let select = k.modifiers.contains(KeyModifiers::SHIFT);
+        let ctrl = k.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = k.modifiers.contains(KeyModifiers::ALT);
+        let cursor = state.cursor;
+        let before = state.value.clone();
+
+        // Most terminals send Ctrl+Enter as Ctrl+J unless they speak the
+        // kitty keyboard protocol
+        let submit = match k.code {
+            KeyCode::Enter => !self.multiline || ctrl,
+            KeyCode::Char('j') => self.multiline && ctrl,
+            _ => false,
+        };
+
+        match k.code {
+            _ if submit => {
+                self.record(&mut state);
+                if let Handler::Text(handler) = &self.handler {
+                    match handler(state.value.clone()) {
+                        Ok(()) => return true,
+                        Err(e) => state.error = e,
+                    }
                 }
             }
-            KeyCode::Right => {
-                if *cursor < value.len() {
-                    *cursor += 1;
+            KeyCode::Enter if self.multiline && self.room(&state) == 0 => state.at_limit = true,
+            KeyCode::Enter if self.multiline => {
+                state.delete_selection();
+                let at = state.byte(state.cursor);
+                state.value.insert(at, '\n');
+                state.cursor += 1;
+            }
+            KeyCode::Up | KeyCode::Down if self.multiline => {
+                let (row, col) = position(&state.value, cursor);
+                let row = match k.code {
+                    KeyCode::Up => row.saturating_sub(1),
+                    _ => row + 1,
+                };
+                let to = index_at(&state.value, row, col);
+                state.move_to(to, select);
+            }
+            KeyCode::Up | KeyCode::Down => self.recall(&mut state, k.code == KeyCode::Up),
+            KeyCode::Tab => {
+                if let Some(completer) = &self.completer {
+                    let mut found = completer(&state.value);
+                    match found.len() {
+                        1 => state.replace(found.remove(0)),
+                        _ => state.completions = found,
+                    }
                 }
             }
-            KeyCode::Home => {
-                *cursor = 0;
+            KeyCode::Char('c') if ctrl => {
+                // Never leak a secret through the clipboard
+                if let Some(text) = state.selected().filter(|_| !self.secret) {
+                    state.copied = Some(text.to_string());
+                }
             }
-            KeyCode::End => {
-                *cursor = value.len();
+            // Deleting with a selection only removes the selection
+            KeyCode::Backspace | KeyCode::Delete if state.delete_selection() => {}
+            KeyCode::Backspace if cursor > 0 => {
+                let at = state.byte(cursor - 1);
+                state.value.remove(at);
+                state.cursor -= 1;
             }
-            KeyCode::Delete => {
-                value.remove(*cursor);
+            KeyCode::Delete if cursor < state.len() => {
+                let at = state.byte(cursor);
+                state.value.remove(at);
             }
+            KeyCode::Char('w') if ctrl && state.delete_selection() => {}
+            KeyCode::Char('w') if ctrl => {
+                let from = state.word_before(cursor);
+                let range = state.byte(from)..state.byte(cursor);
+                state.value.replace_range(range, "");
+                state.cursor = from;
+            }
+            KeyCode::Left if ctrl => state.move_word(false, select),
+            KeyCode::Right if ctrl => state.move_word(true, select),
+            KeyCode::Char('b') if alt => state.move_word(false, false),
+            KeyCode::Char('f') if alt => state.move_word(true, false),
+            KeyCode::Left => state.move_to(cursor.saturating_sub(1), select),
+            KeyCode::Right => state.move_to(cursor + 1, select),
+            KeyCode::Home => state.move_to(0, select),
+            KeyCode::End => state.move_to(usize::MAX, select),
+            // A `-` only fits at the front, and only once
+            KeyCode::Char(c)
+                if self.numeric
+                    && !c.is_ascii_digit()
+                    && (c != '-' || cursor > 0 || state.value.starts_with('-')) => {}
+            KeyCode::Char(_) if self.room(&state) == 0 => state.at_limit = true,
             KeyCode::Char(c) => {
-                value.insert(*cursor, c);
-                *cursor += 1;
+                state.delete_selection();
+                let at = state.byte(state.cursor);
+                state.value.insert(at, c);
+                state.cursor += 1;
             }
-
-            KeyCode::Enter => match (self.handler.clone())(value.to_string()) {
-                Err(e) => *error = e,
-                _ => {}
-            },
             _ => {}
         }
+
+        if state.value != before {
+            state.completions.clear();
+            self.check(&mut state);
+        }
+        false
+    }
+
+    fn check(&self, state: &mut PromptState) {
+        if let Some(validator) = &self.validator {
+            state.error = validator(&state.value).unwrap_or_default();
+        }
+    }
+
+    /// Inserts `text` at the cursor in one go, over any selection. Single
+    /// line prompts get line breaks as spaces.
+    pub fn paste(&self, text: &str) {
+        if !matches!(self.handler, Handler::Text(_) | Handler::Form(..)) {
+            return;
+        }
+
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let text = match self.multiline {
+            true => text,
+            false => text.trim_end_matches('\n').replace('\n', " "),
+        };
+        let text = match self.numeric {
+            true => text.chars().filter(char::is_ascii_digit).collect(),
+            false => text,
+        };
+        let mut state = self.state.write_or_recover();
+        let room = self.room(&state);
+        state.at_limit = text.chars().count() > room;
+        let text = text.chars().take(room).collect::<String>();
+        state.delete_selection();
+        let at = state.byte(state.cursor);
+        state.value.insert_str(at, &text);
+        state.cursor += text.chars().count();
+        state.completions.clear();
+        self.check(&mut state);
+    }
+
+    fn record(&self, state: &mut PromptState) {
+        state.recalled = None;
+        let Some(history) = self.history.as_ref().filter(|_| !self.secret) else {
+            return;
+        };
+        let mut history = history.write_or_recover();
+        if !state.value.is_empty() && history.last() != Some(&state.value) {
+            history.push(state.value.clone());
+        }
+    }
+
+    /// Steps to the previous (or next) entry. Going past the newest brings
+    /// back what was being typed.
+    fn recall(&self, state: &mut PromptState, back: bool) {
+        let Some(history) = self.history.as_ref().filter(|_| !self.secret) else {
+            return;
+        };
+        let history = history.read_or_recover();
+        let at = state.recalled.as_ref().map_or(history.len(), |(at, _)| *at);
+        let to = match back {
+            true => at.checked_sub(1),
+            false => Some(at + 1).filter(|&to| to <= history.len()),
+        };
+        let Some(to) = to else {
+            return;
+        };
+
+        let draft = match state.recalled.take() {
+            Some((_, draft)) => draft,
+            None => state.value.clone(),
+        };
+        match history.get(to) {
+            Some(entry) => {
+                state.replace(entry.clone());
+                state.recalled = Some((to, draft));
+            }
+            None => state.replace(draft),
+        }
+    }
+
+    fn pick(&self, handler: &SelectHandler, options: &[String], code: KeyCode) -> bool {
+        let mut state = self.state.write_or_recover();
+        let last = options.len().saturating_sub(1);
+        state.choice = match code {
+            KeyCode::Up => state.choice.saturating_sub(1),
+            KeyCode::Down => (state.choice + 1).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            KeyCode::Enter => {
+                let Some(option) = options.get(state.choice) else {
+                    return false;
+                };
+                return match handler(state.choice, option.clone()) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        state.error = e;
+                        false
+                    }
+                };
+            }
+            _ => return false,
+        };
+        false
+    }
+
+    fn choose(&self, handler: &ConfirmHandler, code: KeyCode) -> bool {
+        let mut state = self.state.write_or_recover();
+        let answer = match code {
+            KeyCode::Char('y' | 'Y') => true,
+            KeyCode::Char('n' | 'N') => false,
+            KeyCode::Enter => state.yes,
+            KeyCode::Left | KeyCode::Right | KeyCode::Tab => {
+                state.yes = !state.yes;
+                return false;
+            }
+            _ => return false,
+        };
+
+        match handler(answer) {
+            Ok(()) => true,
+            Err(e) => {
+                state.error = e;
+                false
+            }
+        }
     }
 }
 
@@ -85,14 +662,16 @@ impl Widget for Prompt {
     where
         Self: Sized,
     {
-        let mut state = self.state.read().unwrap();
-        let state = &mut state;
+        let state = self.state.read_or_recover();
 
         Clear.render(area, buf);
         Block::bordered()
             .title(self.title.clone())
             .render(area, buf);
-        let error = state.2.clone();
+        let error = match (state.at_limit, self.max_len) {
+            (true, Some(max)) => format!("At most {max} characters"),
+            _ => state.error.clone(),
+        };
 
         let lay = Layout::new(
             Direction::Vertical,
@@ -102,16 +681,551 @@ impl Widget for Prompt {
                     false => 1,
                     true => 0,
                 }),
-                Constraint::Length(3),
+                Constraint::Length(self.field_rows(area)),
+                Constraint::Length(state.completions.len() as u16),
                 Constraint::Fill(1),
             ],
         )
         .split(area.inner(Margin::new(1, 1)));
 
         if !error.is_empty() {
-            Paragraph::new(error).red().render(lay[1], buf);
+            Paragraph::new(error)
+                .style(self.theme.error)
+                .render(lay[1], buf);
+        }
+
+        if let Handler::Confirm(_) = self.handler {
+            let button = |label: &str, selected: bool| match selected {
+                true => format!("[ {label} ]").black().on_white().bold(),
+                false => format!("  {label}  ").bold(),
+            };
+            let line = Line::from(vec![
+                button("Yes", state.yes),
+                "   ".into(),
+                button("No", !state.yes),
+            ]);
+            let row = Rect {
+                y: lay[2].y + lay[2].height / 2,
+                height: 1,
+                ..lay[2]
+            };
+            return Paragraph::new(line).centered().render(row, buf);
+        }
+
+        if let Handler::Select(_, options) = &self.handler {
+            let rows = lay[2].height as usize;
+            let lines = options
+                .iter()
+                .enumerate()
+                .skip(scroll_offset(state.choice, rows))
+                .take(rows)
+                .map(|(i, option)| match i == state.choice {
+                    true => Line::from(option.as_str()).style(self.theme.selected),
+                    false => Line::from(option.as_str()),
+                })
+                .collect::<Vec<_>>();
+            return Paragraph::new(lines).render(lay[2], buf);
+        }
+
+        if let Handler::Form(_, labels) = &self.handler {
+            let rows = Layout::vertical(labels.iter().map(|_| Constraint::Length(4))).split(lay[2]);
+            for (i, (label, row)) in labels.iter().zip(rows.iter()).enumerate() {
+                let [label_row, input] =
+                    Layout::vertical([Constraint::Length(1), Constraint::Length(3)]).areas(*row);
+                let editing = i == state.field;
+                let (field, border) = match editing {
+                    true => (Input::new(false), self.theme.prompt_border),
+                    false => (Input::new(false).focused(false), self.theme.unselected),
+                };
+                Paragraph::new(match editing {
+                    true => Line::from(label.as_str()).bold(),
+                    false => Line::from(label.as_str()),
+                })
+                .render(label_row, buf);
+                let cursor = match editing {
+                    true => state.cursor,
+                    false => 0,
+                };
+                field
+                    .selection(state.selection().filter(|_| editing))
+                    .border(border)
+                    .render(input, buf, &mut (cursor, state.field_value(i).to_string()));
+            }
+            return;
+        }
+
+        Input::new(self.secret)
+            .selection(state.selection())
+            .max_len(self.max_len)
+            .multiline(self.multiline)
+            .border(self.theme.prompt_border)
+            .render(lay[2], buf, &mut (state.cursor, state.value.clone()));
+
+        let listed = state
+            .completions
+            .iter()
+            .map(|c| Line::from(format!(" {c}")).dark_gray())
+            .collect::<Vec<_>>();
+        Paragraph::new(listed).render(lay[3], buf);
+    }
+}
+
+/// Letters, digits and `_`, what Ctrl+arrows and Ctrl+W treat as a word.
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(prompt: &Prompt, code: KeyCode, modifiers: KeyModifiers) {
+        prompt.input(KeyEvent::new(code, modifiers));
+    }
+
+    fn typed(text: &str) -> Prompt {
+        let prompt = Prompt::new("test", |_| Ok(()));
+        text.chars()
+            .for_each(|c| press(&prompt, KeyCode::Char(c), KeyModifiers::NONE));
+        prompt
+    }
+
+    fn value(prompt: &Prompt) -> String {
+        prompt.state.read_or_recover().value.clone()
+    }
+
+    #[test]
+    fn shift_arrows_select_and_typing_replaces() {
+        let prompt = typed("héllo wörld");
+        press(&prompt, KeyCode::Left, KeyModifiers::SHIFT);
+        press(&prompt, KeyCode::Left, KeyModifiers::SHIFT);
+        press(&prompt, KeyCode::Left, KeyModifiers::SHIFT);
+        assert_eq!(prompt.state.read_or_recover().selection(), Some(8..11));
+        assert_eq!(prompt.state.read_or_recover().selected(), Some("rld"));
+
+        press(&prompt, KeyCode::Char('k'), KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "héllo wök");
+        assert_eq!(prompt.state.read_or_recover().selection(), None);
+    }
+
+    #[test]
+    fn confirm_answers_with_keys_or_buttons() {
+        let answers = Arc::new(RwLock::new(vec![]));
+        let prompt = Prompt::confirm("Kill all jobs?", {
+            let answers = answers.clone();
+            move |yes| {
+                answers.write_or_recover().push(yes);
+                Ok(())
+            }
+        });
+
+        press(&prompt, KeyCode::Char('y'), KeyModifiers::NONE);
+        press(&prompt, KeyCode::Char('n'), KeyModifiers::NONE);
+        press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Left, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Char('x'), KeyModifiers::NONE);
+        assert_eq!(*answers.read_or_recover(), [true, false, false, true]);
+        assert!(value(&prompt).is_empty());
+    }
+
+    #[test]
+    fn closes_once_the_handler_accepts() {
+        let prompt = Prompt::new("Set ENV (KEY=VALUE)", |pair| {
+            pair.split_once('=')
+                .map(|_| ())
+                .ok_or("Expected KEY=VALUE".into())
+        });
+        "PATH".chars().for_each(|c| {
+            assert!(!prompt.input(KeyEvent::from(KeyCode::Char(c))));
+        });
+        assert!(!prompt.input(KeyCode::Enter.into()));
+        assert_eq!(prompt.state.read_or_recover().error, "Expected KEY=VALUE");
+
+        press(&prompt, KeyCode::Char('='), KeyModifiers::NONE);
+        assert!(prompt.input(KeyCode::Enter.into()));
+
+        let confirm = Prompt::confirm("Sure?", |yes| match yes {
+            true => Ok(()),
+            false => Err("Say yes".into()),
+        });
+        assert!(!confirm.input(KeyCode::Left.into()));
+        assert!(!confirm.input(KeyCode::Char('n').into()));
+        assert!(confirm.input(KeyCode::Char('y').into()));
+    }
+
+    #[test]
+    fn max_len_refuses_what_doesnt_fit() {
+        let prompt = Prompt::number("PIN", |_| Ok(())).max_len(4);
+        "123456"
+            .chars()
+            .for_each(|c| press(&prompt, KeyCode::Char(c), KeyModifiers::NONE));
+        assert_eq!(value(&prompt), "1234");
+        assert!(prompt.state.read_or_recover().at_limit);
+
+        press(&prompt, KeyCode::Backspace, KeyModifiers::NONE);
+        assert!(!prompt.state.read_or_recover().at_limit);
+        prompt.paste("98765");
+        assert_eq!(value(&prompt), "1239");
+        assert!(prompt.state.read_or_recover().at_limit);
+
+        // Typing over a selection only swaps chars
+        press(&prompt, KeyCode::Left, KeyModifiers::SHIFT);
+        press(&prompt, KeyCode::Char('0'), KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "1230");
+
+        let area = Rect::new(0, 0, 30, 6);
+        let mut buf = ratatui::buffer::Buffer::empty(area);
+        press(&prompt, KeyCode::Char('1'), KeyModifiers::NONE);
+        prompt.clone().render(area, &mut buf);
+        let rows = (0..area.height)
+            .map(|y| {
+                (0..area.width)
+                    .map(|x| buf[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        assert!(rows.iter().any(|r| r.contains("At most 4 characters")));
+        assert!(rows.iter().any(|r| r.contains("4/4")));
+    }
+
+    #[test]
+    fn form_fields_keep_their_own_values() {
+        let submitted = Arc::new(RwLock::new(None));
+        let prompt = Prompt::form("Connect", &["host", "port"], {
+            let submitted = submitted.clone();
+            move |values| {
+                *submitted.write_or_recover() = Some(values);
+                Ok(())
+            }
+        });
+        "db".chars()
+            .for_each(|c| press(&prompt, KeyCode::Char(c), KeyModifiers::NONE));
+        press(&prompt, KeyCode::Tab, KeyModifiers::NONE);
+        "5432"
+            .chars()
+            .for_each(|c| press(&prompt, KeyCode::Char(c), KeyModifiers::NONE));
+        // Round past the last field back to the first, cursor where it was
+        press(&prompt, KeyCode::Tab, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "d");
+        press(&prompt, KeyCode::BackTab, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "5432");
+
+        assert!(prompt.input(KeyCode::Enter.into()));
+        let values = submitted.read_or_recover().clone().unwrap();
+        assert_eq!(values["host"], "d");
+        assert_eq!(values["port"], "5432");
+    }
+
+    #[test]
+    fn confirm_renders_buttons() {
+        let prompt = Prompt::confirm("Sure?", |_| Ok(()));
+        let area = Rect::new(0, 0, 30, 6);
+        let mut buf = ratatui::buffer::Buffer::empty(area);
+        prompt.clone().render(area, &mut buf);
+
+        let rows = (0..area.height)
+            .map(|y| {
+                (0..area.width)
+                    .map(|x| buf[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        assert!(rows[0].contains("Sure?"));
+        assert!(rows.iter().any(|r| r.contains("Yes     [ No ]")));
+    }
+
+    #[test]
+    fn edits_multibyte_text_by_char() {
+        let prompt = typed("café");
+        press(&prompt, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "caf");
+        press(&prompt, KeyCode::Char('é'), KeyModifiers::NONE);
+        press(&prompt, KeyCode::Left, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Delete, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "caf");
+
+        let prompt = typed("🚀x");
+        press(&prompt, KeyCode::Left, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Char('y'), KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "🚀yx");
+        press(&prompt, KeyCode::Left, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "yx");
+        press(&prompt, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "yx");
+        assert_eq!(prompt.state.read_or_recover().cursor, 0);
+    }
+
+    #[test]
+    fn moves_and_deletes_by_word() {
+        let prompt = typed("cargo run --bin café");
+        let cursor = || prompt.state.read_or_recover().cursor;
+        press(&prompt, KeyCode::Left, KeyModifiers::CONTROL);
+        assert_eq!(cursor(), 16);
+        press(&prompt, KeyCode::Left, KeyModifiers::CONTROL);
+        assert_eq!(cursor(), 12);
+        press(&prompt, KeyCode::Char('b'), KeyModifiers::ALT);
+        assert_eq!(cursor(), 6);
+        press(&prompt, KeyCode::Char('f'), KeyModifiers::ALT);
+        assert_eq!(cursor(), 9);
+        press(&prompt, KeyCode::Right, KeyModifiers::CONTROL);
+        assert_eq!(cursor(), 15);
+
+        press(&prompt, KeyCode::End, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(value(&prompt), "cargo run --bin ");
+        press(&prompt, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(value(&prompt), "cargo run --");
+        press(&prompt, KeyCode::Home, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(value(&prompt), "cargo run --");
+    }
+
+    #[test]
+    fn tab_completes_one_match_and_lists_several() {
+        let prompt = typed("ca").completer(|value| {
+            ["cargo build", "cargo run", "cat"]
+                .into_iter()
+                .filter(|c| c.starts_with(value))
+                .map(str::to_string)
+                .collect()
+        });
+        let completions = || prompt.state.read_or_recover().completions.clone();
+
+        press(&prompt, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "ca");
+        assert_eq!(completions(), ["cargo build", "cargo run", "cat"]);
+        assert_eq!(prompt.area(Rect::new(0, 0, 80, 24)).height, 9);
+
+        let mut buf = ratatui::buffer::Buffer::empty(Rect::new(0, 0, 40, 9));
+        prompt.clone().render(buf.area, &mut buf);
+        let rows = (0..9)
+            .map(|y| (0..40).map(|x| buf[(x, y)].symbol()).collect::<String>())
+            .collect::<Vec<_>>();
+        assert!(
+            rows.iter().any(|row| row.contains(" cargo run ")),
+            "{rows:#?}"
+        );
+
+        "rgo r"
+            .chars()
+            .for_each(|c| press(&prompt, KeyCode::Char(c), KeyModifiers::NONE));
+        assert!(completions().is_empty());
+        press(&prompt, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "cargo run");
+        assert_eq!(prompt.state.read_or_recover().cursor, 9);
+    }
+
+    #[test]
+    fn backspace_deletes_selection() {
+        let prompt = typed("🚀 launch");
+        press(&prompt, KeyCode::Home, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Right, KeyModifiers::SHIFT);
+        press(&prompt, KeyCode::Right, KeyModifiers::SHIFT);
+        press(&prompt, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "launch");
+        assert_eq!(prompt.state.read_or_recover().cursor, 0);
+    }
+
+    #[test]
+    fn multiline_enter_inserts_newline_until_ctrl_enter() {
+        let submitted = Arc::new(RwLock::new(None));
+        let prompt = Prompt::new("test", {
+            let submitted = submitted.clone();
+            move |v| {
+                *submitted.write_or_recover() = Some(v);
+                Ok(())
+            }
+        })
+        .multiline();
+
+        for code in [
+            KeyCode::Char('a'),
+            KeyCode::Char('b'),
+            KeyCode::Enter,
+            KeyCode::Char('c'),
+            KeyCode::Up,
+            KeyCode::Char('x'),
+        ] {
+            press(&prompt, code, KeyModifiers::NONE);
         }
+        assert_eq!(value(&prompt), "axb\nc");
+        assert!(submitted.read_or_recover().is_none());
+
+        press(&prompt, KeyCode::Enter, KeyModifiers::CONTROL);
+        assert_eq!(submitted.read_or_recover().as_deref(), Some("axb\nc"));
+    }
+
+    #[test]
+    fn modal_is_centred() {
+        let area = Rect::new(0, 0, 80, 24);
+        assert_eq!(typed("").area(area), Rect::new(20, 9, 40, 6));
+        assert_eq!(
+            typed("").multiline().width(60).area(area),
+            Rect::new(10, 6, 60, 12)
+        );
+    }
+
+    #[test]
+    fn plain_arrows_clear_selection() {
+        let prompt = typed("abc");
+        press(&prompt, KeyCode::Home, KeyModifiers::SHIFT);
+        press(&prompt, KeyCode::Right, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Delete, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "ac");
+    }
+
+    #[test]
+    fn up_and_down_walk_the_history() {
+        let history = History::default();
+        let prompt = Prompt::new("test", |_| Ok(())).history(history.clone());
+        for entry in ["cargo build", "cargo test"] {
+            entry
+                .chars()
+                .for_each(|c| press(&prompt, KeyCode::Char(c), KeyModifiers::NONE));
+            press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+            prompt.state.write_or_recover().replace(String::new());
+        }
+        assert_eq!(*history.read_or_recover(), ["cargo build", "cargo test"]);
+
+        let prompt = typed("car").history(history.clone());
+        press(&prompt, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "cargo test");
+        press(&prompt, KeyCode::Up, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "cargo build");
+        assert_eq!(prompt.state.read_or_recover().cursor, 11);
+
+        press(&prompt, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "cargo test");
+        press(&prompt, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "car");
+    }
+
+    #[test]
+    fn secrets_stay_out_of_the_history() {
+        let history = History::default();
+        let prompt = Prompt::secret("pin", |_| Ok(())).history(history.clone());
+        press(&prompt, KeyCode::Char('1'), KeyModifiers::NONE);
+        press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(history.read_or_recover().is_empty());
+    }
+
+    #[test]
+    fn pastes_at_the_cursor_in_one_go() {
+        let prompt = typed("héllo");
+        press(&prompt, KeyCode::Left, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Left, KeyModifiers::NONE);
+        prompt.paste("ünï\r\ncode\n");
+        assert_eq!(value(&prompt), "hélünï codelo");
+        assert_eq!(prompt.state.read_or_recover().cursor, 11);
+
+        let prompt = Prompt::new("test", |_| Ok(())).multiline();
+        prompt.paste("a\nb");
+        assert_eq!(value(&prompt), "a\nb");
+    }
+
+    #[test]
+    fn select_scrolls_to_the_choice_and_passes_it_on() {
+        let picked = Arc::new(RwLock::new(None));
+        let options = (0..20).map(|i| format!("branch-{i}")).collect();
+        let prompt = Prompt::select("Branch", options, {
+            let picked = picked.clone();
+            move |i, option| {
+                *picked.write_or_recover() = Some((i, option));
+                Ok(())
+            }
+        });
+
+        for _ in 0..12 {
+            press(&prompt, KeyCode::Down, KeyModifiers::NONE);
+        }
+        press(&prompt, KeyCode::Up, KeyModifiers::NONE);
+
+        let area = Rect::new(0, 0, 40, 12);
+        let mut buf = ratatui::buffer::Buffer::empty(area);
+        prompt.clone().render(area, &mut buf);
+        // 8 rows of options, scrolled so the 12th is on the last one
+        let text = |y: u16| (1..39).map(|x| buf[(x, y)].symbol()).collect::<String>();
+        assert_eq!(text(9).trim_end(), "branch-11");
+        assert_eq!(text(2).trim_end(), "branch-4");
+
+        press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(
+            *picked.read_or_recover(),
+            Some((11, "branch-11".to_string()))
+        );
+    }
+
+    #[test]
+    fn validates_while_typing() {
+        let submitted = Arc::new(RwLock::new(false));
+        let prompt = Prompt::new("Retries", {
+            let submitted = submitted.clone();
+            move |_| {
+                *submitted.write_or_recover() = true;
+                Ok(())
+            }
+        })
+        .validate(|v| v.parse::<u32>().err().map(|_| "Expected a number".into()));
+        let error = || prompt.state.read_or_recover().error.clone();
+
+        press(&prompt, KeyCode::Char('3'), KeyModifiers::NONE);
+        assert_eq!(error(), "");
+        press(&prompt, KeyCode::Char('x'), KeyModifiers::NONE);
+        assert_eq!(error(), "Expected a number");
+        press(&prompt, KeyCode::Char('y'), KeyModifiers::NONE);
+        assert_eq!(value(&prompt), "3xy");
+
+        // Moving around leaves the message alone
+        press(&prompt, KeyCode::Left, KeyModifiers::NONE);
+        assert_eq!(error(), "Expected a number");
+        press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(*submitted.read_or_recover());
+
+        press(&prompt, KeyCode::End, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Backspace, KeyModifiers::NONE);
+        press(&prompt, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(error(), "");
+    }
+
+    #[test]
+    fn number_takes_digits_and_parses_on_submit() {
+        let numbers = Arc::new(RwLock::new(vec![]));
+        let prompt = Prompt::number("Port", {
+            let numbers = numbers.clone();
+            move |n| {
+                numbers.write_or_recover().push(n);
+                Ok(())
+            }
+        });
+        let error = || prompt.state.read_or_recover().error.clone();
+        let set = |text: &str| {
+            prompt.state.write_or_recover().replace(String::new());
+            text.chars()
+                .for_each(|c| press(&prompt, KeyCode::Char(c), KeyModifiers::NONE));
+        };
+
+        set("8a0-8.0");
+        assert_eq!(value(&prompt), "8080");
+        press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+
+        set("--12");
+        assert_eq!(value(&prompt), "-12");
+        press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(*numbers.read_or_recover(), [8080, -12]);
+
+        prompt.paste("1 000\n");
+        assert_eq!(value(&prompt), "-121000");
 
-        Input::new(self.secret).render(lay[2], buf, &mut (state.0, state.1.clone()));
+        set("99999999999999999999");
+        press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(error().starts_with("Must be between"), "{}", error());
+        set("");
+        press(&prompt, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(error(), "Enter a number");
+        assert_eq!(numbers.read_or_recover().len(), 2);
     }
 }